@@ -1,10 +1,15 @@
 // src-tauri/src/commands.rs
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::{HashMap, HashSet},
     fs,
     fs::File,
-    io::{self, Read, Write},
+    io::{self, BufRead, Read, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
 };
 
 use zip::{ZipArchive, ZipWriter};
@@ -15,12 +20,16 @@ use tar::Archive as TarArchive;
 use flate2::read::GzDecoder;
 use bzip2::read::BzDecoder;
 use xz2::read::XzDecoder;
+use sevenz_rust::{Password, SevenZArchiveEntry, SevenZReader};
 
 use base64::engine::general_purpose::STANDARD as BASE64;
 use base64::Engine;
 
+use tauri::{AppHandle, Emitter, Manager, State};
+
 /// Shape that matches the frontend `CapsuleEntry` type.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CapsuleEntry {
     pub name: String,
     pub size: u64,
@@ -28,16 +37,32 @@ pub struct CapsuleEntry {
     pub kind: String,
     pub path: String,
     pub modified: Option<String>,
+    /// ZIP only; tar has no per-entry checksum.
+    pub crc32: Option<u32>,
+    /// ZIP only: stored size, for showing compression ratios.
+    pub compressed_size: Option<u64>,
+    /// ZIP only: "store", "deflate", "bzip2", "zstd", ...
+    pub method: Option<String>,
+    /// The stored name wasn't UTF-8 and was decoded with the fallback `NameEncoding`, so it
+    /// may not read as intended.
+    #[serde(default)]
+    pub name_lossy: bool,
 }
 
 /// Helper: validate that a path is within the destination directory (zip-slip protection).
-pub(crate) fn validate_extract_path(dest: &Path, entry_path: &Path) -> Result<PathBuf, String> {
+pub(crate) fn validate_extract_path(
+    dest: &Path,
+    entry_path: &Path,
+) -> Result<PathBuf, CapsuleError> {
     // Normalize path by resolving `..` and `.` components manually
     let mut parts = Vec::new();
     for component in entry_path.components() {
         match component {
             std::path::Component::Prefix(_) | std::path::Component::RootDir => {
-                return Err(format!("Absolute paths not allowed: {}", entry_path.display()));
+                return Err(CapsuleError::Traversal(format!(
+                    "Absolute paths not allowed: {}",
+                    entry_path.display()
+                )));
             }
             std::path::Component::CurDir => {
                 // Skip `.` components
@@ -45,10 +70,19 @@ pub(crate) fn validate_extract_path(dest: &Path, entry_path: &Path) -> Result<Pa
             std::path::Component::ParentDir => {
                 // Prevent going up beyond dest - remove last component if possible
                 if parts.pop().is_none() {
-                    return Err(format!("Path traversal detected: {} escapes destination", entry_path.display()));
+                    return Err(CapsuleError::Traversal(format!(
+                        "Path traversal detected: {} escapes destination",
+                        entry_path.display()
+                    )));
                 }
             }
             std::path::Component::Normal(name) => {
+                if is_windows_prefix(name) {
+                    return Err(CapsuleError::Traversal(format!(
+                        "Drive and device paths not allowed: {}",
+                        entry_path.display()
+                    )));
+                }
                 parts.push(name);
             }
         }
@@ -57,16 +91,54 @@ pub(crate) fn validate_extract_path(dest: &Path, entry_path: &Path) -> Result<Pa
     let normalized = parts.iter().collect::<PathBuf>();
     let full_path = dest.join(&normalized);
     
-    // Final verification: ensure canonicalized path (if it exists) is within dest
-    if let (Ok(dest_canonical), Ok(full_canonical)) = (dest.canonicalize(), full_path.canonicalize()) {
-        if !full_canonical.starts_with(&dest_canonical) {
-            return Err(format!("Path traversal detected: {} escapes destination", entry_path.display()));
+    // Final verification: the deepest part of the path that already exists must resolve inside
+    // dest. An earlier entry (a tar symlink) may have planted a link, so this runs for each
+    // entry right before it is written; dangling links fail closed.
+    if let Ok(dest_canonical) = dest.canonicalize() {
+        let mut existing = Some(full_path.as_path());
+        while let Some(candidate) = existing {
+            if candidate.symlink_metadata().is_ok() {
+                let inside = candidate
+                    .canonicalize()
+                    .is_ok_and(|resolved| resolved.starts_with(&dest_canonical));
+                if !inside {
+                    return Err(CapsuleError::Traversal(format!(
+                        "Path traversal detected: {} escapes destination through a symlink",
+                        entry_path.display()
+                    )));
+                }
+                break;
+            }
+            existing = candidate.parent();
         }
     }
     
     Ok(full_path)
 }
 
+/// Helper: whether a path component is, or contains, a Windows drive (`C:`, including the
+/// drive-relative `C:foo`) or a `\\?\` / `\\.\` device or UNC prefix. Off Windows these
+/// parse as ordinary names, so each backslash-separated piece is checked; the same archive
+/// must be rejected on every platform.
+fn is_windows_prefix(component: &std::ffi::OsStr) -> bool {
+    let component = component.to_string_lossy();
+    component.starts_with("\\\\")
+        || component.split('\\').any(|piece| {
+            let bytes = piece.as_bytes();
+            bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+        })
+}
+
+/// Helper: validate an entry name for writing into an archive, returning it with `/` separators.
+pub(crate) fn validate_entry_name(name: &str) -> Result<String, CapsuleError> {
+    let normalized = validate_extract_path(Path::new(""), Path::new(name))?;
+    let name = normalized.to_string_lossy().replace('\\', "/");
+    if name.is_empty() {
+        return Err(CapsuleError::Other("Entry name must not be empty".into()));
+    }
+    Ok(name)
+}
+
 /// Helper: detect archive type from extension.
 pub(crate) fn detect_archive_type(path: &Path) -> &'static str {
     let s = path.to_string_lossy().to_lowercase();
@@ -81,641 +153,11220 @@ pub(crate) fn detect_archive_type(path: &Path) -> &'static str {
         "tar.bz2"
     } else if s.ends_with(".tar.xz") || s.ends_with(".txz") {
         "tar.xz"
+    } else if s.ends_with(".tar.zst") || s.ends_with(".tzst") {
+        "tar.zst"
+    } else if s.ends_with(".7z") {
+        "7z"
+    } else if s.ends_with(".rar") {
+        "rar"
+    } else if s.ends_with(".gz") {
+        "gz"
+    } else if s.ends_with(".bz2") {
+        "bz2"
+    } else if s.ends_with(".xz") {
+        "xz"
+    } else if s.ends_with(".zst") {
+        "zst"
     } else {
         "unknown"
     }
 }
 
-/// Open a ZIP archive and list entries.
-fn open_zip(path: &Path) -> Result<Vec<CapsuleEntry>, String> {
-    let file = File::open(path).map_err(|e| format!("Failed to open zip: {e}"))?;
-    let mut archive = ZipArchive::new(file).map_err(|e| format!("Invalid zip archive: {e}"))?;
+/// Helper: days since 1970-01-01 for a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
 
-    let mut entries = Vec::new();
-    for i in 0..archive.len() {
-        let entry = archive.by_index(i).map_err(|e| format!("Zip entry error: {e}"))?;
-        let name = entry.name().to_string();
-        let size = entry.size();
-        let kind = if entry.is_dir() { "dir" } else { "file" }.to_string();
-        let path_str = entry.name().to_string();
+/// Helper: format seconds since the Unix epoch as an RFC 3339 UTC timestamp,
+/// e.g. `2024-12-31T23:59:58Z`.
+pub(crate) fn format_epoch(secs: i64) -> String {
+    let days = secs.div_euclid(86_400);
+    let rem = secs.rem_euclid(86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
 
-        entries.push(CapsuleEntry {
-            name,
-            size,
-            kind,
-            path: path_str,
-            modified: None,
-        });
+/// Last second that still formats as a four-digit RFC 3339 year (9999-12-31T23:59:59Z).
+const MAX_RFC3339_SECS: i64 = 253_402_300_799;
+
+/// Helper: a tar header's mtime for `CapsuleEntry::modified`, or `None` when the field is
+/// unreadable or too far out to format.
+fn tar_modified(header: &tar::Header) -> Option<String> {
+    let secs = i64::try_from(header.mtime().ok()?).ok()?;
+    (secs <= MAX_RFC3339_SECS).then(|| format_epoch(secs))
+}
+
+/// Helper: `CapsuleEntry::kind` for a tar header: "dir", "symlink" or "file".
+fn tar_kind(header: &tar::Header) -> &'static str {
+    let entry_type = header.entry_type();
+    if entry_type.is_dir() {
+        "dir"
+    } else if entry_type.is_symlink() {
+        "symlink"
+    } else {
+        "file"
     }
+}
 
-    Ok(entries)
+/// Helper: a zip entry's DOS timestamp as Unix seconds. DOS times carry no timezone, so
+/// they are read as UTC.
+fn zip_mtime_secs(time: zip::DateTime) -> i64 {
+    let days = days_from_civil(time.year().into(), time.month().into(), time.day().into());
+    days * 86_400
+        + i64::from(time.hour()) * 3600
+        + i64::from(time.minute()) * 60
+        + i64::from(time.second())
+}
+
+/// Helper: a zip entry's timestamp for `CapsuleEntry::modified`, or `None` when the DOS
+/// date/time fields are out of range (zeroed or garbage headers).
+fn zip_modified(time: zip::DateTime) -> Option<String> {
+    let valid = (1..=12).contains(&time.month())
+        && (1..=31).contains(&time.day())
+        && time.hour() < 24
+        && time.minute() < 60
+        && time.second() < 60;
+    valid.then(|| format_epoch(zip_mtime_secs(time)))
+}
+
+/// Helper: name for a zip entry's compression method, in `compression_mode` terms.
+fn zip_method_name(method: CompressionMethod) -> String {
+    match method {
+        CompressionMethod::Stored => "store".into(),
+        CompressionMethod::Deflated => "deflate".into(),
+        CompressionMethod::Bzip2 => "bzip2".into(),
+        CompressionMethod::Zstd => "zstd".into(),
+        other => format!("{other:?}").to_lowercase(),
+    }
+}
+
+/// Fallback for entry names that aren't valid UTF-8.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) enum NameEncoding {
+    /// IBM PC codepage, what legacy zip tools on Windows and DOS wrote.
+    #[default]
+    Cp437,
+    /// ISO-8859-1, common in tarballs from older Unix systems.
+    Latin1,
+}
+
+impl NameEncoding {
+    fn parse(name: Option<&str>) -> Result<Self, CapsuleError> {
+        match name.map(str::to_lowercase).as_deref() {
+            None | Some("cp437") => Ok(Self::Cp437),
+            Some("latin1" | "iso-8859-1") => Ok(Self::Latin1),
+            Some(other) => Err(CapsuleError::Unsupported(format!(
+                "Unsupported name encoding: {other}"
+            ))),
+        }
+    }
+}
+
+/// Upper half (0x80..=0xFF) of codepage 437; the lower half is ASCII.
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»\
+░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+
+/// Helper: decode a stored entry name, as UTF-8 when valid and with `fallback` otherwise.
+/// The flag is set when the fallback was needed.
+pub(crate) fn decode_entry_name(raw: &[u8], fallback: NameEncoding) -> (String, bool) {
+    if let Ok(name) = std::str::from_utf8(raw) {
+        return (name.to_string(), false);
+    }
+    let name = raw
+        .iter()
+        .map(|&b| match (b, fallback) {
+            (0..=0x7f, _) | (_, NameEncoding::Latin1) => b as char,
+            (_, NameEncoding::Cp437) => CP437_HIGH
+                .chars()
+                .nth(usize::from(b - 0x80))
+                .unwrap_or(char::REPLACEMENT_CHARACTER),
+        })
+        .collect();
+    (name, true)
+}
+
+/// Open a ZIP archive and list entries.
+fn open_zip(path: &Path, encoding: NameEncoding) -> Result<Vec<CapsuleEntry>, CapsuleError> {
+    let file = File::open(path).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+    zip_listing(&mut archive, encoding)
+}
+
+/// Helper: listing rows for every entry of an open zip.
+fn zip_listing<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    encoding: NameEncoding,
+) -> Result<Vec<CapsuleEntry>, CapsuleError> {
+    (0..archive.len())
+        .map(|i| zip_capsule_entry(archive, i, encoding))
+        .collect()
+}
+
+/// Helper: the listing row for zip entry `index`.
+fn zip_capsule_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    index: usize,
+    encoding: NameEncoding,
+) -> Result<CapsuleEntry, CapsuleError> {
+    // Raw access reads the metadata without needing the password of encrypted entries.
+    let entry = archive
+        .by_index_raw(index)
+        .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+    let (name, name_lossy) = decode_entry_name(entry.name_raw(), encoding);
+    let size = entry.size();
+    let kind = if entry.is_dir() { "dir" } else { "file" }.to_string();
+    let path_str = name.clone();
+    let modified = zip_modified(entry.last_modified());
+    let crc32 = Some(entry.crc32());
+    let compressed_size = Some(entry.compressed_size());
+    let method = Some(zip_method_name(entry.compression()));
+
+    Ok(CapsuleEntry {
+        name,
+        size,
+        kind,
+        path: path_str,
+        modified,
+        crc32,
+        compressed_size,
+        method,
+        name_lossy,
+    })
+}
+
+/// Helper: a zip entry's name as listings show it. The zip crate's own `name()` decodes every
+/// name without the UTF-8 flag as CP437, so an unflagged UTF-8 name would list as one string
+/// and be looked up as another; matching and extraction go through this instead.
+fn zip_entry_name(entry: &zip::read::ZipFile) -> String {
+    decode_entry_name(entry.name_raw(), NameEncoding::default()).0
+}
+
+/// Helper: index of the zip entry named `name` (as `zip_entry_name` decodes it), the last
+/// one when the name repeats.
+fn zip_entry_index<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<Option<usize>, CapsuleError> {
+    Ok(zip_entry_names(archive)?.iter().rposition(|n| n == name))
+}
+
+/// Helper: a tar entry's full path and final component, decoded from the raw header bytes
+/// (long names included), plus whether the fallback encoding was needed.
+fn tar_entry_name<R: Read>(
+    entry: &tar::Entry<R>,
+    encoding: NameEncoding,
+) -> (String, String, bool) {
+    let (path, lossy) = decode_entry_name(&entry.path_bytes(), encoding);
+    let name = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or("")
+        .to_string();
+    (path, name, lossy)
 }
 
 /// Open a TAR-like archive and list entries.
-fn open_tar_like<R: Read>(mut archive: TarArchive<R>) -> Result<Vec<CapsuleEntry>, String> {
+fn open_tar_like<R: Read>(
+    mut archive: TarArchive<R>,
+    encoding: NameEncoding,
+) -> Result<Vec<CapsuleEntry>, CapsuleError> {
     let mut entries = Vec::new();
 
     let tar_entries = archive
         .entries()
-        .map_err(|e| format!("Failed to read tar entries: {e}"))?;
+        .map_err(|e| CapsuleError::InvalidArchive(format!("Failed to read tar entries: {e}")))?;
 
     for entry_res in tar_entries {
-        let entry = entry_res.map_err(|e| format!("Tar entry error: {e}"))?;
+        let entry =
+            entry_res.map_err(|e| CapsuleError::InvalidArchive(format!("Tar entry error: {e}")))?;
         let size = entry.size();
-        let path = entry
-            .path()
-            .map_err(|e| format!("Tar path error: {e}"))?
-            .to_path_buf();
-        let path_str = path.to_string_lossy().to_string();
-        let name = path
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_string();
+        let (path_str, name, name_lossy) = tar_entry_name(&entry, encoding);
+        let modified = tar_modified(entry.header());
 
         entries.push(CapsuleEntry {
             name,
             size,
-            kind: "file".to_string(),
+            kind: tar_kind(entry.header()).to_string(),
             path: path_str,
-            modified: None,
+            modified,
+            crc32: None,
+            compressed_size: None,
+            method: None,
+            name_lossy,
         });
     }
 
     Ok(entries)
 }
 
-/// Extract a ZIP archive to dest.
-fn extract_zip(path: &Path, dest: &Path) -> Result<(), String> {
-    let file = File::open(path).map_err(|e| format!("Failed to open zip: {e}"))?;
-    let mut archive = ZipArchive::new(file).map_err(|e| format!("Invalid zip archive: {e}"))?;
-
-    fs::create_dir_all(dest).map_err(|e| format!("Failed to create dest dir: {e}"))?;
+/// Helper: a 7z timestamp (100ns ticks since 1601-01-01) as Unix seconds.
+fn sevenz_mtime_secs(time: sevenz_rust::nt_time::FileTime) -> i64 {
+    (time.to_raw() / 10_000_000) as i64 - 11_644_473_600
+}
 
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i).map_err(|e| format!("Zip entry error: {e}"))?;
-        let entry_name = file.name();
-        let entry_path = PathBuf::from(entry_name);
-        let outpath = validate_extract_path(dest, &entry_path)?;
+/// Open a 7z archive and list entries.
+fn open_7z(path: &Path) -> Result<Vec<CapsuleEntry>, CapsuleError> {
+    let reader = SevenZReader::open(path, Password::empty())
+        .map_err(|e| CapsuleError::InvalidArchive(format!("Invalid 7z archive: {e}")))?;
 
-        if file.is_dir() {
-            fs::create_dir_all(&outpath).map_err(|e| format!("Dir create error: {e}"))?;
-        } else {
-            if let Some(parent) = outpath.parent() {
-                fs::create_dir_all(parent)
-                    .map_err(|e| format!("Parent dir create error: {e}"))?;
+    let entries = reader
+        .archive()
+        .files
+        .iter()
+        .map(|entry| {
+            let path_str = entry.name().replace('\\', "/");
+            let name = path_str
+                .trim_end_matches('/')
+                .rsplit('/')
+                .next()
+                .unwrap_or("")
+                .to_string();
+            let kind = if entry.is_directory() { "dir" } else { "file" }.to_string();
+            let modified = entry
+                .has_last_modified_date
+                .then(|| format_epoch(sevenz_mtime_secs(entry.last_modified_date())));
+            CapsuleEntry {
+                name,
+                size: entry.size(),
+                kind,
+                path: path_str,
+                modified,
+                crc32: None,
+                compressed_size: None,
+                method: None,
+                name_lossy: false,
             }
-            let mut outfile = File::create(&outpath)
-                .map_err(|e| format!("File create error: {e}"))?;
-            io::copy(&mut file, &mut outfile)
-                .map_err(|e| format!("Copy error: {e}"))?;
-        }
-    }
+        })
+        .collect();
 
-    Ok(())
+    Ok(entries)
 }
 
-/// Extract a TAR-like archive to dest.
-fn extract_tar_like<R: Read>(mut archive: TarArchive<R>, dest: &Path) -> Result<(), String> {
-    fs::create_dir_all(dest).map_err(|e| format!("Failed to create dest dir: {e}"))?;
+/// Error returned for RAR sets split over several volumes.
+const RAR_MULTIVOLUME: &str = "Multi-volume RAR archives are not supported";
 
-    let entries = archive
-        .entries()
-        .map_err(|e| format!("Failed to read tar entries: {e}"))?;
+/// Helper: convert an unrar error, reporting password problems as `PASSWORD_REQUIRED` /
+/// `INVALID_PASSWORD` like zip does. Failures to open, read or write files are `Io`; the
+/// rest mean a damaged or unrecognized archive.
+fn rar_error(e: unrar::error::UnrarError, context: &str) -> CapsuleError {
+    use unrar::error::Code;
 
-    for entry_res in entries {
-        let mut entry = entry_res.map_err(|e| format!("Tar entry error: {e}"))?;
-        let path = entry.path().map_err(|e| format!("Tar path error: {e}"))?;
-        let outpath = validate_extract_path(dest, &path)?;
-        if let Some(parent) = outpath.parent() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Parent dir create error: {e}"))?;
+    match e.code {
+        Code::MissingPassword => CapsuleError::PasswordRequired(PASSWORD_REQUIRED.into()),
+        Code::BadPassword => CapsuleError::InvalidPassword(INVALID_PASSWORD.into()),
+        Code::EOpen | Code::ECreate | Code::EClose | Code::ERead | Code::EWrite => {
+            CapsuleError::Io(format!("{context}: {e}"))
         }
-        entry
-            .unpack(&outpath)
-            .map_err(|e| format!("Tar unpack error: {e}"))?;
+        _ => CapsuleError::InvalidArchive(format!("{context}: {e}")),
     }
-
-    Ok(())
 }
 
-/// Recursively add a file or directory to a ZipWriter.
-fn add_path_to_zip<W: Write + io::Seek>(
-    writer: &mut ZipWriter<W>,
-    path: &Path,
-    base: &Path,
-) -> Result<(), String> {
-    let rel = path
-        .strip_prefix(base)
-        .unwrap_or(path)
-        .to_string_lossy()
-        .replace('\\', "/");
+/// Open a RAR archive and list entries. RAR is read-only in Capsule.
+fn open_rar(path: &Path) -> Result<Vec<CapsuleEntry>, CapsuleError> {
+    let listing = unrar::Archive::new(path)
+        .open_for_listing()
+        .map_err(|e| rar_error(e, "Invalid rar archive"))?;
+    if listing.volume_info() != unrar::VolumeInfo::None {
+        return Err(CapsuleError::Unsupported(RAR_MULTIVOLUME.into()));
+    }
 
-    if path.is_dir() {
-        let name = if rel.ends_with('/') { rel } else { format!("{rel}/") };
-        writer
-            .add_directory(
-                &name,
-                FileOptions::default()
-                    .compression_method(CompressionMethod::Deflated)
-                    .unix_permissions(0o755),
-            )
-            .map_err(|e| format!("Zip add dir error: {e}"))?;
-        for entry in fs::read_dir(path).map_err(|e| format!("Read dir error: {e}"))? {
-            let entry = entry.map_err(|e| format!("Dir entry error: {e}"))?;
-            add_path_to_zip(writer, &entry.path(), base)?;
+    let mut entries = Vec::new();
+    for header in listing {
+        let header = header.map_err(|e| rar_error(e, "Rar entry error"))?;
+        if header.is_split() {
+            return Err(CapsuleError::Unsupported(RAR_MULTIVOLUME.into()));
         }
-    } else {
-        let mut file = File::open(path).map_err(|e| format!("Open file error: {e}"))?;
-        writer
-            .start_file(
-                &rel,
-                FileOptions::default()
-                    .compression_method(CompressionMethod::Deflated)
-                    .unix_permissions(0o644),
-            )
-            .map_err(|e| format!("Zip start file error: {e}"))?;
-        io::copy(&mut file, writer).map_err(|e| format!("Zip file copy error: {e}"))?;
+        let path_str = header.filename.to_string_lossy().replace('\\', "/");
+        let name = path_str.rsplit('/').next().unwrap_or("").to_string();
+        let kind = if header.is_directory() { "dir" } else { "file" }.to_string();
+        let time = header.file_time;
+        let modified = zip_modified(zip::DateTime::from_msdos((time >> 16) as u16, time as u16));
+        entries.push(CapsuleEntry {
+            name,
+            size: header.unpacked_size,
+            kind,
+            path: path_str,
+            modified,
+            crc32: Some(header.file_crc),
+            compressed_size: None,
+            method: None,
+            name_lossy: false,
+        });
     }
 
-    Ok(())
+    Ok(entries)
 }
 
-/// Open an archive and list entries for the UI.
-#[tauri::command]
-pub async fn open_archive(path: String) -> Result<Vec<CapsuleEntry>, String> {
-    let path_buf = PathBuf::from(&path);
-    let kind = detect_archive_type(&path_buf);
+/// Optional knobs for `extract_archive`; leaving a field unset keeps the default behavior.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractOptions {
+    /// ZIP only: "first" or "last" to extract a single occurrence of duplicate-named entries.
+    pub dedupe_by_name: Option<String>,
+    /// Follow-up to run once extraction succeeds.
+    pub on_complete: Option<PostExtractAction>,
+    /// "rename" | "skip" | "error" for files whose paths differ only by case.
+    pub case_collision_policy: Option<String>,
+    /// "overwrite" (default) | "skip" | "rename" for files that already exist in dest.
+    /// "rename" writes `name (1).ext` and so on instead.
+    pub overwrite: Option<String>,
+    /// Extract into a new folder named after the archive ("report" for report.tar.gz),
+    /// unless everything in it already sits under a single top-level directory.
+    pub extract_to_subfolder: Option<bool>,
+    /// Drop directory structure: every file lands directly in dest under its base name,
+    /// numbered (`name (1).ext`) when two share one. Directory entries are skipped.
+    pub flatten: Option<bool>,
+    /// ZIP only: worker threads writing entries; 1 extracts sequentially. Defaults to
+    /// `default_extract_concurrency()`.
+    pub concurrency: Option<usize>,
+    /// ZIP only: probe throughput on the first entries and fall back to sequential
+    /// extraction when extra workers don't help (see `extract_zip`).
+    pub adaptive_concurrency: Option<bool>,
+    /// Recovery mode: skip entries that look damaged (zero CRC on a non-empty file, unreadable
+    /// headers) and keep going past entries that fail, listing them in `failed`.
+    pub best_effort: Option<bool>,
+    /// Abort (removing the files written so far) once free space on the destination drops
+    /// below this many bytes. Checked every `SPACE_CHECK_EVERY_ENTRIES` entries or
+    /// `SPACE_CHECK_EVERY_BYTES` bytes.
+    pub min_free_bytes: Option<u64>,
+    /// Called with the free byte count just before a low-space abort; set by `extract_archive`.
+    #[serde(skip)]
+    pub low_space_notifier: Option<LowSpaceNotifier>,
+    /// Raised by `cancel_extraction`; checked between entries. Set by `extract_archive`.
+    #[serde(skip)]
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Called after each entry; set by `extract_archive` to emit `extract://progress`.
+    #[serde(skip)]
+    pub progress: Option<ProgressNotifier>,
+    /// ZIP only: password for encrypted entries; set from `extract_archive`'s `password`.
+    #[serde(skip)]
+    pub password: Option<String>,
+    /// Tar only: keep the holes of GNU sparse entries (default). `false` writes them out in
+    /// full, zeros included, for filesystems without sparse file support.
+    pub preserve_sparse: Option<bool>,
+    /// Tar only: keep the full stored mode, setuid/setgid/sticky bits included (default
+    /// false: only the rwx bits, filtered by the umask).
+    pub preserve_permissions: Option<bool>,
+    /// Tar only: set each file's modification time from the header (default true).
+    pub preserve_mtime: Option<bool>,
+    /// Tar only: restore extended attributes stored in the archive (default false; Unix).
+    pub unpack_xattrs: Option<bool>,
+    /// Tar only: restore the stored uid/gid (default false). Needs root to take effect.
+    pub preserve_ownership: Option<bool>,
+    /// Copy buffer used for each file written, in bytes. Defaults to
+    /// `DEFAULT_STREAM_BUFFER_SIZE`; clamped to `MIN_STREAM_BUFFER_SIZE..=MAX_STREAM_BUFFER_SIZE`.
+    pub stream_buffer_size: Option<usize>,
+    /// Abort once the entries' uncompressed sizes add up to more than this (for single
+    /// compressed files, once the output grows past it). Defaults to
+    /// `DEFAULT_MAX_TOTAL_BYTES`.
+    pub max_total_bytes: Option<u64>,
+    /// ZIP only: abort on an entry whose uncompressed size is more than this many times its
+    /// compressed size (checked from `RATIO_CHECK_MIN_BYTES` up). Defaults to
+    /// `DEFAULT_MAX_COMPRESSION_RATIO`.
+    pub max_compression_ratio: Option<f64>,
+    /// Abort on archives with more entries than this. Defaults to `DEFAULT_MAX_ENTRIES`.
+    pub max_entries: Option<usize>,
+}
 
-    match kind {
-        "zip" => open_zip(&path_buf),
-        "tar" => {
-            let file =
-                File::open(&path_buf).map_err(|e| format!("Failed to open tar: {e}"))?;
-            let archive = TarArchive::new(file);
-            open_tar_like(archive)
-        }
-        "tar.gz" => {
-            let file =
-                File::open(&path_buf).map_err(|e| format!("Failed to open tar.gz: {e}"))?;
-            let decoder = GzDecoder::new(file);
-            let archive = TarArchive::new(decoder);
-            open_tar_like(archive)
-        }
-        "tar.bz2" => {
-            let file =
-                File::open(&path_buf).map_err(|e| format!("Failed to open tar.bz2: {e}"))?;
-            let decoder = BzDecoder::new(file);
-            let archive = TarArchive::new(decoder);
-            open_tar_like(archive)
-        }
-        "tar.xz" => {
-            let file =
-                File::open(&path_buf).map_err(|e| format!("Failed to open tar.xz: {e}"))?;
-            let decoder = XzDecoder::new(file);
-            let archive = TarArchive::new(decoder);
-            open_tar_like(archive)
-        }
-        _ => Err("Unsupported archive type".into()),
+impl ExtractOptions {
+    fn buffer_size(&self) -> usize {
+        self.stream_buffer_size
+            .unwrap_or(DEFAULT_STREAM_BUFFER_SIZE)
+            .clamp(MIN_STREAM_BUFFER_SIZE, MAX_STREAM_BUFFER_SIZE)
     }
 }
 
-/// Extract a whole archive to a directory.
-#[tauri::command]
-pub async fn extract_archive(path: String, dest: String) -> Result<(), String> {
-    let path_buf = PathBuf::from(&path);
-    let dest_buf = PathBuf::from(&dest);
-    let kind = detect_archive_type(&path_buf);
+/// Copy buffer for `stream_to_file` when the caller doesn't choose one.
+const DEFAULT_STREAM_BUFFER_SIZE: usize = 64 * 1024;
+/// Bounds for `ExtractOptions::stream_buffer_size`. Each worker allocates one buffer, so an
+/// unbounded value from the frontend could abort the process on allocation.
+const MIN_STREAM_BUFFER_SIZE: usize = 4 * 1024;
+const MAX_STREAM_BUFFER_SIZE: usize = 16 * 1024 * 1024;
 
-    match kind {
-        "zip" => extract_zip(&path_buf, &dest_buf),
-        "tar" => {
-            let file =
-                File::open(&path_buf).map_err(|e| format!("Failed to open tar: {e}"))?;
-            let archive = TarArchive::new(file);
-            extract_tar_like(archive, &dest_buf)
-        }
-        "tar.gz" => {
-            let file =
-                File::open(&path_buf).map_err(|e| format!("Failed to open tar.gz: {e}"))?;
-            let decoder = GzDecoder::new(file);
-            let archive = TarArchive::new(decoder);
-            extract_tar_like(archive, &dest_buf)
-        }
-        "tar.bz2" => {
-            let file =
-                File::open(&path_buf).map_err(|e| format!("Failed to open tar.bz2: {e}"))?;
-            let decoder = BzDecoder::new(file);
-            let archive = TarArchive::new(decoder);
-            extract_tar_like(archive, &dest_buf)
-        }
-        "tar.xz" => {
-            let file =
-                File::open(&path_buf).map_err(|e| format!("Failed to open tar.xz: {e}"))?;
-            let decoder = XzDecoder::new(file);
-            let archive = TarArchive::new(decoder);
-            extract_tar_like(archive, &dest_buf)
-        }
-        _ => Err("Unsupported archive type".into()),
+/// Helper: create `path` and copy `reader` into it through a single `buf_size` buffer, so
+/// memory stays bounded however large the entry is. Shared by every extractor that writes
+/// entry data itself. Returns the number of bytes written.
+fn stream_to_file(reader: &mut dyn Read, path: &Path, buf_size: usize) -> io::Result<u64> {
+    let mut outfile = File::create(path)?;
+    let mut buf = vec![0u8; buf_size.max(1)];
+    let mut written = 0;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        outfile.write_all(&buf[..n])?;
+        written += n as u64;
     }
+    Ok(written)
 }
 
-/// Shape that matches how `create_zip_archive` is invoked from TypeScript (args: { ... }).
-#[derive(Debug, Deserialize)]
-pub struct CreateZipArgs {
-    pub outputPath: String,
-    pub inputPaths: Vec<String>,
-    pub compressionMode: String, // currently unused; all deflated
-    pub parallelCompression: bool, // currently unused, but kept for future
-    pub tempDir: Option<String>,
+/// Callback for low-space aborts (see `ExtractOptions::low_space_notifier`).
+pub struct LowSpaceNotifier(pub Box<dyn Fn(u64) + Send + Sync>);
+
+impl std::fmt::Debug for LowSpaceNotifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("LowSpaceNotifier")
+    }
 }
 
-/// Create a new ZIP archive from a set of input paths.
-#[tauri::command]
-pub async fn create_zip_archive(args: CreateZipArgs) -> Result<(), String> {
-    let output = PathBuf::from(&args.outputPath);
+/// Callback for per-entry progress: entries done, total entries when known, bytes written.
+pub struct ProgressNotifier(pub Box<dyn Fn(usize, Option<usize>, u64) + Send + Sync>);
 
-    if let Some(parent) = output.parent() {
-        fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create output dir: {e}"))?;
+impl std::fmt::Debug for ProgressNotifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressNotifier")
     }
+}
 
-    let file = File::create(&output)
-        .map_err(|e| format!("Failed to create archive file: {e}"))?;
-    let mut writer = ZipWriter::new(file);
+/// Running totals behind `ExtractOptions::progress`, shared by extraction workers.
+struct ProgressTracker<'a> {
+    notifier: Option<&'a ProgressNotifier>,
+    total: Option<usize>,
+    entries: AtomicUsize,
+    bytes: AtomicU64,
+}
 
-    for input in &args.inputPaths {
-        let path = PathBuf::from(input);
-        if !path.exists() {
-            continue;
+impl<'a> ProgressTracker<'a> {
+    fn new(options: &'a ExtractOptions, total: Option<usize>) -> Self {
+        Self {
+            notifier: options.progress.as_ref(),
+            total,
+            entries: AtomicUsize::new(0),
+            bytes: AtomicU64::new(0),
         }
-
-        let base = if path.is_dir() {
-            path.clone()
-        } else {
-            path.parent().unwrap_or(&path).to_path_buf()
-        };
-
-        add_path_to_zip(&mut writer, &path, &base)?;
     }
 
-    writer.finish().map_err(|e| format!("Failed to finalize zip: {e}"))?;
-    Ok(())
+    /// Count one finished (or failed) entry and report it.
+    fn entry_done(&self, bytes: u64) {
+        let entries = self.entries.fetch_add(1, Ordering::Relaxed) + 1;
+        let bytes = self.bytes.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        if let Some(notifier) = self.notifier {
+            (notifier.0)(entries, self.total, bytes);
+        }
+    }
 }
 
-/// Shape for `add_files_to_zip({ args: { zip, files } })`.
-#[derive(Debug, Deserialize)]
-pub struct AddFilesArgs {
-    pub zip: String,
-    pub files: Vec<String>,
-}
+/// Entries between free-space checks when `min_free_bytes` is set.
+const SPACE_CHECK_EVERY_ENTRIES: usize = 64;
+/// Bytes written between free-space checks when `min_free_bytes` is set.
+const SPACE_CHECK_EVERY_BYTES: u64 = 64 * 1024 * 1024;
 
-/// Add files to an existing ZIP by rewriting it to a temp file and then replacing.
-#[tauri::command]
-pub async fn add_files_to_zip(args: AddFilesArgs) -> Result<(), String> {
-    let zip_path = PathBuf::from(&args.zip);
-    let tmp_path = zip_path
-        .with_extension("tmp.zip");
+/// Zip-bomb guardrail defaults; see `ExtractOptions::max_total_bytes` and friends.
+const DEFAULT_MAX_TOTAL_BYTES: u64 = 64 * 1024 * 1024 * 1024;
+/// Deflate tops out near 1032:1, which only long runs of one byte reach.
+const DEFAULT_MAX_COMPRESSION_RATIO: f64 = 1000.0;
+const DEFAULT_MAX_ENTRIES: usize = 1_000_000;
+/// Entries smaller than this skip the ratio check; their ratio says little.
+const RATIO_CHECK_MIN_BYTES: u64 = 1024 * 1024;
 
-    // 1. Open existing zip (if present) and copy entries to new writer.
-    let mut writer = {
-        let tmp_file = File::create(&tmp_path)
-            .map_err(|e| format!("Failed to create temp zip: {e}"))?;
-        ZipWriter::new(tmp_file)
-    };
+/// Start of the error returned when extraction hits one of the zip-bomb guardrails.
+pub(crate) const EXTRACTION_LIMIT_ERROR: &str = "Extraction limit exceeded";
 
-    if zip_path.exists() {
-        let file = File::open(&zip_path)
-            .map_err(|e| format!("Failed to open existing zip: {e}"))?;
-        let mut archive = ZipArchive::new(file)
-            .map_err(|e| format!("Invalid existing zip: {e}"))?;
+/// Start of the error returned when extraction stops for lack of space.
+pub(crate) const LOW_SPACE_ERROR: &str = "low disk space";
 
-        for i in 0..archive.len() {
-            let mut entry = archive
-                .by_index(i)
-                .map_err(|e| format!("Existing zip entry error: {e}"))?;
-            let name = entry.name().to_string();
+/// Error returned when `cancel_extraction` stops an extraction. Files already written stay.
+pub(crate) const EXTRACTION_CANCELLED: &str = "Extraction cancelled";
 
-            writer
-                .start_file(
-                    &name,
-                    FileOptions::default()
-                        .compression_method(CompressionMethod::Deflated)
-                        .unix_permissions(0o644),
-                )
-                .map_err(|e| format!("Temp zip start file error: {e}"))?;
+/// Error returned when an encrypted zip entry is read without a password.
+pub(crate) const PASSWORD_REQUIRED: &str = "Password required";
+/// Error returned when the password given for an encrypted zip entry is wrong.
+pub(crate) const INVALID_PASSWORD: &str = "Invalid password";
+
+/// Error returned by every command. Serializes as `{ code, message }`; `code` is stable so
+/// the UI can branch on it, `message` is for showing to the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CapsuleError {
+    NotFound(String),
+    InvalidArchive(String),
+    Unsupported(String),
+    PasswordRequired(String),
+    InvalidPassword(String),
+    Traversal(String),
+    Cancelled(String),
+    LowSpace(String),
+    /// Extraction stopped by a zip-bomb guardrail (size, ratio or entry count).
+    LimitExceeded(String),
+    /// Content that was expected to be UTF-8 text isn't.
+    NotText(String),
+    Io(String),
+    Other(String),
+}
 
-            io::copy(&mut entry, &mut writer)
-                .map_err(|e| format!("Temp zip copy error: {e}"))?;
+impl CapsuleError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "notFound",
+            Self::InvalidArchive(_) => "invalidArchive",
+            Self::Unsupported(_) => "unsupported",
+            Self::PasswordRequired(_) => "passwordRequired",
+            Self::InvalidPassword(_) => "invalidPassword",
+            Self::Traversal(_) => "traversal",
+            Self::Cancelled(_) => "cancelled",
+            Self::LowSpace(_) => "lowSpace",
+            Self::LimitExceeded(_) => "limitExceeded",
+            Self::NotText(_) => "notText",
+            Self::Io(_) => "io",
+            Self::Other(_) => "other",
         }
     }
 
-    // 2. Add new files.
-    for f in &args.files {
-        let path = PathBuf::from(f);
-        if !path.exists() {
-            continue;
+    pub fn message(&self) -> &str {
+        match self {
+            Self::NotFound(m)
+            | Self::InvalidArchive(m)
+            | Self::Unsupported(m)
+            | Self::PasswordRequired(m)
+            | Self::InvalidPassword(m)
+            | Self::Traversal(m)
+            | Self::Cancelled(m)
+            | Self::LowSpace(m)
+            | Self::LimitExceeded(m)
+            | Self::NotText(m)
+            | Self::Io(m)
+            | Self::Other(m) => m,
         }
-
-        let base = if path.is_dir() {
-            path.clone()
-        } else {
-            path.parent().unwrap_or(&path).to_path_buf()
-        };
-
-        add_path_to_zip(&mut writer, &path, &base)?;
     }
+}
 
-    writer.finish().map_err(|e| format!("Failed to finalize temp zip: {e}"))?;
+impl std::fmt::Display for CapsuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
 
-    // 3. Replace original zip.
-    fs::rename(&tmp_path, &zip_path)
-        .map_err(|e| format!("Failed to replace original zip: {e}"))?;
+impl std::error::Error for CapsuleError {}
 
-    Ok(())
+/// Fallback for plain messages; errors with a more specific code are built as that
+/// variant where they happen.
+impl From<String> for CapsuleError {
+    fn from(message: String) -> Self {
+        Self::Other(message)
+    }
 }
 
-/// Shape for `remove_files_from_zip({ args: { zipPath, entryNames } })`.
-#[derive(Debug, Deserialize)]
-pub struct RemoveFilesArgs {
-    pub zipPath: String,
-    pub entryNames: Vec<String>,
+impl From<&str> for CapsuleError {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
 }
 
-/// Remove entries from an existing ZIP.
-#[tauri::command]
-pub async fn remove_files_from_zip(args: RemoveFilesArgs) -> Result<(), String> {
-    let zip_path = PathBuf::from(&args.zipPath);
-    let tmp_path = zip_path.with_extension("tmp.zip");
-
-    let file = File::open(&zip_path)
-        .map_err(|e| format!("Failed to open existing zip: {e}"))?;
-    let mut archive = ZipArchive::new(file)
-        .map_err(|e| format!("Invalid existing zip: {e}"))?;
-
-    let mut writer = {
-        let tmp_file = File::create(&tmp_path)
-            .map_err(|e| format!("Failed to create temp zip: {e}"))?;
-        ZipWriter::new(tmp_file)
-    };
+impl Serialize for CapsuleError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("CapsuleError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", self.message())?;
+        state.end()
+    }
+}
 
-    for i in 0..archive.len() {
-        let mut entry = archive
-            .by_index(i)
-            .map_err(|e| format!("Zip entry error: {e}"))?;
-        let name = entry.name().to_string();
+/// Helpers build errors where they happen, with the variant taken from the failing call's
+/// own error type, so codes don't depend on how messages are worded.
+impl CapsuleError {
+    /// An I/O error, with `context` in front. Malformed or truncated data (`InvalidData`,
+    /// `UnexpectedEof`) means a damaged archive; anything else is `Io`.
+    pub(crate) fn io(context: impl std::fmt::Display, e: io::Error) -> Self {
+        let message = format!("{context}: {e}");
+        match e.kind() {
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => {
+                Self::InvalidArchive(message)
+            }
+            _ => Self::Io(message),
+        }
+    }
 
-        if args.entryNames.iter().any(|e| e == &name) {
-            // Skip entries that should be removed
-            continue;
+    /// Like `io`, for opening a file the caller named: a missing one is `NotFound`.
+    pub(crate) fn open(context: impl std::fmt::Display, e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::NotFound => Self::NotFound(format!("{context}: {e}")),
+            _ => Self::io(context, e),
         }
+    }
 
-        writer
-            .start_file(
-                &name,
-                FileOptions::default()
-                    .compression_method(CompressionMethod::Deflated)
-                    .unix_permissions(0o644),
-            )
-            .map_err(|e| format!("Temp zip start file error: {e}"))?;
+    /// A zip crate error, with `context` in front. A missing password is reported as
+    /// `PASSWORD_REQUIRED` so the UI can prompt for one.
+    pub(crate) fn zip(context: impl std::fmt::Display, e: zip::result::ZipError) -> Self {
+        use zip::result::ZipError;
 
-        io::copy(&mut entry, &mut writer)
-            .map_err(|e| format!("Temp zip copy error: {e}"))?;
+        match e {
+            ZipError::Io(e) => Self::io(context, e),
+            ZipError::UnsupportedArchive(msg) if msg == ZipError::PASSWORD_REQUIRED => {
+                Self::PasswordRequired(PASSWORD_REQUIRED.into())
+            }
+            ZipError::UnsupportedArchive(_) => Self::Unsupported(format!("{context}: {e}")),
+            ZipError::InvalidArchive(_) => Self::InvalidArchive(format!("{context}: {e}")),
+            ZipError::FileNotFound => Self::NotFound(format!("{context}: {e}")),
+        }
     }
+}
 
-    writer.finish().map_err(|e| format!("Failed to finalize temp zip: {e}"))?;
-    fs::rename(&tmp_path, &zip_path)
-        .map_err(|e| format!("Failed to replace original zip: {e}"))?;
+/// Helper: open zip entry `index` for reading, decrypting it with `password` when given.
+fn open_zip_entry<'a, R: Read + Seek>(
+    archive: &'a mut ZipArchive<R>,
+    index: usize,
+    password: Option<&str>,
+) -> Result<zip::read::ZipFile<'a>, CapsuleError> {
+    match password {
+        Some(password) => archive
+            .by_index_decrypt(index, password.as_bytes())
+            .map_err(|e| CapsuleError::zip("Zip entry error", e))?
+            .map_err(|_| CapsuleError::InvalidPassword(INVALID_PASSWORD.into())),
+        None => archive
+            .by_index(index)
+            .map_err(|e| CapsuleError::zip("Zip entry error", e)),
+    }
+}
 
+/// Helper: check `password` against the first encrypted entry of a zip, if it has any.
+fn check_zip_password(path: &Path, password: &str) -> Result<(), CapsuleError> {
+    let file = File::open(path).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+    for i in 0..archive.len() {
+        let encrypted = matches!(
+            archive.by_index(i),
+            Err(zip::result::ZipError::UnsupportedArchive(msg))
+                if msg == zip::result::ZipError::PASSWORD_REQUIRED
+        );
+        if encrypted {
+            open_zip_entry(&mut archive, i, Some(password))?;
+            break;
+        }
+    }
     Ok(())
 }
 
-/// Simple "copy file" helper.
-#[tauri::command]
-pub async fn copy_file(src: String, dest: String) -> Result<(), String> {
-    fs::copy(&src, &dest)
-        .map_err(|e| format!("Failed to copy file: {e}"))?;
-    Ok(())
+/// Helper: fail with `EXTRACTION_CANCELLED` once the operation's cancel flag is raised.
+fn check_cancelled(cancel: Option<&AtomicBool>) -> Result<(), CapsuleError> {
+    match cancel {
+        Some(flag) if flag.load(Ordering::Relaxed) => {
+            Err(CapsuleError::Cancelled(EXTRACTION_CANCELLED.into()))
+        }
+        _ => Ok(()),
+    }
 }
 
-/// Get file size in bytes.
-#[tauri::command]
-pub async fn get_file_size(path: String) -> Result<u64, String> {
-    let metadata = fs::metadata(&path)
-        .map_err(|e| format!("Failed to read file metadata: {e}"))?;
-    Ok(metadata.len())
+/// Helper: bytes available to the current user on the volume holding `path`, or its nearest
+/// existing ancestor (the destination may not exist yet).
+pub(crate) fn get_available_space(path: &Path) -> Result<u64, CapsuleError> {
+    let existing = path.ancestors().find(|p| p.exists()).unwrap_or(path);
+    fs4::available_space(existing).map_err(|e| CapsuleError::io("Failed to query free space", e))
 }
 
-/// Preview result shape for frontend.
-#[derive(Debug, Serialize)]
-pub struct PreviewResult {
-    pub kind: String,          // "text" | "binary"
-    pub mime: String,
-    pub text: Option<String>,  // for text previews
-    pub data_base64: Option<String>, // for binary previews if you want
-    pub size: u64,
+/// Running totals checked against the zip-bomb guardrails in `ExtractOptions`. Archive
+/// entries are admitted from their headers, before anything is written for them; a single
+/// compressed file is admitted from its (capped) output instead.
+struct ExtractLimits {
+    max_total_bytes: u64,
+    max_compression_ratio: f64,
+    max_entries: usize,
+    total_bytes: u64,
+    entries: usize,
 }
 
-/// Detect MIME type from file extension
-pub(crate) fn detect_mime_type(filename: &str) -> String {
-    let ext = filename
-        .rfind('.')
-        .map(|i| &filename[i + 1..])
-        .unwrap_or("")
-        .to_lowercase();
+impl ExtractLimits {
+    fn new(options: &ExtractOptions) -> Self {
+        Self {
+            max_total_bytes: options.max_total_bytes.unwrap_or(DEFAULT_MAX_TOTAL_BYTES),
+            max_compression_ratio: options
+                .max_compression_ratio
+                .unwrap_or(DEFAULT_MAX_COMPRESSION_RATIO),
+            max_entries: options.max_entries.unwrap_or(DEFAULT_MAX_ENTRIES),
+            total_bytes: 0,
+            entries: 0,
+        }
+    }
 
-    match ext.as_str() {
-        "jpg" | "jpeg" => "image/jpeg".into(),
-        "png" => "image/png".into(),
-        "gif" => "image/gif".into(),
-        "webp" => "image/webp".into(),
-        "svg" => "image/svg+xml".into(),
-        "bmp" => "image/bmp".into(),
-        "ico" => "image/x-icon".into(),
-        "json" => "application/json".into(),
-        "xml" => "application/xml".into(),
-        "html" | "htm" => "text/html".into(),
-        "css" => "text/css".into(),
-        "js" => "application/javascript".into(),
-        "ts" => "application/typescript".into(),
-        "md" | "markdown" => "text/markdown".into(),
-        "py" => "text/x-python".into(),
-        "rs" => "text/x-rust".into(),
-        "sh" | "bash" => "text/x-shellscript".into(),
-        "txt" => "text/plain".into(),
-        _ => "application/octet-stream".into(),
+    /// Count entry `name` of `size` bytes (`compressed` bytes stored, when known), failing
+    /// with `EXTRACTION_LIMIT_ERROR` if that crosses a limit.
+    fn admit(
+        &mut self,
+        name: &str,
+        size: u64,
+        compressed: Option<u64>,
+    ) -> Result<(), CapsuleError> {
+        self.entries += 1;
+        if self.entries > self.max_entries {
+            return Err(CapsuleError::LimitExceeded(format!(
+                "{EXTRACTION_LIMIT_ERROR}: more than {} entries",
+                self.max_entries
+            )));
+        }
+        self.total_bytes = self.total_bytes.saturating_add(size);
+        if self.total_bytes > self.max_total_bytes {
+            return Err(CapsuleError::LimitExceeded(format!(
+                "{EXTRACTION_LIMIT_ERROR}: more than {} bytes uncompressed",
+                self.max_total_bytes
+            )));
+        }
+        if let Some(compressed) = compressed {
+            let ratio = size as f64 / compressed.max(1) as f64;
+            if size >= RATIO_CHECK_MIN_BYTES && ratio > self.max_compression_ratio {
+                return Err(CapsuleError::LimitExceeded(format!(
+                    "{EXTRACTION_LIMIT_ERROR}: {name} expands {ratio:.0}x (limit {})",
+                    self.max_compression_ratio
+                )));
+            }
+        }
+        Ok(())
     }
 }
 
-/// Basic preview: currently only supports ZIP entries.
-#[tauri::command]
-pub async fn preview_archive_entry(
-    archive_path: String,
-    entry_path: String,
-) -> Result<PreviewResult, String> {
-    let path = PathBuf::from(&archive_path);
-    let kind = detect_archive_type(&path);
+/// Helper: enforces `min_free_bytes` during an extraction. Remembers the files written so
+/// they can be removed if space runs out; safe to share between extraction workers.
+struct SpaceGuard<'a> {
+    dest: &'a Path,
+    min_free_bytes: u64,
+    notifier: Option<&'a LowSpaceNotifier>,
+    /// Entries and bytes written since the last check.
+    since_check: Mutex<(usize, u64)>,
+    /// Whether the guard has tripped, and the files written so far.
+    written: Mutex<(bool, Vec<PathBuf>)>,
+}
 
-    if kind != "zip" {
-        return Err("Preview currently only implemented for ZIP archives".into());
+impl<'a> SpaceGuard<'a> {
+    /// `None` when `min_free_bytes` isn't set; errors if space is already too low.
+    fn new(dest: &'a Path, options: &'a ExtractOptions) -> Result<Option<Self>, CapsuleError> {
+        let Some(min_free_bytes) = options.min_free_bytes else {
+            return Ok(None);
+        };
+        let guard = Self {
+            dest,
+            min_free_bytes,
+            notifier: options.low_space_notifier.as_ref(),
+            since_check: Mutex::new((0, 0)),
+            written: Mutex::new((false, Vec::new())),
+        };
+        guard.check()?;
+        Ok(Some(guard))
     }
 
-    let file = File::open(&path).map_err(|e| format!("Failed to open zip: {e}"))?;
-    let mut archive = ZipArchive::new(file).map_err(|e| format!("Invalid zip: {e}"))?;
+    fn check(&self) -> Result<(), CapsuleError> {
+        let free = get_available_space(self.dest)?;
+        if free >= self.min_free_bytes {
+            return Ok(());
+        }
 
-    let mut entry = archive
-        .by_name(&entry_path)
-        .map_err(|e| format!("Entry not found: {e}"))?;
+        let mut written = self
+            .written
+            .lock()
+            .map_err(|_| CapsuleError::Other("Space guard lock poisoned".into()))?;
+        if !written.0 {
+            written.0 = true;
+            if let Some(notifier) = self.notifier {
+                (notifier.0)(free);
+            }
+            for path in written.1.drain(..) {
+                let _ = fs::remove_file(path);
+            }
+        }
+        Err(CapsuleError::LowSpace(format!(
+            "{LOW_SPACE_ERROR}: {free} bytes free at {}, below the {} byte minimum",
+            self.dest.display(),
+            self.min_free_bytes
+        )))
+    }
 
-    let size = entry.size();
-    let mime = detect_mime_type(&entry_path);
-    
-    // Limit preview size to 10MB to avoid memory issues
-    let max_preview_size: u64 = 10 * 1024 * 1024;
-    let read_size: usize = if size > max_preview_size {
-        max_preview_size as usize
-    } else {
-        size as usize
+    /// Note a written entry, checking free space when enough has been written since the last
+    /// check. A file finished after the guard tripped is removed straight away.
+    fn record(&self, path: &Path, bytes: u64) -> Result<(), CapsuleError> {
+        {
+            let mut written = self
+                .written
+                .lock()
+                .map_err(|_| CapsuleError::Other("Space guard lock poisoned".into()))?;
+            if written.0 {
+                let _ = fs::remove_file(path);
+                return Err(CapsuleError::LowSpace(format!(
+                    "{LOW_SPACE_ERROR}: extraction aborted"
+                )));
+            }
+            if path.is_file() {
+                written.1.push(path.to_path_buf());
+            }
+        }
+
+        let due = {
+            let mut since = self
+                .since_check
+                .lock()
+                .map_err(|_| CapsuleError::Other("Space guard lock poisoned".into()))?;
+            since.0 += 1;
+            since.1 += bytes;
+            let due = since.0 >= SPACE_CHECK_EVERY_ENTRIES || since.1 >= SPACE_CHECK_EVERY_BYTES;
+            if due {
+                *since = (0, 0);
+            }
+            due
+        };
+        if due {
+            self.check()
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Payload of the `extract://low-space` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LowSpaceWarning {
+    pub dest: String,
+    pub free_bytes: u64,
+    pub min_free_bytes: u64,
+}
+
+/// Payload of the `extract://progress` event. `index` counts entries done so far; `total` is
+/// unset for tar archives, whose entry count isn't known until the stream has been read.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractProgress {
+    pub operation_id: Option<String>,
+    pub index: usize,
+    pub total: Option<usize>,
+    pub bytes_written: u64,
+}
+
+/// Safe follow-ups the frontend can chain onto an extraction (no arbitrary commands).
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PostExtractAction {
+    /// Open the destination folder in the OS file manager.
+    Reveal,
+    /// Re-read extracted files and compare them with the archive (CRC for zip, size for tar).
+    Verify,
+    /// Return the paths of every extracted file.
+    ListFiles,
+}
+
+/// Outcome of the `on_complete` action, tagged by `action`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum PostExtractResult {
+    Reveal {
+        path: String,
+    },
+    Verify {
+        checked: usize,
+        mismatched: Vec<String>,
+    },
+    ListFiles {
+        files: Vec<String>,
+    },
+}
+
+/// What `extract_archive` did, returned to the frontend.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractSummary {
+    pub extracted: usize,
+    pub skipped_duplicates: Vec<String>,
+    pub post_action: Option<PostExtractResult>,
+    /// Requested entries (selective extraction) that the archive doesn't contain.
+    pub not_found: Vec<String>,
+    pub skipped_case_collisions: Vec<String>,
+    pub renamed: Vec<RenamedEntry>,
+    /// Entries not written because the file already existed (overwrite policy "skip").
+    pub skipped_existing: Vec<String>,
+    /// ZIP only: worker count used for (the bulk of) the extraction.
+    pub concurrency: Option<usize>,
+    /// Tar only: bytes of sparse-file holes left unallocated instead of written as zeros.
+    pub sparse_bytes_saved: u64,
+    /// Best-effort mode: entries skipped as suspect or that failed to extract.
+    pub failed: Vec<FailedEntry>,
+    /// Non-fatal notes, e.g. tar symlinks this platform couldn't recreate.
+    pub warnings: Vec<String>,
+    /// Dry run only: destination paths that would be written. Rejected entries go in `failed`.
+    pub planned: Vec<String>,
+    /// `extract_to_subfolder`: the folder created for the archive, if one was needed.
+    pub subfolder: Option<String>,
+    /// Files written, at the paths renames, flattening and case folding actually gave them.
+    /// Backs the verify and list `on_complete` actions; not sent to the frontend.
+    #[serde(skip)]
+    pub written: Vec<WrittenFile>,
+}
+
+/// A file an extraction wrote, with what the archive says it should contain.
+#[derive(Debug)]
+pub struct WrittenFile {
+    /// Entry name as stored in the archive.
+    pub entry: String,
+    pub path: PathBuf,
+    /// Zip and 7z store a CRC-32 per entry; other formats are checked by size only.
+    pub crc32: Option<u32>,
+    /// `None` for links, which are listed but not checked.
+    pub size: Option<u64>,
+}
+
+/// An entry best-effort extraction skipped or could not write.
+#[derive(Debug, Serialize)]
+pub struct FailedEntry {
+    pub name: String,
+    pub error: String,
+}
+
+/// An entry written under a different name than the one stored in the archive.
+#[derive(Debug, Serialize)]
+pub struct RenamedEntry {
+    pub from: String,
+    pub to: String,
+}
+
+/// Helper: insert ` (n)` before the extension of the last path component,
+/// e.g. `docs/Readme.txt` -> `docs/Readme (1).txt`.
+pub(crate) fn numbered_name(name: &str, n: usize) -> String {
+    let (dir, file) = match name.rfind('/') {
+        Some(i) => name.split_at(i + 1),
+        None => ("", name),
     };
+    match file.rfind('.') {
+        Some(dot) if dot > 0 => format!("{dir}{} ({n}){}", &file[..dot], &file[dot..]),
+        _ => format!("{dir}{file} ({n})"),
+    }
+}
 
-    let mut buf = vec![0u8; read_size];
-    let bytes_read = entry
-        .read(&mut buf)
-        .map_err(|e| format!("Failed to read entry: {e}"))?;
-    buf.truncate(bytes_read);
+/// Helper: applies `case_collision_policy` by remembering extracted file paths case-folded.
+struct CaseCollisionGuard {
+    policy: Option<String>,
+    seen: HashMap<String, String>,
+}
 
-    // Check if it's an image based on MIME type
-    if mime.starts_with("image/") {
-        return Ok(PreviewResult {
-            kind: "binary".into(),
-            mime: mime.clone(),
-            text: None,
-            data_base64: Some(BASE64.encode(&buf)),
-            size,
-        });
+impl CaseCollisionGuard {
+    fn new(policy: Option<&str>) -> Result<Self, CapsuleError> {
+        match policy {
+            None | Some("rename") | Some("skip") | Some("error") => Ok(Self {
+                policy: policy.map(String::from),
+                seen: HashMap::new(),
+            }),
+            Some(other) => Err(CapsuleError::Other(format!(
+                "Invalid caseCollisionPolicy value: {other}"
+            ))),
+        }
     }
 
-    // Try to decode as UTF-8 text
-    match String::from_utf8(buf.clone()) {
-        Ok(text) => {
-            // It's valid UTF-8, treat as text
-            let short = if text.len() > 500 * 1024 {
-                // Limit text preview to 500KB for performance
-                format!("{}…\n\n[Preview truncated. Full file is {} bytes]", 
-                    &text[..500 * 1024], size)
-            } else {
-                text
-            };
+    /// Name to extract `name` under, or `None` to skip it.
+    fn resolve(
+        &mut self,
+        name: &str,
+        summary: &mut ExtractSummary,
+    ) -> Result<Option<String>, CapsuleError> {
+        let Some(policy) = self.policy.as_deref() else {
+            return Ok(Some(name.to_string()));
+        };
 
-            Ok(PreviewResult {
-                kind: "text".into(),
-                mime,
-                text: Some(short),
-                data_base64: None,
-                size,
-            })
+        let key = name.to_lowercase();
+        let existing = match self.seen.get(&key) {
+            // Exact repeats are duplicates, not case collisions.
+            Some(existing) if existing != name => existing.clone(),
+            _ => {
+                self.seen.insert(key, name.to_string());
+                return Ok(Some(name.to_string()));
+            }
+        };
+
+        match policy {
+            "error" => Err(CapsuleError::Other(format!(
+                "Case collision: {name} conflicts with {existing}"
+            ))),
+            "skip" => {
+                summary.skipped_case_collisions.push(name.to_string());
+                Ok(None)
+            }
+            _ => {
+                let mut n = 1;
+                let renamed = loop {
+                    let candidate = numbered_name(name, n);
+                    if !self.seen.contains_key(&candidate.to_lowercase()) {
+                        break candidate;
+                    }
+                    n += 1;
+                };
+                self.seen.insert(renamed.to_lowercase(), renamed.clone());
+                summary.renamed.push(RenamedEntry {
+                    from: name.to_string(),
+                    to: renamed.clone(),
+                });
+                Ok(Some(renamed))
+            }
         }
-        Err(_) => {
-            // Not valid UTF-8, treat as binary
-            // For binary, only include first 64KB for hex preview
-            let hex_preview_size = if buf.len() > 64 * 1024 {
-                64 * 1024
-            } else {
-                buf.len()
-            };
-            let hex_buf = &buf[..hex_preview_size];
+    }
+}
 
-            Ok(PreviewResult {
-                kind: "binary".into(),
-                mime,
-                text: None,
-                data_base64: Some(BASE64.encode(hex_buf)),
-                size,
-            })
+/// Applies `ExtractOptions::overwrite` to files that exist in dest before extraction starts.
+/// Paths claimed earlier in the same extraction don't count, so duplicate entries still
+/// replace each other as before.
+struct OverwriteGuard {
+    policy: Option<String>,
+    claimed: HashSet<PathBuf>,
+}
+
+impl OverwriteGuard {
+    fn new(policy: Option<&str>) -> Result<Self, CapsuleError> {
+        match policy {
+            None | Some("overwrite") => Ok(Self {
+                policy: None,
+                claimed: HashSet::new(),
+            }),
+            Some("skip") | Some("rename") => Ok(Self {
+                policy: policy.map(String::from),
+                claimed: HashSet::new(),
+            }),
+            Some(other) => Err(CapsuleError::Other(format!(
+                "Invalid overwrite value: {other}"
+            ))),
+        }
+    }
+
+    /// Name to extract `name` under, or `None` to skip it.
+    fn resolve(
+        &mut self,
+        dest: &Path,
+        name: &str,
+        summary: &mut ExtractSummary,
+    ) -> Result<Option<String>, CapsuleError> {
+        let Some(policy) = self.policy.as_deref() else {
+            return Ok(Some(name.to_string()));
+        };
+
+        let mut candidate = name.to_string();
+        let mut n = 0;
+        loop {
+            let outpath = validate_extract_path(dest, Path::new(&candidate))?;
+            let exists = outpath.symlink_metadata().is_ok();
+            if !exists || self.claimed.contains(&outpath) {
+                self.claimed.insert(outpath);
+                break;
+            }
+            if policy == "skip" {
+                summary.skipped_existing.push(name.to_string());
+                return Ok(None);
+            }
+            n += 1;
+            candidate = numbered_name(name, n);
         }
+
+        if n > 0 {
+            summary.renamed.push(RenamedEntry {
+                from: name.to_string(),
+                to: candidate.clone(),
+            });
+        }
+        Ok(Some(candidate))
     }
 }
 
-/// Extract a single entry to a temp file and return its path.
-/// You can later open it with the OS using `tauri-plugin-opener`.
+/// Applies `ExtractOptions::flatten`, remembering the base names handed out so far.
+struct Flattener {
+    enabled: bool,
+    claimed: HashSet<String>,
+}
+
+impl Flattener {
+    fn new(enabled: Option<bool>) -> Self {
+        Self {
+            enabled: enabled.unwrap_or(false),
+            claimed: HashSet::new(),
+        }
+    }
+
+    /// Name to extract the file `name` under: unchanged unless flattening, otherwise its
+    /// base name, numbered if an earlier file already took it.
+    fn resolve(&mut self, name: &str, summary: &mut ExtractSummary) -> String {
+        if !self.enabled {
+            return name.to_string();
+        }
+        let base = name.rsplit(['/', '\\']).next().unwrap_or(name);
+        let mut candidate = base.to_string();
+        let mut n = 0;
+        while !self.claimed.insert(candidate.to_lowercase()) {
+            n += 1;
+            candidate = numbered_name(base, n);
+        }
+        if n > 0 {
+            summary.renamed.push(RenamedEntry {
+                from: name.to_string(),
+                to: candidate.clone(),
+            });
+        }
+        candidate
+    }
+}
+
+/// Helper: group entry paths that differ only by letter case.
+fn find_case_collisions(entries: &[CapsuleEntry]) -> Vec<Vec<String>> {
+    let mut groups: HashMap<String, Vec<String>> = HashMap::new();
+    for entry in entries {
+        let name = entry.path.trim_end_matches('/');
+        let group = groups.entry(name.to_lowercase()).or_default();
+        if !group.iter().any(|n| n == name) {
+            group.push(name.to_string());
+        }
+    }
+
+    let mut collisions: Vec<Vec<String>> = groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort();
+            group
+        })
+        .collect();
+    collisions.sort();
+    collisions
+}
+
+/// List groups of entries whose paths differ only by case, which overwrite each other
+/// when extracted on case-insensitive filesystems (Windows, macOS by default).
 #[tauri::command]
-pub async fn extract_archive_entry_to_temp(
-    archive_path: String,
-    entry_path: String,
-    temp_dir: Option<String>,
-) -> Result<String, String> {
-    let path = PathBuf::from(&archive_path);
-    let kind = detect_archive_type(&path);
+pub async fn case_collisions(path: String) -> Result<Vec<Vec<String>>, CapsuleError> {
+    Ok(find_case_collisions(&list_archive(&PathBuf::from(&path))?))
+}
 
-    if kind != "zip" {
-        return Err("Temp-entry extraction currently only implemented for ZIP".into());
+/// Helper: for each zip entry, whether it survives `dedupe_by_name` ("first" | "last").
+fn zip_dedupe_mask<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    dedupe_by_name: Option<&str>,
+) -> Result<Vec<bool>, CapsuleError> {
+    if dedupe_by_name.is_none() {
+        return Ok(vec![true; archive.len()]);
     }
+    dedupe_mask(&zip_entry_names(archive)?, dedupe_by_name)
+}
 
-    let file = File::open(&path).map_err(|e| format!("Failed to open zip: {e}"))?;
-    let mut archive = ZipArchive::new(file).map_err(|e| format!("Invalid zip: {e}"))?;
+/// Helper: for each of `names`, whether it survives `dedupe_by_name` ("first" | "last").
+fn dedupe_mask(names: &[String], dedupe_by_name: Option<&str>) -> Result<Vec<bool>, CapsuleError> {
+    let keep_last = match dedupe_by_name {
+        None => return Ok(vec![true; names.len()]),
+        Some("first") => false,
+        Some("last") => true,
+        Some(other) => {
+            return Err(CapsuleError::Other(format!(
+                "Invalid dedupeByName value: {other}"
+            )))
+        }
+    };
 
-    let mut entry = archive
-        .by_name(&entry_path)
-        .map_err(|e| format!("Entry not found: {e}"))?;
+    let mut chosen: HashMap<&str, usize> = HashMap::new();
+    for (i, name) in names.iter().enumerate() {
+        if keep_last {
+            chosen.insert(name, i);
+        } else {
+            chosen.entry(name).or_insert(i);
+        }
+    }
 
-    let base_temp = temp_dir
-        .map(PathBuf::from)
-        .unwrap_or(std::env::temp_dir());
+    let mut mask = vec![false; names.len()];
+    for i in chosen.into_values() {
+        mask[i] = true;
+    }
+    Ok(mask)
+}
 
-    fs::create_dir_all(&base_temp)
-        .map_err(|e| format!("Failed to create temp dir: {e}"))?;
+/// Cap for the default extraction concurrency: beyond this, extra threads rarely help
+/// and thrash spinning disks.
+const MAX_DEFAULT_EXTRACT_CONCURRENCY: usize = 8;
+/// Work units per adaptive probe window.
+const ADAPTIVE_PROBE_UNITS: usize = 16;
+/// Parallel probe throughput must beat the sequential one by this factor to keep the workers.
+const ADAPTIVE_MIN_SPEEDUP: f64 = 1.2;
 
-    let safe_name = entry
-        .name()
-        .replace('/', "_")
-        .replace('\\', "_");
-    let out_path = base_temp.join(safe_name);
+/// Helper: one worker per available core, capped at `MAX_DEFAULT_EXTRACT_CONCURRENCY`.
+fn default_extract_concurrency() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(MAX_DEFAULT_EXTRACT_CONCURRENCY)
+}
 
-    let mut outfile = File::create(&out_path)
-        .map_err(|e| format!("Failed to create temp file: {e}"))?;
-    io::copy(&mut entry, &mut outfile)
-        .map_err(|e| format!("Failed to write temp file: {e}"))?;
+/// Zip entries (index, name, validated output path) that land on the same path, compared
+/// case-insensitively. A unit is written in archive order by one worker, so later entries
+/// overwrite earlier ones exactly as in a sequential extraction.
+type ExtractUnit = Vec<(usize, String, PathBuf)>;
 
-    Ok(out_path.to_string_lossy().to_string())
+/// Helper: write `units` using up to `workers` threads, each with its own archive handle.
+/// Returns the number of uncompressed bytes written. With `failures`, an entry that fails is
+/// recorded there (and its partial output removed) instead of aborting the extraction.
+fn extract_zip_units(
+    path: &Path,
+    units: &[ExtractUnit],
+    workers: usize,
+    failures: Option<&Mutex<Vec<FailedEntry>>>,
+    space_guard: Option<&SpaceGuard>,
+    options: &ExtractOptions,
+    progress: &ProgressTracker,
+) -> Result<u64, CapsuleError> {
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let written = AtomicU64::new(0);
+
+    let work = || -> Result<(), CapsuleError> {
+        let file = File::open(path).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+        let mut archive =
+            ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+        while !failed.load(Ordering::Relaxed) {
+            let Some(unit) = units.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                break;
+            };
+            for (index, name, outpath) in unit {
+                check_cancelled(options.cancel.as_deref())?;
+                let result = open_zip_entry(&mut archive, *index, options.password.as_deref())
+                    .and_then(|mut file| {
+                        write_zip_entry(&mut file, outpath, options.buffer_size())?;
+                        Ok(file.size())
+                    });
+                match (result, failures) {
+                    (Ok(size), _) => {
+                        written.fetch_add(size, Ordering::Relaxed);
+                        if let Some(guard) = space_guard {
+                            guard.record(outpath, size)?;
+                        }
+                        progress.entry_done(size);
+                    }
+                    (Err(error), Some(failures)) => {
+                        if outpath.is_file() {
+                            let _ = fs::remove_file(outpath);
+                        }
+                        failures
+                            .lock()
+                            .map_err(|_| CapsuleError::Other("Failure list lock poisoned".into()))?
+                            .push(FailedEntry {
+                                name: name.clone(),
+                                error: error.to_string(),
+                            });
+                        progress.entry_done(0);
+                    }
+                    (Err(error), None) => return Err(error),
+                }
+            }
+        }
+        Ok(())
+    };
+    let run = || {
+        let result = work();
+        if result.is_err() {
+            failed.store(true, Ordering::Relaxed);
+        }
+        result
+    };
+
+    if workers <= 1 || units.len() <= 1 {
+        run()?;
+    } else {
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers.min(units.len()))
+                .map(|_| scope.spawn(run))
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| {
+                    h.join().unwrap_or_else(|_| {
+                        Err(CapsuleError::Other("Extraction worker panicked".into()))
+                    })
+                })
+                .collect::<Result<Vec<_>, CapsuleError>>()
+        })?;
+    }
+
+    Ok(written.into_inner())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Extract a ZIP archive to dest.
+///
+/// Output paths are resolved up front (dedupe, case policy, zip-slip checks), then entries
+/// are written by `options.concurrency` workers. With `adaptive_concurrency`, the first
+/// `ADAPTIVE_PROBE_UNITS` units are written sequentially and the next batch in parallel;
+/// if the parallel batch isn't at least `ADAPTIVE_MIN_SPEEDUP` times faster, the disk is
+/// taken to be the bottleneck and the rest is extracted sequentially.
+fn extract_zip(
+    path: &Path,
+    dest: &Path,
+    options: &ExtractOptions,
+) -> Result<ExtractSummary, CapsuleError> {
+    let file = File::open(path).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
 
-    #[test]
-    fn test_detect_archive_type() {
-        assert_eq!(detect_archive_type(&PathBuf::from("test.zip")), "zip");
-        assert_eq!(detect_archive_type(&PathBuf::from("test.tar")), "tar");
-        assert_eq!(detect_archive_type(&PathBuf::from("test.tar.gz")), "tar.gz");
-        assert_eq!(detect_archive_type(&PathBuf::from("test.tgz")), "tar.gz");
-        assert_eq!(detect_archive_type(&PathBuf::from("test.tar.bz2")), "tar.bz2");
-        assert_eq!(detect_archive_type(&PathBuf::from("test.tar.xz")), "tar.xz");
-        assert_eq!(detect_archive_type(&PathBuf::from("test.unknown")), "unknown");
+    let workers = match options.concurrency {
+        Some(0) => return Err(CapsuleError::Other("concurrency must be at least 1".into())),
+        Some(n) => n,
+        None => default_extract_concurrency(),
+    };
+
+    fs::create_dir_all(dest).map_err(|e| CapsuleError::io("Failed to create dest dir", e))?;
+
+    let keep = zip_dedupe_mask(&mut archive, options.dedupe_by_name.as_deref())?;
+    let mut limits = ExtractLimits::new(options);
+    let mut flattener = Flattener::new(options.flatten);
+    let mut case_guard = CaseCollisionGuard::new(options.case_collision_policy.as_deref())?;
+    let mut overwrite_guard = OverwriteGuard::new(options.overwrite.as_deref())?;
+    let mut summary = ExtractSummary::default();
+    let mut units: Vec<ExtractUnit> = Vec::new();
+    let mut unit_by_path: HashMap<String, usize> = HashMap::new();
+
+    let best_effort = options.best_effort.unwrap_or(false);
+
+    for (i, keep_entry) in keep.into_iter().enumerate() {
+        let file = match archive.by_index_raw(i) {
+            Ok(file) => file,
+            Err(e) if best_effort => {
+                summary.failed.push(FailedEntry {
+                    name: format!("entry #{i}"),
+                    error: format!("Zip entry error: {e}"),
+                });
+                continue;
+            }
+            Err(e) => {
+                return Err(CapsuleError::InvalidArchive(format!(
+                    "Zip entry error: {e}"
+                )))
+            }
+        };
+        let name = zip_entry_name(&file);
+        if !keep_entry {
+            summary.skipped_duplicates.push(name);
+            continue;
+        }
+        limits.admit(&name, file.size(), Some(file.compressed_size()))?;
+        // Damaged central directories often zero the CRC field; real data almost never
+        // hashes to zero.
+        if best_effort && !file.is_dir() && file.size() > 0 && file.crc32() == 0 {
+            summary.failed.push(FailedEntry {
+                name,
+                error: "Stored CRC is zero; header looks damaged".into(),
+            });
+            continue;
+        }
+        if file.is_dir() && flattener.enabled {
+            continue;
+        }
+        let entry_name = if file.is_dir() {
+            name.clone()
+        } else {
+            let flat = flattener.resolve(&name, &mut summary);
+            let Some(name) = case_guard.resolve(&flat, &mut summary)? else {
+                continue;
+            };
+            match overwrite_guard.resolve(dest, &name, &mut summary)? {
+                Some(name) => name,
+                None => continue,
+            }
+        };
+        let entry_path = PathBuf::from(&entry_name);
+        let outpath = validate_extract_path(dest, &entry_path)?;
+        if !file.is_dir() {
+            summary.written.push(WrittenFile {
+                entry: name,
+                path: outpath.clone(),
+                crc32: Some(file.crc32()),
+                size: Some(file.size()),
+            });
+        }
+        let key = outpath.to_string_lossy().to_lowercase();
+        let job = (i, entry_name, outpath);
+        match unit_by_path.get(&key) {
+            Some(&unit) => units[unit].push(job),
+            None => {
+                unit_by_path.insert(key, units.len());
+                units.push(vec![job]);
+            }
+        }
+        summary.extracted += 1;
     }
 
-    #[test]
-    fn test_validate_extract_path_prevents_traversal() {
-        let dest = PathBuf::from("/tmp/extract");
-        
-        // Normal path should work
-        let normal = PathBuf::from("file.txt");
-        assert!(validate_extract_path(&dest, &normal).is_ok());
-        
-        // Path with .. should be prevented (will fail when canonicalized)
-        let traversal = PathBuf::from("../../etc/passwd");
-        // This may pass if path doesn't exist, but should fail on actual extraction
-        let result = validate_extract_path(&dest, &traversal);
-        // Either fails immediately or would fail on canonicalize
-        assert!(result.is_err() || result.is_ok());
+    let space_guard = SpaceGuard::new(dest, options)?;
+    let space_guard = space_guard.as_ref();
+    let failures = Mutex::new(Vec::new());
+    let failures_ref = best_effort.then_some(&failures);
+    let progress = ProgressTracker::new(options, Some(summary.extracted));
+    let adaptive = options.adaptive_concurrency.unwrap_or(false);
+    let workers = if adaptive && workers > 1 && units.len() > 3 * ADAPTIVE_PROBE_UNITS {
+        let throughput = |units: &[ExtractUnit], workers| -> Result<f64, CapsuleError> {
+            let started = std::time::Instant::now();
+            let bytes = extract_zip_units(
+                path,
+                units,
+                workers,
+                failures_ref,
+                space_guard,
+                options,
+                &progress,
+            )?;
+            Ok(bytes as f64 / started.elapsed().as_secs_f64().max(1e-9))
+        };
+        let (sequential, rest) = units.split_at(ADAPTIVE_PROBE_UNITS);
+        let (parallel, rest) = rest.split_at(ADAPTIVE_PROBE_UNITS);
+        let sequential_rate = throughput(sequential, 1)?;
+        let parallel_rate = throughput(parallel, workers)?;
+        let workers = if parallel_rate < sequential_rate * ADAPTIVE_MIN_SPEEDUP {
+            1
+        } else {
+            workers
+        };
+        extract_zip_units(
+            path,
+            rest,
+            workers,
+            failures_ref,
+            space_guard,
+            options,
+            &progress,
+        )?;
+        workers
+    } else {
+        extract_zip_units(
+            path,
+            &units,
+            workers,
+            failures_ref,
+            space_guard,
+            options,
+            &progress,
+        )?;
+        workers
+    };
+    summary.concurrency = Some(workers);
+
+    let failures = failures
+        .into_inner()
+        .map_err(|_| CapsuleError::Other("Failure list lock poisoned".into()))?;
+    summary.extracted -= failures.len();
+    if !failures.is_empty() {
+        // Failed entries had their partial output removed.
+        summary
+            .written
+            .retain(|file| file.path.symlink_metadata().is_ok());
     }
+    summary.failed.extend(failures);
 
-    #[test]
-    fn test_detect_mime_type() {
-        assert_eq!(detect_mime_type("file.jpg"), "image/jpeg");
-        assert_eq!(detect_mime_type("file.png"), "image/png");
-        assert_eq!(detect_mime_type("file.json"), "application/json");
+    Ok(summary)
+}
+
+/// Helper: write one zip entry (file or directory) to an already-validated path.
+fn write_zip_entry(
+    file: &mut zip::read::ZipFile,
+    outpath: &Path,
+    buf_size: usize,
+) -> Result<(), CapsuleError> {
+    if file.is_dir() {
+        fs::create_dir_all(outpath).map_err(|e| CapsuleError::io("Dir create error", e))?;
+    } else {
+        if let Some(parent) = outpath.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| CapsuleError::io("Parent dir create error", e))?;
+        }
+        // Reading one byte past the declared size catches headers that understate it,
+        // which would otherwise slip past the `ExtractLimits` checks.
+        let declared = file.size();
+        let written = stream_to_file(&mut file.take(declared.saturating_add(1)), outpath, buf_size)
+            .map_err(|e| CapsuleError::io("Copy error", e))?;
+        if written > declared {
+            let _ = fs::remove_file(outpath);
+            return Err(CapsuleError::LimitExceeded(format!(
+                "{EXTRACTION_LIMIT_ERROR}: {} inflates past its declared {declared} bytes",
+                file.name()
+            )));
+        }
+        if let Some(mode) = file.unix_mode() {
+            set_unix_permissions(outpath, mode)?;
+        }
+    }
+    Ok(())
+}
+
+/// Helper: restore an entry's stored permission bits (setuid/setgid/sticky dropped). The
+/// owner always keeps read/write, so a stored 0o000 or 0o444 can't lock out later
+/// overwrite or rename passes.
+#[cfg(unix)]
+fn set_unix_permissions(path: &Path, mode: u32) -> Result<(), CapsuleError> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777 | 0o600))
+        .map_err(|e| CapsuleError::io("Failed to set permissions", e))
+}
+
+/// Unix permissions have no equivalent elsewhere; the file keeps the default ones.
+#[cfg(not(unix))]
+fn set_unix_permissions(_path: &Path, _mode: u32) -> Result<(), CapsuleError> {
+    Ok(())
+}
+
+/// Helper: strip `root` (a directory inside the archive) from an entry path for rebased
+/// extraction. Errors when the entry does not live under `root`.
+fn rebase_entry_path<'a>(entry: &'a str, root: Option<&str>) -> Result<&'a str, CapsuleError> {
+    let entry = entry.trim_start_matches("./");
+    let root = match root.map(|r| r.trim_start_matches("./").trim_end_matches('/')) {
+        None | Some("") => return Ok(entry),
+        Some(root) => root,
+    };
+
+    match entry.strip_prefix(root) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => Ok(rest.trim_start_matches('/')),
+        _ => Err(CapsuleError::Other(format!(
+            "Rebase root {root} is not a common prefix of selected entry {entry}"
+        ))),
+    }
+}
+
+/// Helper: extract the named zip entries, optionally rebased under `rebase_root`.
+fn extract_zip_entries(
+    path: &Path,
+    dest: &Path,
+    entry_paths: &[String],
+    rebase_root: Option<&str>,
+) -> Result<ExtractSummary, CapsuleError> {
+    let file = File::open(path).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+    fs::create_dir_all(dest).map_err(|e| CapsuleError::io("Failed to create dest dir", e))?;
+
+    let names = zip_entry_names(&mut archive)?;
+    let mut summary = ExtractSummary::default();
+    for name in entry_paths {
+        let Some(index) = names.iter().rposition(|n| n == name) else {
+            summary.not_found.push(name.clone());
+            continue;
+        };
+        let mut file = archive
+            .by_index(index)
+            .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+        let rel = rebase_entry_path(name, rebase_root)?;
+        let outpath = validate_extract_path(dest, Path::new(rel))?;
+        write_zip_entry(&mut file, &outpath, DEFAULT_STREAM_BUFFER_SIZE)?;
+        summary.extracted += 1;
+    }
+
+    Ok(summary)
+}
+
+/// Helper: extract the named tar entries, optionally rebased under `rebase_root`. Links go
+/// through `unpack_tar_link` and sizes count against the default limits, as in a full
+/// extraction.
+fn extract_tar_entries<R: Read>(
+    mut archive: TarArchive<R>,
+    dest: &Path,
+    entry_paths: &[String],
+    rebase_root: Option<&str>,
+) -> Result<ExtractSummary, CapsuleError> {
+    fs::create_dir_all(dest).map_err(|e| CapsuleError::io("Failed to create dest dir", e))?;
+
+    // Tarballs made with `tar -C dir .` store names as "./name"; match either form.
+    let key = |p: &str| p.trim_start_matches("./").trim_end_matches('/').to_string();
+    let wanted: HashMap<String, &str> = entry_paths
+        .iter()
+        .map(|p| (key(p), p.as_str()))
+        .collect();
+    let mut found = HashSet::new();
+    let mut summary = ExtractSummary::default();
+    let mut limits = ExtractLimits::new(&ExtractOptions::default());
+
+    let entries = archive
+        .entries()
+        .map_err(|e| CapsuleError::InvalidArchive(format!("Failed to read tar entries: {e}")))?;
+    for entry_res in entries {
+        let mut entry =
+            entry_res.map_err(|e| CapsuleError::InvalidArchive(format!("Tar entry error: {e}")))?;
+        // Match the names listings show, including their fallback for non-UTF-8 bytes.
+        let name = tar_entry_name(&entry, NameEncoding::default()).0;
+        let Some(&requested) = wanted.get(&key(&name)) else {
+            continue;
+        };
+        limits.admit(&name, entry.size(), None)?;
+
+        let rel = rebase_entry_path(requested, rebase_root)?;
+        let outpath = validate_extract_path(dest, Path::new(rel))?;
+        if let Some(parent) = outpath.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| CapsuleError::io("Parent dir create error", e))?;
+        }
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_symlink() || entry_type.is_hard_link() {
+            unpack_tar_link(
+                &entry,
+                dest,
+                Path::new(rel),
+                &outpath,
+                &mut summary.warnings,
+            )?;
+        } else {
+            entry
+                .unpack(&outpath)
+                .map_err(|e| CapsuleError::io("Tar unpack error", e))?;
+        }
+        found.insert(requested);
+        summary.extracted += 1;
+    }
+
+    summary.not_found = entry_paths
+        .iter()
+        .filter(|p| !found.contains(p.as_str()))
+        .cloned()
+        .collect();
+    Ok(summary)
+}
+
+/// Helper: CRC-32 of a file on disk, streamed.
+fn file_crc32(path: &Path) -> io::Result<u32> {
+    let mut file = File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize())
+}
+
+/// Helper: the files an extraction left on disk, in extraction order. When several entries
+/// wrote the same path, only the last one (the one whose content survived) is kept.
+fn final_written(written: &[WrittenFile]) -> Vec<&WrittenFile> {
+    let mut seen = HashSet::new();
+    let mut files: Vec<_> = written
+        .iter()
+        .rev()
+        .filter(|file| seen.insert(&file.path))
+        .collect();
+    files.reverse();
+    files
+}
+
+/// Helper: compare the files an extraction wrote with what the archive recorded for them.
+/// Returns how many files were checked and the entry names that didn't match.
+fn verify_extracted(written: &[WrittenFile]) -> (usize, Vec<String>) {
+    let mut checked = 0;
+    let mut mismatched = Vec::new();
+
+    for file in final_written(written) {
+        let matches = match (file.crc32, file.size) {
+            (Some(crc), _) => file_crc32(&file.path).ok() == Some(crc),
+            // Without a checksum, sizes are the best we can compare.
+            (None, Some(size)) => fs::metadata(&file.path).map(|m| m.len()).ok() == Some(size),
+            (None, None) => continue,
+        };
+        checked += 1;
+        if !matches {
+            mismatched.push(file.entry.clone());
+        }
+    }
+
+    (checked, mismatched)
+}
+
+/// Helper: run the `on_complete` follow-up for a finished extraction.
+fn run_post_extract_action(
+    action: PostExtractAction,
+    dest: &Path,
+    summary: &ExtractSummary,
+) -> Result<PostExtractResult, CapsuleError> {
+    match action {
+        PostExtractAction::Reveal => {
+            tauri_plugin_opener::open_path(dest, None::<&str>)
+                .map_err(|e| CapsuleError::Io(format!("Failed to reveal folder: {e}")))?;
+            Ok(PostExtractResult::Reveal {
+                path: dest.to_string_lossy().to_string(),
+            })
+        }
+        PostExtractAction::Verify => {
+            let (checked, mismatched) = verify_extracted(&summary.written);
+            Ok(PostExtractResult::Verify {
+                checked,
+                mismatched,
+            })
+        }
+        PostExtractAction::ListFiles => Ok(PostExtractResult::ListFiles {
+            files: final_written(&summary.written)
+                .into_iter()
+                .map(|file| file.path.to_string_lossy().to_string())
+                .collect(),
+        }),
+    }
+}
+
+/// Extract a TAR-like archive to dest.
+fn extract_tar_like<R: Read>(
+    mut archive: TarArchive<R>,
+    dest: &Path,
+    options: &ExtractOptions,
+) -> Result<ExtractSummary, CapsuleError> {
+    fs::create_dir_all(dest).map_err(|e| CapsuleError::io("Failed to create dest dir", e))?;
+
+    archive.set_preserve_permissions(options.preserve_permissions.unwrap_or(false));
+    archive.set_preserve_mtime(options.preserve_mtime.unwrap_or(true));
+    archive.set_unpack_xattrs(options.unpack_xattrs.unwrap_or(false));
+    archive.set_preserve_ownerships(options.preserve_ownership.unwrap_or(false));
+    let entries = archive
+        .entries()
+        .map_err(|e| CapsuleError::InvalidArchive(format!("Failed to read tar entries: {e}")))?;
+    let mut limits = ExtractLimits::new(options);
+    let mut flattener = Flattener::new(options.flatten);
+    let mut case_guard = CaseCollisionGuard::new(options.case_collision_policy.as_deref())?;
+    let mut overwrite_guard = OverwriteGuard::new(options.overwrite.as_deref())?;
+    let mut summary = ExtractSummary::default();
+    let space_guard = SpaceGuard::new(dest, options)?;
+    let progress = ProgressTracker::new(options, None);
+
+    let best_effort = options.best_effort.unwrap_or(false);
+
+    for (i, entry_res) in entries.enumerate() {
+        check_cancelled(options.cancel.as_deref())?;
+        let mut entry = match entry_res {
+            Ok(entry) => entry,
+            // The tar stream can't be resynchronized after a bad header; keep what we have.
+            Err(e) if best_effort => {
+                summary.failed.push(FailedEntry {
+                    name: format!("entry #{i}"),
+                    error: format!("Tar entry error: {e}"),
+                });
+                break;
+            }
+            Err(e) => {
+                return Err(CapsuleError::InvalidArchive(format!(
+                    "Tar entry error: {e}"
+                )))
+            }
+        };
+        let mut path = entry
+            .path()
+            .map_err(|e| CapsuleError::InvalidArchive(format!("Tar path error: {e}")))?
+            .into_owned();
+        let entry_name = path.to_string_lossy().to_string();
+        // Tar members aren't compressed one by one, so there's no per-entry ratio to check.
+        limits.admit(&entry_name, entry.size(), None)?;
+        if flattener.enabled {
+            let entry_type = entry.header().entry_type();
+            if entry_type.is_symlink() || entry_type.is_hard_link() {
+                summary
+                    .warnings
+                    .push(format!("{}: link skipped when flattening", path.display()));
+            }
+            if entry_type.is_dir() || entry_type.is_symlink() || entry_type.is_hard_link() {
+                continue;
+            }
+        }
+        if !entry.header().entry_type().is_dir() {
+            let flat = flattener.resolve(&path.to_string_lossy(), &mut summary);
+            let Some(name) = case_guard.resolve(&flat, &mut summary)? else {
+                continue;
+            };
+            match overwrite_guard.resolve(dest, &name, &mut summary)? {
+                Some(name) => path = PathBuf::from(name),
+                None => continue,
+            }
+        }
+        let outpath = validate_extract_path(dest, &path)?;
+        if let Some(parent) = outpath.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| CapsuleError::io("Parent dir create error", e))?;
+        }
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_symlink() || entry_type.is_hard_link() {
+            match unpack_tar_link(&entry, dest, &path, &outpath, &mut summary.warnings) {
+                Ok(()) => {
+                    summary.extracted += 1;
+                    if outpath.symlink_metadata().is_ok() {
+                        summary.written.push(WrittenFile {
+                            entry: entry_name,
+                            path: outpath,
+                            crc32: None,
+                            size: None,
+                        });
+                    }
+                }
+                Err(error) if best_effort => summary.failed.push(FailedEntry {
+                    name: path.to_string_lossy().to_string(),
+                    error: error.to_string(),
+                }),
+                Err(error) => return Err(error),
+            }
+            progress.entry_done(0);
+            continue;
+        }
+        let mut dense = false;
+        if entry.header().entry_type().is_gnu_sparse() {
+            let stored = entry
+                .header()
+                .entry_size()
+                .map_err(|e| CapsuleError::InvalidArchive(format!("Tar size error: {e}")))?;
+            let holes = entry.size().saturating_sub(stored);
+            if options.preserve_sparse.unwrap_or(true) {
+                // `unpack` seeks over holes instead of writing them.
+                summary.sparse_bytes_saved += holes;
+            } else {
+                dense = true;
+            }
+        }
+        // `unpack` streams too, and also restores metadata as set up on the archive above.
+        let result = if dense {
+            stream_to_file(&mut entry, &outpath, options.buffer_size()).map(|_| ())
+        } else {
+            entry.unpack(&outpath).map(|_| ())
+        };
+        match result {
+            Ok(()) => {
+                summary.extracted += 1;
+                if let Some(guard) = &space_guard {
+                    guard.record(&outpath, entry.size())?;
+                }
+                progress.entry_done(entry.size());
+                summary.written.push(WrittenFile {
+                    entry: entry_name,
+                    path: outpath,
+                    crc32: None,
+                    size: Some(entry.size()),
+                });
+            }
+            Err(e) if best_effort => {
+                if outpath.is_file() {
+                    let _ = fs::remove_file(&outpath);
+                }
+                summary.failed.push(FailedEntry {
+                    name: path.to_string_lossy().to_string(),
+                    error: format!("Tar unpack error: {e}"),
+                });
+                progress.entry_done(0);
+            }
+            Err(e) => return Err(CapsuleError::io("Tar unpack error", e)),
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Helper: recreate a tar symlink or hard link at `outpath` (`path` is its name in the
+/// archive). Targets must resolve inside `dest`: symlinks relative to their own directory,
+/// hard links relative to the archive root.
+fn unpack_tar_link<R: Read>(
+    entry: &tar::Entry<R>,
+    dest: &Path,
+    path: &Path,
+    outpath: &Path,
+    warnings: &mut Vec<String>,
+) -> Result<(), CapsuleError> {
+    let target = entry
+        .link_name()
+        .map_err(|e| CapsuleError::InvalidArchive(format!("Tar link error: {e}")))?
+        .ok_or_else(|| {
+            CapsuleError::InvalidArchive(format!("Tar link without a target: {}", path.display()))
+        })?
+        .into_owned();
+    if outpath.symlink_metadata().is_ok() {
+        let _ = fs::remove_file(outpath);
+    }
+
+    if entry.header().entry_type().is_hard_link() {
+        let source = validate_extract_path(dest, &target)?;
+        check_link_on_disk(dest, &dest.join(&target), path, &target)?;
+        return fs::hard_link(&source, outpath)
+            .or_else(|_| fs::copy(&source, outpath).map(|_| ()))
+            .map_err(|e| CapsuleError::io("Tar hard link error", e));
+    }
+
+    let link_dir = path.parent().unwrap_or(Path::new(""));
+    let resolved = validate_extract_path(dest, &link_dir.join(&target))?;
+    check_link_on_disk(dest, &dest.join(link_dir).join(&target), path, &target)?;
+    create_symlink(&target, &resolved, outpath, warnings)
+}
+
+/// Helper: `validate_extract_path` checks link targets lexically, so `esc -> d/up/..` passes
+/// as `d` even when `d/up` is itself a link to `..`. This follows the links already on disk:
+/// the longest existing prefix of `full` (the target joined unnormalized) must resolve
+/// inside `dest`, and whatever is left must be plain names.
+fn check_link_on_disk(
+    dest: &Path,
+    full: &Path,
+    path: &Path,
+    target: &Path,
+) -> Result<(), CapsuleError> {
+    let inside = dest.canonicalize().is_ok_and(|root| {
+        full.ancestors()
+            .find_map(|existing| Some((existing, existing.canonicalize().ok()?)))
+            .is_some_and(|(existing, resolved)| {
+                let rest = full.strip_prefix(existing).unwrap_or(full);
+                resolved.starts_with(&root)
+                    && rest
+                        .components()
+                        .all(|c| matches!(c, std::path::Component::Normal(_)))
+            })
+    });
+    if inside {
+        Ok(())
+    } else {
+        Err(CapsuleError::Traversal(format!(
+            "Path traversal detected: link {} -> {} escapes destination through a symlink",
+            path.display(),
+            target.display()
+        )))
+    }
+}
+
+/// Helper: create `link` pointing at `target` (as stored in the archive).
+#[cfg(unix)]
+fn create_symlink(
+    target: &Path,
+    _resolved: &Path,
+    link: &Path,
+    _warnings: &mut Vec<String>,
+) -> Result<(), CapsuleError> {
+    std::os::unix::fs::symlink(target, link).map_err(|e| CapsuleError::io("Symlink error", e))
+}
+
+/// Symlinks need extra privileges on Windows, so a link to an already extracted file becomes
+/// a copy of it and anything else is skipped, noting either in `warnings`.
+#[cfg(not(unix))]
+fn create_symlink(
+    target: &Path,
+    resolved: &Path,
+    link: &Path,
+    warnings: &mut Vec<String>,
+) -> Result<(), CapsuleError> {
+    if resolved.is_file() {
+        fs::copy(resolved, link).map_err(|e| CapsuleError::io("Symlink copy error", e))?;
+        warnings.push(format!(
+            "{}: symlink to {} extracted as a copy",
+            link.display(),
+            target.display()
+        ));
+    } else {
+        warnings.push(format!(
+            "{}: symlink to {} skipped",
+            link.display(),
+            target.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Decompress a single gzip/bzip2/xz file into dest.
+fn extract_compressed(
+    path: &Path,
+    kind: &str,
+    dest: &Path,
+    options: &ExtractOptions,
+) -> Result<ExtractSummary, CapsuleError> {
+    let mut reader = open_compressed_file(path, kind)?;
+    fs::create_dir_all(dest).map_err(|e| CapsuleError::io("Failed to create dest dir", e))?;
+    let space_guard = SpaceGuard::new(dest, options)?;
+    let progress = ProgressTracker::new(options, Some(1));
+    check_cancelled(options.cancel.as_deref())?;
+
+    let mut summary = ExtractSummary::default();
+    let name = compressed_file_entry_name(path);
+    let Some(name) =
+        OverwriteGuard::new(options.overwrite.as_deref())?.resolve(dest, &name, &mut summary)?
+    else {
+        return Ok(summary);
+    };
+    let outpath = validate_extract_path(dest, Path::new(&name))?;
+    // There's no header size to trust, so the output itself is capped one byte past the
+    // limit and then admitted.
+    let mut limits = ExtractLimits::new(options);
+    let mut capped = (&mut reader).take(limits.max_total_bytes.saturating_add(1));
+    let written = stream_to_file(&mut capped, &outpath, options.buffer_size())
+        .map_err(|e| CapsuleError::InvalidArchive(format!("Failed to decompress {kind}: {e}")))
+        .and_then(|bytes| limits.admit(&name, bytes, None).map(|()| bytes));
+    let bytes = match written {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            let _ = fs::remove_file(&outpath);
+            return Err(e);
+        }
+    };
+    if let Some(guard) = &space_guard {
+        guard.record(&outpath, bytes)?;
+    }
+    progress.entry_done(bytes);
+
+    summary.extracted = 1;
+    summary.written.push(WrittenFile {
+        entry: compressed_file_entry_name(path),
+        path: outpath,
+        crc32: None,
+        size: Some(bytes),
+    });
+    Ok(summary)
+}
+
+/// Extract a 7z archive to dest.
+///
+/// Entries in a solid block share one decoder stream, so skipped entries are still read
+/// through to keep the following ones aligned.
+fn extract_7z(
+    path: &Path,
+    dest: &Path,
+    options: &ExtractOptions,
+) -> Result<ExtractSummary, CapsuleError> {
+    let mut reader = SevenZReader::open(path, Password::empty())
+        .map_err(|e| CapsuleError::InvalidArchive(format!("Invalid 7z archive: {e}")))?;
+    let mut limits = ExtractLimits::new(options);
+    for entry in &reader.archive().files {
+        limits.admit(entry.name(), entry.size(), None)?;
+    }
+
+    fs::create_dir_all(dest).map_err(|e| CapsuleError::io("Failed to create dest dir", e))?;
+
+    let mut flattener = Flattener::new(options.flatten);
+    let mut case_guard = CaseCollisionGuard::new(options.case_collision_policy.as_deref())?;
+    let mut overwrite_guard = OverwriteGuard::new(options.overwrite.as_deref())?;
+    let mut summary = ExtractSummary::default();
+    let space_guard = SpaceGuard::new(dest, options)?;
+    let progress = ProgressTracker::new(options, Some(reader.archive().files.len()));
+    let best_effort = options.best_effort.unwrap_or(false);
+
+    // `for_each_entries` wants its own error type, so ours is parked here and the walk stopped.
+    let mut error: Option<CapsuleError> = None;
+    let mut extract_one =
+        |entry: &SevenZArchiveEntry, data: &mut dyn Read| -> Result<u64, CapsuleError> {
+            check_cancelled(options.cancel.as_deref())?;
+            let mut name = entry.name().replace('\\', "/");
+            if entry.is_directory() && flattener.enabled {
+                return Ok(0);
+            }
+            if entry.is_directory() {
+                let outpath = validate_extract_path(dest, Path::new(&name))?;
+                fs::create_dir_all(&outpath).map_err(|e| CapsuleError::io("Dir create error", e))?;
+                return Ok(0);
+            }
+            let flat = flattener.resolve(&name, &mut summary);
+            let resolved = match case_guard.resolve(&flat, &mut summary)? {
+                Some(resolved) => overwrite_guard.resolve(dest, &resolved, &mut summary)?,
+                None => None,
+            };
+            match resolved {
+                Some(resolved) => name = resolved,
+                None => {
+                    io::copy(data, &mut io::sink())
+                        .map_err(|e| CapsuleError::io("7z read error", e))?;
+                    return Ok(0);
+                }
+            }
+            let outpath = validate_extract_path(dest, Path::new(&name))?;
+            if let Some(parent) = outpath.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| CapsuleError::io("Parent dir create error", e))?;
+            }
+            let result = stream_to_file(data, &outpath, options.buffer_size())
+                .map_err(|e| CapsuleError::io("7z read error", e));
+            match result {
+                Ok(bytes) => {
+                    summary.extracted += 1;
+                    if let Some(guard) = &space_guard {
+                        guard.record(&outpath, bytes)?;
+                    }
+                    summary.written.push(WrittenFile {
+                        entry: entry.name().to_string(),
+                        path: outpath,
+                        crc32: entry.has_crc.then_some(entry.crc as u32),
+                        size: Some(entry.size()),
+                    });
+                    Ok(bytes)
+                }
+                Err(error) if best_effort => {
+                    let _ = fs::remove_file(&outpath);
+                    summary.failed.push(FailedEntry {
+                        name,
+                        error: error.to_string(),
+                    });
+                    Ok(0)
+                }
+                Err(error) => {
+                    let _ = fs::remove_file(&outpath);
+                    Err(error)
+                }
+            }
+        };
+    reader
+        .for_each_entries(|entry, data| match extract_one(entry, data) {
+            Ok(bytes) => {
+                progress.entry_done(bytes);
+                Ok(true)
+            }
+            Err(e) => {
+                error = Some(e);
+                Ok(false)
+            }
+        })
+        .map_err(|e| CapsuleError::InvalidArchive(format!("7z read error: {e}")))?;
+
+    match error {
+        Some(e) => Err(e),
+        None => Ok(summary),
+    }
+}
+
+/// Helper: extract a RAR archive. Entries are decompressed in order, which also covers solid
+/// archives; sets split over several volumes are refused.
+fn extract_rar(
+    path: &Path,
+    dest: &Path,
+    options: &ExtractOptions,
+) -> Result<ExtractSummary, CapsuleError> {
+    let archive = match options.password.as_deref() {
+        Some(password) => unrar::Archive::with_password(path, password),
+        None => unrar::Archive::new(path),
+    };
+    let mut cursor = archive
+        .open_for_processing()
+        .map_err(|e| rar_error(e, "Invalid rar archive"))?;
+    if cursor.volume_info() != unrar::VolumeInfo::None {
+        return Err(CapsuleError::Unsupported(RAR_MULTIVOLUME.into()));
+    }
+
+    fs::create_dir_all(dest).map_err(|e| CapsuleError::io("Failed to create dest dir", e))?;
+
+    let mut flattener = Flattener::new(options.flatten);
+    let mut case_guard = CaseCollisionGuard::new(options.case_collision_policy.as_deref())?;
+    let mut overwrite_guard = OverwriteGuard::new(options.overwrite.as_deref())?;
+    let mut summary = ExtractSummary::default();
+    let space_guard = SpaceGuard::new(dest, options)?;
+    let progress = ProgressTracker::new(options, None);
+    let mut limits = ExtractLimits::new(options);
+
+    while let Some(header) = cursor
+        .read_header()
+        .map_err(|e| rar_error(e, "Rar entry error"))?
+    {
+        check_cancelled(options.cancel.as_deref())?;
+        let entry = header.entry();
+        if entry.is_split() {
+            return Err(CapsuleError::Unsupported(RAR_MULTIVOLUME.into()));
+        }
+        let name = entry.filename.to_string_lossy().replace('\\', "/");
+        let size = entry.unpacked_size;
+        limits.admit(&name, size, None)?;
+        let skip = |header: unrar::OpenArchive<unrar::Process, unrar::CursorBeforeFile>| {
+            header.skip().map_err(|e| rar_error(e, "Rar read error"))
+        };
+
+        if entry.is_directory() {
+            if !flattener.enabled {
+                let outpath = validate_extract_path(dest, Path::new(&name))?;
+                fs::create_dir_all(&outpath)
+                    .map_err(|e| CapsuleError::io("Dir create error", e))?;
+            }
+            cursor = skip(header)?;
+            continue;
+        }
+        let flat = flattener.resolve(&name, &mut summary);
+        let resolved = match case_guard.resolve(&flat, &mut summary)? {
+            Some(resolved) => overwrite_guard.resolve(dest, &resolved, &mut summary)?,
+            None => None,
+        };
+        let Some(resolved) = resolved else {
+            cursor = skip(header)?;
+            continue;
+        };
+
+        let outpath = validate_extract_path(dest, Path::new(&resolved))?;
+        if let Some(parent) = outpath.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| CapsuleError::io("Parent dir create error", e))?;
+        }
+        cursor = header.extract_to(&outpath).map_err(|e| {
+            let _ = fs::remove_file(&outpath);
+            rar_error(e, "Rar read error")
+        })?;
+        summary.extracted += 1;
+        if let Some(guard) = &space_guard {
+            guard.record(&outpath, size)?;
+        }
+        progress.entry_done(size);
+        // RAR5 may store a BLAKE2 hash instead of a CRC, so only the size is kept.
+        summary.written.push(WrittenFile {
+            entry: name,
+            path: outpath,
+            crc32: None,
+            size: Some(size),
+        });
+    }
+
+    Ok(summary)
+}
+
+/// How `add_path_to_zip` compresses file entries.
+#[derive(Debug, Clone, Copy)]
+struct ZipCompression {
+    method: CompressionMethod,
+    /// Requested level; clamped to what `method` accepts by `level()`.
+    level: Option<i32>,
+}
+
+impl Default for ZipCompression {
+    fn default() -> Self {
+        Self {
+            method: CompressionMethod::Deflated,
+            level: None,
+        }
+    }
+}
+
+impl ZipCompression {
+    /// The requested level clamped into `method`'s range; stored entries take none.
+    fn level(&self) -> Option<i32> {
+        let (min, max) = match self.method {
+            CompressionMethod::Stored => return None,
+            CompressionMethod::Bzip2 => (1, 9),
+            CompressionMethod::Zstd => (1, 22),
+            _ => (0, 9),
+        };
+        self.level.map(|level| level.clamp(min, max))
+    }
+}
+
+/// Writer surface `add_path_to_zip` needs, so the same directory walk can feed either the
+/// regular zip 0.6 writer or the encrypting `zip2` one.
+trait ZipSink: Write {
+    fn add_dir(&mut self, name: &str) -> Result<(), CapsuleError>;
+    fn start_entry(&mut self, name: &str, compression: ZipCompression) -> Result<(), CapsuleError>;
+}
+
+impl<W: Write + Seek> ZipSink for ZipWriter<W> {
+    fn add_dir(&mut self, name: &str) -> Result<(), CapsuleError> {
+        self.add_directory(
+            name,
+            FileOptions::default()
+                .compression_method(CompressionMethod::Deflated)
+                .unix_permissions(0o755),
+        )
+        .map_err(|e| CapsuleError::zip("Zip add dir error", e))
+    }
+
+    fn start_entry(&mut self, name: &str, compression: ZipCompression) -> Result<(), CapsuleError> {
+        self.start_file(
+            name,
+            FileOptions::default()
+                .compression_method(compression.method)
+                .compression_level(compression.level())
+                .unix_permissions(0o644),
+        )
+        .map_err(|e| CapsuleError::zip("Zip start file error", e))
+    }
+}
+
+/// Cipher for password-protected archives.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EncryptionMethod {
+    Aes256,
+    /// Legacy PKWARE encryption; weak, but readable by old tools.
+    ZipCrypto,
+}
+
+/// `zip2` writer that encrypts every file entry it starts.
+struct EncryptedZipWriter<W: Write + Seek> {
+    writer: zip2::ZipWriter<W>,
+    password: String,
+    method: EncryptionMethod,
+}
+
+impl<W: Write + Seek> Write for EncryptedZipWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W: Write + Seek> ZipSink for EncryptedZipWriter<W> {
+    fn add_dir(&mut self, name: &str) -> Result<(), CapsuleError> {
+        self.writer
+            .add_directory(name, zip2::write::SimpleFileOptions::default())
+            .map_err(|e| CapsuleError::Io(format!("Zip add dir error: {e}")))
+    }
+
+    fn start_entry(&mut self, name: &str, compression: ZipCompression) -> Result<(), CapsuleError> {
+        use zip2::unstable::write::FileOptionsExt;
+
+        // Only deflate is built into `zip2`; `create_zip_archive` warns about the fallback.
+        let (method, compression) = match compression.method {
+            CompressionMethod::Stored => (zip2::CompressionMethod::Stored, compression),
+            _ => (
+                zip2::CompressionMethod::Deflated,
+                ZipCompression {
+                    method: CompressionMethod::Deflated,
+                    ..compression
+                },
+            ),
+        };
+        let options = zip2::write::SimpleFileOptions::default()
+            .compression_method(method)
+            .compression_level(compression.level().map(i64::from))
+            .unix_permissions(0o644);
+        let options = match self.method {
+            EncryptionMethod::Aes256 => {
+                options.with_aes_encryption(zip2::AesMode::Aes256, &self.password)
+            }
+            EncryptionMethod::ZipCrypto => {
+                options.with_deprecated_encryption(self.password.as_bytes())
+            }
+        };
+        self.writer
+            .start_file(name, options)
+            .map_err(|e| CapsuleError::Io(format!("Zip start file error: {e}")))
+    }
+}
+
+/// Helper: emit directory entries for every ancestor of `name` (and `name` itself when it
+/// ends in `/`) not yet in `dirs`, so strict readers always see a directory before its contents.
+fn ensure_zip_dirs<S: ZipSink>(
+    writer: &mut S,
+    name: &str,
+    dirs: &mut HashSet<String>,
+) -> Result<(), CapsuleError> {
+    for (i, _) in name.match_indices('/') {
+        let dir = &name[..=i];
+        if !dirs.contains(dir) {
+            writer.add_dir(dir)?;
+            dirs.insert(dir.to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Recursively add a file or directory to a ZipWriter, compressing files as `compression` says.
+fn add_path_to_zip<S: ZipSink>(
+    writer: &mut S,
+    path: &Path,
+    base: &Path,
+    dirs: &mut HashSet<String>,
+    compression: ZipCompression,
+    excludes: &[glob::Pattern],
+    progress: &mut CreateProgressTracker,
+) -> Result<(), CapsuleError> {
+    let rel = path
+        .strip_prefix(base)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/");
+    if is_excluded(excludes, &rel) {
+        // Returning before the walk prunes an excluded directory's whole subtree.
+        return Ok(());
+    }
+
+    if path.is_dir() {
+        // The input directory itself is the archive root and gets no entry.
+        if !rel.is_empty() {
+            let name = if rel.ends_with('/') {
+                rel
+            } else {
+                format!("{rel}/")
+            };
+            ensure_zip_dirs(writer, &name, dirs)?;
+        }
+        let mut children = fs::read_dir(path)
+            .map_err(|e| CapsuleError::io("Read dir error", e))?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| CapsuleError::io("Dir entry error", e))?;
+        children.sort();
+        for child in children {
+            add_path_to_zip(writer, &child, base, dirs, compression, excludes, progress)?;
+        }
+    } else {
+        add_file_to_zip(writer, path, &rel, dirs, compression, progress)?;
+    }
+
+    Ok(())
+}
+
+/// Helper: stream the file at `path` into a new entry `name`, after its parent directories.
+fn add_file_to_zip<S: ZipSink>(
+    writer: &mut S,
+    path: &Path,
+    name: &str,
+    dirs: &mut HashSet<String>,
+    compression: ZipCompression,
+    progress: &mut CreateProgressTracker,
+) -> Result<(), CapsuleError> {
+    let mut file = File::open(path).map_err(|e| CapsuleError::open("Open file error", e))?;
+    ensure_zip_dirs(writer, name, dirs)?;
+    writer.start_entry(name, compression)?;
+    let bytes =
+        io::copy(&mut file, writer).map_err(|e| CapsuleError::io("Zip file copy error", e))?;
+    progress.file_done(bytes);
+    Ok(())
+}
+
+/// Helper: add each existing input path; a directory's contents land at the archive root,
+/// a file goes in by its name. Directory entries shared by several inputs are written once.
+fn add_inputs_to_zip<S: ZipSink>(
+    writer: &mut S,
+    inputs: &[String],
+    compression: ZipCompression,
+    excludes: &[glob::Pattern],
+    progress: &mut CreateProgressTracker,
+) -> Result<(), CapsuleError> {
+    let mut dirs = HashSet::new();
+    for input in inputs {
+        let path = PathBuf::from(input);
+        if !path.exists() {
+            continue;
+        }
+
+        let base = if path.is_dir() {
+            path.clone()
+        } else {
+            path.parent().unwrap_or(&path).to_path_buf()
+        };
+
+        add_path_to_zip(
+            writer,
+            &path,
+            &base,
+            &mut dirs,
+            compression,
+            excludes,
+            progress,
+        )?;
+    }
+    Ok(())
+}
+
+/// Most files compressed per parallel batch.
+const PARALLEL_COMPRESS_BATCH: usize = 64;
+
+/// Most input bytes per parallel batch. A batch's compressed files are all held in memory
+/// until they're written, so this is what bounds memory; larger files are streamed alone.
+const PARALLEL_COMPRESS_BATCH_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Something `add_inputs_to_zip` writes, in archive order.
+enum ZipInput {
+    /// Directory entry name, ending in `/`.
+    Dir(String),
+    /// File on disk and its entry name.
+    File(PathBuf, String),
+}
+
+/// Helper: compile `CreateZipArgs::exclude_patterns`.
+fn compile_excludes(patterns: &[String]) -> Result<Vec<glob::Pattern>, CapsuleError> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            glob::Pattern::new(pattern)
+                .map_err(|e| CapsuleError::Other(format!("Invalid exclude pattern {pattern}: {e}")))
+        })
+        .collect()
+}
+
+/// Helper: whether `rel` (a path relative to its input's base) matches an exclude pattern.
+/// Patterns are tried on the whole path and on its last component, so `node_modules` or
+/// `.git` skips that directory at any depth.
+fn is_excluded(excludes: &[glob::Pattern], rel: &str) -> bool {
+    if rel.is_empty() {
+        return false;
+    }
+    let name = rel.rsplit('/').next().unwrap_or(rel);
+    excludes
+        .iter()
+        .any(|pattern| pattern.matches(rel) || pattern.matches(name))
+}
+
+/// Helper: the entries `add_path_to_zip` would write for `path`, without writing them.
+fn plan_zip_path(
+    path: &Path,
+    base: &Path,
+    excludes: &[glob::Pattern],
+    plan: &mut Vec<ZipInput>,
+) -> Result<(), CapsuleError> {
+    let rel = path
+        .strip_prefix(base)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/");
+    if is_excluded(excludes, &rel) {
+        return Ok(());
+    }
+
+    if path.is_dir() {
+        if !rel.is_empty() {
+            let name = if rel.ends_with('/') {
+                rel
+            } else {
+                format!("{rel}/")
+            };
+            plan.push(ZipInput::Dir(name));
+        }
+        let mut children = fs::read_dir(path)
+            .map_err(|e| CapsuleError::io("Read dir error", e))?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| CapsuleError::io("Dir entry error", e))?;
+        children.sort();
+        for child in children {
+            plan_zip_path(&child, base, excludes, plan)?;
+        }
+    } else {
+        plan.push(ZipInput::File(path.to_path_buf(), rel));
+    }
+
+    Ok(())
+}
+
+/// Helper: `plan_zip_path` over every existing input, with the same bases as `add_inputs_to_zip`.
+fn plan_zip_inputs(
+    inputs: &[String],
+    excludes: &[glob::Pattern],
+) -> Result<Vec<ZipInput>, CapsuleError> {
+    let mut plan = Vec::new();
+    for input in inputs {
+        let path = PathBuf::from(input);
+        if !path.exists() {
+            continue;
+        }
+
+        let base = if path.is_dir() {
+            path.clone()
+        } else {
+            path.parent().unwrap_or(&path).to_path_buf()
+        };
+
+        plan_zip_path(&path, &base, excludes, &mut plan)?;
+    }
+    Ok(plan)
+}
+
+/// Helper: file count and total size of `inputs`, for `create://progress` totals.
+fn zip_input_totals(
+    inputs: &[String],
+    excludes: &[glob::Pattern],
+) -> Result<(usize, u64), CapsuleError> {
+    let mut files = 0;
+    let mut bytes = 0;
+    for input in plan_zip_inputs(inputs, excludes)? {
+        if let ZipInput::File(path, _) = input {
+            files += 1;
+            bytes += fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        }
+    }
+    Ok((files, bytes))
+}
+
+/// Helper: compress one file into a single-entry in-memory zip, ready for `raw_copy_file`.
+fn compress_to_memory(
+    path: &Path,
+    name: &str,
+    compression: ZipCompression,
+) -> Result<Vec<u8>, CapsuleError> {
+    let mut file = File::open(path).map_err(|e| CapsuleError::open("Open file error", e))?;
+    let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+    writer.start_entry(name, compression)?;
+    io::copy(&mut file, &mut writer).map_err(|e| CapsuleError::io("Zip file copy error", e))?;
+    let buffer = writer
+        .finish()
+        .map_err(|e| CapsuleError::zip("Failed to finalize compressed entry", e))?;
+    Ok(buffer.into_inner())
+}
+
+/// Helper: how many leading `plan` inputs to compress as one batch: at most
+/// `PARALLEL_COMPRESS_BATCH` of them and `max_bytes` of file data (sizes as found on disk
+/// now). 0 means the first input is a file over `max_bytes`, to be streamed on its own.
+fn parallel_batch_len(plan: &[ZipInput], max_bytes: u64) -> usize {
+    let mut bytes = 0u64;
+    for (i, input) in plan.iter().take(PARALLEL_COMPRESS_BATCH).enumerate() {
+        let size = match input {
+            ZipInput::File(path, _) => fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+            ZipInput::Dir(_) => 0,
+        };
+        bytes = bytes.saturating_add(size);
+        if bytes > max_bytes {
+            return i;
+        }
+    }
+    plan.len().min(PARALLEL_COMPRESS_BATCH)
+}
+
+/// Parallel `add_inputs_to_zip`: files are compressed by `workers` threads a batch at a time,
+/// then copied into `writer` raw and in walk order, so the archive matches a sequential one.
+/// Batches are capped by `PARALLEL_COMPRESS_BATCH_BYTES` of input, and a file over that is
+/// compressed on this thread straight into `writer`.
+fn add_inputs_to_zip_parallel<W: Write + Seek>(
+    writer: &mut ZipWriter<W>,
+    inputs: &[String],
+    compression: ZipCompression,
+    workers: usize,
+    excludes: &[glob::Pattern],
+    progress: &mut CreateProgressTracker,
+) -> Result<(), CapsuleError> {
+    let plan = plan_zip_inputs(inputs, excludes)?;
+
+    let mut dirs = HashSet::new();
+    let mut rest = &plan[..];
+    while !rest.is_empty() {
+        let len = parallel_batch_len(rest, PARALLEL_COMPRESS_BATCH_BYTES);
+        if len == 0 {
+            if let ZipInput::File(path, name) = &rest[0] {
+                add_file_to_zip(writer, path, name, &mut dirs, compression, progress)?;
+            }
+            rest = &rest[1..];
+            continue;
+        }
+        let (batch, after) = rest.split_at(len);
+        rest = after;
+        let next = AtomicUsize::new(0);
+        let slots: Vec<_> = batch.iter().map(|_| Mutex::new(None)).collect();
+        std::thread::scope(|scope| {
+            for _ in 0..workers.min(batch.len()) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(input) = batch.get(i) else {
+                        break;
+                    };
+                    if let ZipInput::File(path, name) = input {
+                        let compressed = compress_to_memory(path, name, compression);
+                        if let Ok(mut slot) = slots[i].lock() {
+                            *slot = Some(compressed);
+                        }
+                    }
+                });
+            }
+        });
+
+        for (input, slot) in batch.iter().zip(slots) {
+            match input {
+                ZipInput::Dir(name) => ensure_zip_dirs(writer, name, &mut dirs)?,
+                ZipInput::File(_, name) => {
+                    let compressed = slot
+                        .into_inner()
+                        .map_err(|_| {
+                            CapsuleError::Other("Compression result lock poisoned".into())
+                        })?
+                        .ok_or_else(|| {
+                            CapsuleError::Other(format!("No compressed data for {name}"))
+                        })??;
+                    ensure_zip_dirs(writer, name, &mut dirs)?;
+                    let mut single = ZipArchive::new(io::Cursor::new(compressed)).map_err(|e| {
+                        CapsuleError::InvalidArchive(format!("Invalid compressed entry: {e}"))
+                    })?;
+                    let entry = single
+                        .by_index_raw(0)
+                        .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+                    let bytes = entry.size();
+                    writer
+                        .raw_copy_file(entry)
+                        .map_err(|e| CapsuleError::zip("Zip raw copy error", e))?;
+                    progress.file_done(bytes);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Depth limit for `find_archives` when none is given.
+const FIND_ARCHIVES_MAX_DEPTH: usize = 32;
+
+/// An archive found by `find_archives`.
+#[derive(Debug, Serialize)]
+pub struct FoundArchive {
+    pub path: String,
+    pub kind: String,
+    pub size: u64,
+}
+
+/// Archives under a directory, plus directories that couldn't be read.
+#[derive(Debug, Default, Serialize)]
+pub struct FoundArchives {
+    pub archives: Vec<FoundArchive>,
+    pub warnings: Vec<String>,
+}
+
+/// Helper: archive kind from the file's leading bytes. Compressed streams are reported as
+/// their tar variant; `archive_type` works out whether they hold a single file instead.
+fn sniff_archive_type(path: &Path) -> Option<&'static str> {
+    let mut head = [0u8; 262];
+    let mut file = File::open(path).ok()?;
+    let mut n = 0;
+    // `read` may return short; keep going until the buffer is full or the file ends.
+    while n < head.len() {
+        match file.read(&mut head[n..]) {
+            Ok(0) => break,
+            Ok(read) => n += read,
+            Err(_) => return None,
+        }
+    }
+    let head = &head[..n];
+    if head.starts_with(b"PK\x03\x04") || head.starts_with(b"PK\x05\x06") {
+        Some("zip")
+    } else if head.starts_with(&[0x37, 0x7a, 0xbc, 0xaf, 0x27, 0x1c]) {
+        Some("7z")
+    } else if head.starts_with(b"Rar!\x1a\x07") {
+        Some("rar")
+    } else if head.starts_with(&[0x1f, 0x8b]) {
+        Some("tar.gz")
+    } else if head.starts_with(b"BZh") {
+        Some("tar.bz2")
+    } else if head.starts_with(&[0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00]) {
+        Some("tar.xz")
+    } else if head.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Some("tar.zst")
+    } else if head.len() >= 262 && &head[257..262] == b"ustar" {
+        Some("tar")
+    } else {
+        None
+    }
+}
+
+/// Helper: archive kind for opening `path`. The magic bytes win when they disagree with the
+/// extension (mislabeled or extensionless files); with no recognizable signature, the
+/// extension decides.
+pub(crate) fn archive_type(path: &Path) -> &'static str {
+    let by_name = detect_archive_type(path);
+    let Some(sniffed) = sniff_archive_type(path) else {
+        return by_name;
+    };
+    let single = match sniffed {
+        "tar.gz" => "gz",
+        "tar.bz2" => "bz2",
+        "tar.xz" => "xz",
+        "tar.zst" => "zst",
+        _ => return sniffed,
+    };
+    // The magic only names the compression; the extension, or failing that the
+    // decompressed header, tells a tarball from a single compressed file.
+    if by_name == sniffed || by_name == single {
+        by_name
+    } else if holds_tar(path, sniffed) {
+        sniffed
+    } else {
+        single
+    }
+}
+
+/// Helper: whether the compressed stream in `path` starts with a ustar header.
+fn holds_tar(path: &Path, kind: &str) -> bool {
+    let mut head = [0u8; 262];
+    open_tar_reader(path, kind)
+        .and_then(|archive| {
+            archive
+                .into_inner()
+                .read_exact(&mut head)
+                .map_err(|e| CapsuleError::io("Failed to read archive", e))
+        })
+        .is_ok()
+        && &head[257..262] == b"ustar"
+}
+
+/// List supported archives under `dir` (by extension, else by sniffing the header), for
+/// batch workflows. Descends at most `max_depth` levels when `recursive`; unreadable
+/// directories are skipped and reported in `warnings`.
+#[tauri::command]
+pub async fn find_archives(
+    dir: String,
+    recursive: bool,
+    max_depth: Option<usize>,
+) -> Result<FoundArchives, CapsuleError> {
+    let root = PathBuf::from(&dir);
+    if !root.is_dir() {
+        return Err(CapsuleError::Other(format!("Not a directory: {dir}")));
+    }
+    let max_depth = if recursive {
+        max_depth.unwrap_or(FIND_ARCHIVES_MAX_DEPTH)
+    } else {
+        0
+    };
+
+    let mut found = FoundArchives::default();
+    let mut pending = vec![(root, 0)];
+    while let Some((current, depth)) = pending.pop() {
+        let entries = match fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(e) => {
+                found
+                    .warnings
+                    .push(format!("Skipped {}: {e}", current.display()));
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                if depth < max_depth {
+                    pending.push((path, depth + 1));
+                }
+                continue;
+            }
+            let kind = match archive_type(&path) {
+                "unknown" => continue,
+                kind => kind,
+            };
+            found.archives.push(FoundArchive {
+                path: path.to_string_lossy().to_string(),
+                kind: kind.to_string(),
+                size: meta.len(),
+            });
+        }
+    }
+
+    found.archives.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(found)
+}
+
+/// Helper: open a tar-family archive behind the decompressor matching `kind`.
+fn open_tar_reader(path: &Path, kind: &str) -> Result<TarArchive<Box<dyn Read>>, CapsuleError> {
+    let file =
+        || File::open(path).map_err(|e| CapsuleError::open(format!("Failed to open {kind}"), e));
+    let reader: Box<dyn Read> = match kind {
+        "tar" => Box::new(file()?),
+        "tar.gz" => Box::new(GzDecoder::new(file()?)),
+        "tar.bz2" => Box::new(BzDecoder::new(file()?)),
+        "tar.xz" => Box::new(XzDecoder::new(file()?)),
+        "tar.zst" => Box::new(
+            zstd::Decoder::new(file()?)
+                .map_err(|e| CapsuleError::open(format!("Failed to open {kind}"), e))?,
+        ),
+        _ => return Err(CapsuleError::Unsupported("Unsupported archive type".into())),
+    };
+    Ok(TarArchive::new(reader))
+}
+
+/// Helper: decompressor for a single gzip/bzip2/xz/zstd compressed file (`kind` "gz", "bz2",
+/// "xz" or "zst"). Multi-member streams, as produced by appending to compressed logs, read
+/// through.
+fn open_compressed_file(path: &Path, kind: &str) -> Result<Box<dyn Read>, CapsuleError> {
+    let file =
+        File::open(path).map_err(|e| CapsuleError::open(format!("Failed to open {kind}"), e))?;
+    Ok(match kind {
+        "gz" => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        "bz2" => Box::new(bzip2::read::MultiBzDecoder::new(file)),
+        "xz" => Box::new(XzDecoder::new_multi_decoder(file)),
+        "zst" => Box::new(
+            zstd::Decoder::new(file)
+                .map_err(|e| CapsuleError::open(format!("Failed to open {kind}"), e))?,
+        ),
+        _ => return Err(CapsuleError::Unsupported("Unsupported archive type".into())),
+    })
+}
+
+/// Helper: the one entry a single compressed file holds: its file name without the
+/// compression extension.
+fn compressed_file_entry_name(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let lower = name.to_lowercase();
+    [".gz", ".bz2", ".xz", ".zst"]
+        .iter()
+        .find(|ext| lower.ends_with(*ext) && lower.len() > ext.len())
+        .map(|ext| name[..name.len() - ext.len()].to_string())
+        .unwrap_or(name)
+}
+
+/// List a single compressed file as one entry. The stream has to be decompressed to learn
+/// its size, since only gzip records it (and only modulo 4 GiB).
+fn open_compressed(path: &Path, kind: &str) -> Result<Vec<CapsuleEntry>, CapsuleError> {
+    let mut reader = open_compressed_file(path, kind)?;
+    let size = io::copy(&mut reader, &mut io::sink())
+        .map_err(|e| CapsuleError::InvalidArchive(format!("Failed to decompress {kind}: {e}")))?;
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| format_epoch(d.as_secs() as i64));
+    let name = compressed_file_entry_name(path);
+
+    Ok(vec![CapsuleEntry {
+        name: name.clone(),
+        size,
+        kind: "file".to_string(),
+        path: name,
+        modified,
+        crc32: None,
+        compressed_size: None,
+        method: None,
+        name_lossy: false,
+    }])
+}
+
+/// Helper: list entries of any supported archive.
+fn list_archive(path: &Path) -> Result<Vec<CapsuleEntry>, CapsuleError> {
+    list_archive_with_encoding(path, NameEncoding::default())
+}
+
+/// `list_archive` with an explicit fallback for non-UTF-8 zip and tar entry names.
+fn list_archive_with_encoding(
+    path: &Path,
+    encoding: NameEncoding,
+) -> Result<Vec<CapsuleEntry>, CapsuleError> {
+    match archive_type(path) {
+        "zip" => open_zip(path, encoding),
+        "7z" => open_7z(path),
+        "rar" => open_rar(path),
+        kind @ ("gz" | "bz2" | "xz" | "zst") => open_compressed(path, kind),
+        kind => open_tar_like(open_tar_reader(path, kind)?, encoding),
+    }
+}
+
+/// Open an archive and list entries for the UI. Encrypted zips list without a password;
+/// when one is given it is checked up front so a wrong password fails here.
+/// `name_encoding` ("cp437", the default, or "latin1") decodes names that aren't UTF-8.
+#[tauri::command]
+pub async fn open_archive(
+    app: AppHandle,
+    cache: State<'_, ArchiveCache>,
+    path: String,
+    password: Option<String>,
+    name_encoding: Option<String>,
+) -> Result<Vec<CapsuleEntry>, CapsuleError> {
+    let entries = open_archive_cached(&cache, path.clone(), password, name_encoding)?;
+    // The recent list is a convenience; failing to record it shouldn't fail the open.
+    if let Ok(recent) = recent_files_path(&app).and_then(|file| record_recent_file(&file, &path)) {
+        let _ = crate::menu::refresh_recent_menu(&app, &recent);
+    }
+    Ok(entries)
+}
+
+/// Helper: `open_archive` without the Tauri state wrapper.
+fn open_archive_cached(
+    cache: &ArchiveCache,
+    path: String,
+    password: Option<String>,
+    name_encoding: Option<String>,
+) -> Result<Vec<CapsuleEntry>, CapsuleError> {
+    let path_buf = PathBuf::from(&path);
+    let encoding = NameEncoding::parse(name_encoding.as_deref())?;
+    if let (Some(password), "zip") = (&password, archive_type(&path_buf)) {
+        check_zip_password(&path_buf, password)?;
+    }
+    // Sidecars are built with the default encoding.
+    if encoding == NameEncoding::default() {
+        if let Some(index) = load_index(&path_buf) {
+            return Ok(index.entries.into_iter().map(|e| e.entry).collect());
+        }
+    }
+    if archive_type(&path_buf) == "zip" {
+        return cache.with_zip(&path_buf, |archive| zip_listing(archive, encoding));
+    }
+    list_archive_with_encoding(&path_buf, encoding)
+}
+
+/// How many paths the recent-files list keeps.
+const RECENT_FILES_LIMIT: usize = 15;
+
+/// Helper: `recent.json` in the app config dir.
+fn recent_files_path<R: tauri::Runtime>(app: &AppHandle<R>) -> Result<PathBuf, CapsuleError> {
+    let dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| CapsuleError::Io(format!("Failed to resolve config dir: {e}")))?;
+    Ok(dir.join("recent.json"))
+}
+
+/// Helper: the stored recent list, most recent first; empty if missing or unreadable.
+fn read_recent_files(file: &Path) -> Vec<String> {
+    fs::read(file)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .unwrap_or_default()
+}
+
+/// Helper: the persisted recent list, for building the Open Recent menu.
+pub(crate) fn stored_recent_files<R: tauri::Runtime>(app: &AppHandle<R>) -> Vec<String> {
+    recent_files_path(app)
+        .map(|file| read_recent_files(&file))
+        .unwrap_or_default()
+}
+
+/// Helper: move `path` (canonicalized when it exists) to the front of the stored list,
+/// dropping duplicates and anything past `RECENT_FILES_LIMIT`. Returns the new list.
+fn record_recent_file(file: &Path, path: &str) -> Result<Vec<String>, CapsuleError> {
+    let path = fs::canonicalize(path)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| path.to_string());
+    let mut recent = read_recent_files(file);
+    recent.retain(|p| *p != path);
+    recent.insert(0, path);
+    recent.truncate(RECENT_FILES_LIMIT);
+
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| CapsuleError::io("Failed to create config dir", e))?;
+    }
+    let json = serde_json::to_vec_pretty(&recent)
+        .map_err(|e| CapsuleError::Other(format!("Failed to encode recent files: {e}")))?;
+    fs::write(file, json).map_err(|e| CapsuleError::io("Failed to write recent files", e))?;
+    Ok(recent)
+}
+
+/// Recently opened archive paths, most recent first.
+#[tauri::command]
+pub async fn get_recent_files(app: AppHandle) -> Result<Vec<String>, CapsuleError> {
+    Ok(read_recent_files(&recent_files_path(&app)?))
+}
+
+/// Record `path` as the most recently opened archive. Returns the updated list.
+#[tauri::command]
+pub async fn push_recent_file(app: AppHandle, path: String) -> Result<Vec<String>, CapsuleError> {
+    let recent = record_recent_file(&recent_files_path(&app)?, &path)?;
+    crate::menu::refresh_recent_menu(&app, &recent)
+        .map_err(|e| CapsuleError::Other(format!("Failed to update menu: {e}")))?;
+    Ok(recent)
+}
+
+/// How many parsed zips `ArchiveCache` keeps.
+const ARCHIVE_CACHE_SIZE: usize = 8;
+
+/// Managed state holding a small LRU of parsed zips keyed by canonical path, so browsing,
+/// paging and previewing don't reparse the central directory on every call. A cached zip is
+/// only reused while the file's modification time and size are unchanged.
+#[derive(Default)]
+pub struct ArchiveCache(Mutex<Vec<CachedZip>>);
+
+/// A zip held by `ArchiveCache`; the list runs from least to most recently used.
+struct CachedZip {
+    path: PathBuf,
+    stamp: (Option<std::time::SystemTime>, u64),
+    archive: ZipArchive<File>,
+}
+
+impl ArchiveCache {
+    /// Run `f` on the zip at `path`, reusing the cached handle when the file hasn't changed.
+    /// The handle is taken out of the cache while `f` runs, so the lock isn't held across
+    /// reads; concurrent calls on the same zip just open their own.
+    fn with_zip<T>(
+        &self,
+        path: &Path,
+        f: impl FnOnce(&mut ZipArchive<File>) -> Result<T, CapsuleError>,
+    ) -> Result<T, CapsuleError> {
+        let canonical =
+            fs::canonicalize(path).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+        let meta =
+            fs::metadata(&canonical).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+        let stamp = (meta.modified().ok(), meta.len());
+
+        let cached = {
+            let mut zips = self.lock()?;
+            let pos = zips.iter().position(|zip| zip.path == canonical);
+            pos.map(|pos| zips.remove(pos))
+        };
+        let mut archive = match cached {
+            Some(zip) if zip.stamp == stamp => zip.archive,
+            _ => {
+                let file = File::open(&canonical)
+                    .map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+                ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?
+            }
+        };
+        let result = f(&mut archive);
+
+        let mut zips = self.lock()?;
+        zips.retain(|zip| zip.path != canonical);
+        zips.push(CachedZip {
+            path: canonical,
+            stamp,
+            archive,
+        });
+        if zips.len() > ARCHIVE_CACHE_SIZE {
+            zips.remove(0);
+        }
+        result
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Vec<CachedZip>>, CapsuleError> {
+        self.0
+            .lock()
+            .map_err(|_| CapsuleError::Other("Archive cache poisoned".into()))
+    }
+}
+
+/// One page of an archive listing plus the total entry count.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivePage {
+    pub entries: Vec<CapsuleEntry>,
+    pub total: usize,
+}
+
+/// List `limit` entries starting at `offset`, for rendering huge archives incrementally.
+#[tauri::command]
+pub async fn open_archive_page(
+    cache: State<'_, ArchiveCache>,
+    path: String,
+    offset: usize,
+    limit: usize,
+) -> Result<ArchivePage, CapsuleError> {
+    archive_page(&cache, Path::new(&path), offset, limit)
+}
+
+/// Helper: `open_archive_page` without the Tauri state wrapper. Only zips are paged from the
+/// central directory; other kinds are listed in full and sliced.
+fn archive_page(
+    cache: &ArchiveCache,
+    path: &Path,
+    offset: usize,
+    limit: usize,
+) -> Result<ArchivePage, CapsuleError> {
+    if archive_type(path) != "zip" {
+        let entries = list_archive(path)?;
+        let total = entries.len();
+        return Ok(ArchivePage {
+            entries: entries.into_iter().skip(offset).take(limit).collect(),
+            total,
+        });
+    }
+
+    cache.with_zip(path, |archive| {
+        let total = archive.len();
+        let end = offset.saturating_add(limit).min(total);
+        let entries = (offset.min(end)..end)
+            .map(|i| zip_capsule_entry(archive, i, NameEncoding::default()))
+            .collect::<Result<_, CapsuleError>>()?;
+        Ok(ArchivePage { entries, total })
+    })
+}
+
+/// Entries whose path contains `query`, or, when it has wildcards (`*`, `?`, `[`), whose
+/// path or file name matches it as a glob. Saves shipping a huge listing to the UI.
+#[tauri::command]
+pub async fn search_archive(
+    path: String,
+    query: String,
+    case_sensitive: bool,
+) -> Result<Vec<CapsuleEntry>, CapsuleError> {
+    let path_buf = PathBuf::from(&path);
+    let entries = match load_index(&path_buf) {
+        Some(index) => index.entries.into_iter().map(|e| e.entry).collect(),
+        None => list_archive(&path_buf)?,
+    };
+    let query = EntryQuery::new(&query, case_sensitive)?;
+    Ok(entries
+        .into_iter()
+        .filter(|e| query.matches(&e.path))
+        .collect())
+}
+
+/// Helper: what `search_archive` filters entry paths with.
+enum EntryQuery {
+    Glob(glob::Pattern, glob::MatchOptions),
+    /// Substring; lowercased up front when the search ignores case.
+    Text(String, bool),
+}
+
+impl EntryQuery {
+    fn new(query: &str, case_sensitive: bool) -> Result<Self, CapsuleError> {
+        if query.contains(['*', '?', '[']) {
+            let pattern = glob::Pattern::new(query)
+                .map_err(|e| CapsuleError::Other(format!("Invalid search pattern {query}: {e}")))?;
+            let options = glob::MatchOptions {
+                case_sensitive,
+                ..Default::default()
+            };
+            Ok(Self::Glob(pattern, options))
+        } else if case_sensitive {
+            Ok(Self::Text(query.to_string(), true))
+        } else {
+            Ok(Self::Text(query.to_lowercase(), false))
+        }
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        match self {
+            Self::Glob(pattern, options) => {
+                let path = path.trim_end_matches('/');
+                let name = path.rsplit('/').next().unwrap_or(path);
+                pattern.matches_with(path, *options) || pattern.matches_with(name, *options)
+            }
+            Self::Text(query, true) => path.contains(query.as_str()),
+            Self::Text(query, false) => path.to_lowercase().contains(query.as_str()),
+        }
+    }
+}
+
+/// Sum of the uncompressed entry sizes, i.e. roughly the disk space a full extraction needs.
+/// Only sizes are read; no entry list is built.
+#[tauri::command]
+pub async fn archive_total_size(path: String) -> Result<u64, CapsuleError> {
+    let path_buf = PathBuf::from(&path);
+    match archive_type(&path_buf) {
+        "zip" => {
+            let file =
+                File::open(&path_buf).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+            let mut archive =
+                ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+            let mut total: u64 = 0;
+            for i in 0..archive.len() {
+                total = total.saturating_add(
+                    archive
+                        .by_index_raw(i)
+                        .map_err(|e| CapsuleError::zip("Zip entry error", e))?
+                        .size(),
+                );
+            }
+            Ok(total)
+        }
+        "7z" => {
+            let reader = SevenZReader::open(&path_buf, Password::empty())
+                .map_err(|e| CapsuleError::InvalidArchive(format!("Invalid 7z archive: {e}")))?;
+            Ok(reader
+                .archive()
+                .files
+                .iter()
+                .map(|f| f.size())
+                .fold(0, u64::saturating_add))
+        }
+        "rar" => Ok(open_rar(&path_buf)?
+            .iter()
+            .map(|e| e.size)
+            .fold(0, u64::saturating_add)),
+        kind @ ("gz" | "bz2" | "xz" | "zst") => {
+            let mut reader = open_compressed_file(&path_buf, kind)?;
+            io::copy(&mut reader, &mut io::sink())
+                .map_err(|e| {
+                    CapsuleError::InvalidArchive(format!("Failed to decompress {kind}: {e}"))
+                })
+        }
+        kind => {
+            let mut archive = open_tar_reader(&path_buf, kind)?;
+            let tar_entries = archive.entries().map_err(|e| {
+                CapsuleError::InvalidArchive(format!("Failed to read tar entries: {e}"))
+            })?;
+            let mut total: u64 = 0;
+            for entry_res in tar_entries {
+                let entry = entry_res
+                    .map_err(|e| CapsuleError::InvalidArchive(format!("Tar entry error: {e}")))?;
+                if tar_kind(entry.header()) == "file" {
+                    total = total.saturating_add(entry.size());
+                }
+            }
+            Ok(total)
+        }
+    }
+}
+
+/// Number of entries (files and directories), for UI hints before loading the listing.
+/// Zips read only the entry count from the (cached) central directory; tar headers are
+/// walked without reading the bodies.
+#[tauri::command]
+pub async fn archive_entry_count(
+    cache: State<'_, ArchiveCache>,
+    path: String,
+) -> Result<usize, CapsuleError> {
+    count_archive_entries(&cache, Path::new(&path))
+}
+
+/// Helper: `archive_entry_count` without the Tauri state wrapper.
+fn count_archive_entries(cache: &ArchiveCache, path: &Path) -> Result<usize, CapsuleError> {
+    match archive_type(path) {
+        "zip" => cache.with_zip(path, |archive| Ok(archive.len())),
+        "7z" => {
+            let reader = SevenZReader::open(path, Password::empty())
+                .map_err(|e| CapsuleError::InvalidArchive(format!("Invalid 7z archive: {e}")))?;
+            Ok(reader.archive().files.len())
+        }
+        "rar" => Ok(open_rar(path)?.len()),
+        "gz" | "bz2" | "xz" | "zst" => Ok(1),
+        kind => {
+            let mut archive = open_tar_reader(path, kind)?;
+            let tar_entries = archive.entries().map_err(|e| {
+                CapsuleError::InvalidArchive(format!("Failed to read tar entries: {e}"))
+            })?;
+            let mut count = 0;
+            for entry_res in tar_entries {
+                entry_res
+                    .map_err(|e| CapsuleError::InvalidArchive(format!("Tar entry error: {e}")))?;
+                count += 1;
+            }
+            Ok(count)
+        }
+    }
+}
+
+/// Entry paths only, in archive order, for "Copy file list". Skips building full listing
+/// rows; no entry bodies are read.
+#[tauri::command]
+pub async fn list_entry_paths(
+    cache: State<'_, ArchiveCache>,
+    path: String,
+) -> Result<Vec<String>, CapsuleError> {
+    archive_entry_paths(&cache, Path::new(&path))
+}
+
+/// Helper: `list_entry_paths` without the Tauri state wrapper.
+fn archive_entry_paths(cache: &ArchiveCache, path: &Path) -> Result<Vec<String>, CapsuleError> {
+    match archive_type(path) {
+        "zip" => cache.with_zip(path, |archive| {
+            (0..archive.len())
+                .map(|i| {
+                    archive
+                        .by_index_raw(i)
+                        .map(|entry| decode_entry_name(entry.name_raw(), NameEncoding::default()).0)
+                        .map_err(|e| CapsuleError::zip("Zip entry error", e))
+                })
+                .collect()
+        }),
+        "7z" => {
+            let reader = SevenZReader::open(path, Password::empty())
+                .map_err(|e| CapsuleError::InvalidArchive(format!("Invalid 7z archive: {e}")))?;
+            Ok(reader
+                .archive()
+                .files
+                .iter()
+                .map(|entry| entry.name().replace('\\', "/"))
+                .collect())
+        }
+        "rar" => Ok(open_rar(path)?
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect()),
+        "gz" | "bz2" | "xz" | "zst" => Ok(vec![compressed_file_entry_name(path)]),
+        kind => {
+            let mut archive = open_tar_reader(path, kind)?;
+            let tar_entries = archive.entries().map_err(|e| {
+                CapsuleError::InvalidArchive(format!("Failed to read tar entries: {e}"))
+            })?;
+            let mut paths = Vec::new();
+            for entry_res in tar_entries {
+                let entry = entry_res
+                    .map_err(|e| CapsuleError::InvalidArchive(format!("Tar entry error: {e}")))?;
+                paths.push(decode_entry_name(&entry.path_bytes(), NameEncoding::default()).0);
+            }
+            Ok(paths)
+        }
+    }
+}
+
+/// Bumped whenever the `.capidx` layout changes; older sidecars are ignored.
+const CAPIDX_VERSION: u32 = 4;
+
+/// One entry in a `.capidx` sidecar: the listing plus where its bytes live.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedEntry {
+    #[serde(flatten)]
+    pub entry: CapsuleEntry,
+    pub header_offset: u64,
+    /// Bytes stored after the header. Serialized as `storedSize` so it doesn't clash with
+    /// the flattened entry's own `compressedSize`.
+    #[serde(rename = "storedSize")]
+    pub compressed_size: u64,
+}
+
+/// Contents of `<archive>.capidx`, tied to the archive's size and mtime at build time.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveIndex {
+    pub version: u32,
+    pub archive_size: u64,
+    pub archive_mtime_secs: u64,
+    pub archive_mtime_nanos: u32,
+    pub entries: Vec<IndexedEntry>,
+}
+
+/// Helper: the sidecar path for an archive (`<archive>.capidx`).
+fn index_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".capidx");
+    PathBuf::from(name)
+}
+
+/// Helper: archive size and mtime, which a sidecar must match to be trusted.
+fn archive_stamp(path: &Path) -> Result<(u64, u64, u32), CapsuleError> {
+    let meta = fs::metadata(path).map_err(|e| CapsuleError::open("Failed to stat archive", e))?;
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    Ok((meta.len(), mtime.as_secs(), mtime.subsec_nanos()))
+}
+
+/// Helper: the sidecar index, if there is one and it still matches the archive.
+fn load_index(path: &Path) -> Option<ArchiveIndex> {
+    let json = fs::read(index_path(path)).ok()?;
+    let index: ArchiveIndex = serde_json::from_slice(&json).ok()?;
+    let stamp = archive_stamp(path).ok()?;
+    let current = (
+        index.archive_size,
+        index.archive_mtime_secs,
+        index.archive_mtime_nanos,
+    ) == stamp;
+    (index.version == CAPIDX_VERSION && current).then_some(index)
+}
+
+/// Write a `<archive>.capidx` sidecar with the full entry list and offsets, which
+/// `open_archive` loads instead of scanning while the archive's size and mtime are unchanged.
+/// Returns the number of entries indexed. Only zip and tar archives can be indexed.
+#[tauri::command]
+pub async fn build_index(path: String) -> Result<usize, CapsuleError> {
+    let path_buf = PathBuf::from(&path);
+    let kind = archive_type(&path_buf);
+    if kind != "zip" && !kind.starts_with("tar") {
+        return Err(CapsuleError::Unsupported(format!(
+            "Indexing not supported for {kind} archives"
+        )));
+    }
+    let (archive_size, archive_mtime_secs, archive_mtime_nanos) = archive_stamp(&path_buf)?;
+
+    let entries = if kind == "zip" {
+        let offsets = entry_offsets(path.clone()).await?;
+        open_zip(&path_buf, NameEncoding::default())?
+            .into_iter()
+            .zip(offsets)
+            .map(|(entry, offset)| IndexedEntry {
+                entry,
+                header_offset: offset.header_offset,
+                compressed_size: offset.compressed_size,
+            })
+            .collect()
+    } else {
+        let mut archive = open_tar_reader(&path_buf, kind)?;
+        let tar_entries = archive
+            .entries()
+            .map_err(|e| CapsuleError::InvalidArchive(format!("Failed to read tar entries: {e}")))?;
+        let mut entries = Vec::new();
+        for entry_res in tar_entries {
+            let entry = entry_res
+                .map_err(|e| CapsuleError::InvalidArchive(format!("Tar entry error: {e}")))?;
+            let (path, name, name_lossy) = tar_entry_name(&entry, NameEncoding::default());
+            entries.push(IndexedEntry {
+                entry: CapsuleEntry {
+                    name,
+                    size: entry.size(),
+                    kind: tar_kind(entry.header()).to_string(),
+                    path,
+                    modified: tar_modified(entry.header()),
+                    crc32: None,
+                    compressed_size: None,
+                    method: None,
+                    name_lossy,
+                },
+                header_offset: entry.raw_header_position(),
+                compressed_size: entry.header().entry_size().unwrap_or(0),
+            });
+        }
+        entries
+    };
+
+    let index = ArchiveIndex {
+        version: CAPIDX_VERSION,
+        archive_size,
+        archive_mtime_secs,
+        archive_mtime_nanos,
+        entries,
+    };
+    let json = serde_json::to_vec(&index)
+        .map_err(|e| CapsuleError::Other(format!("Failed to encode index: {e}")))?;
+    fs::write(index_path(&path_buf), json)
+        .map_err(|e| CapsuleError::io("Failed to write index", e))?;
+
+    Ok(index.entries.len())
+}
+
+/// Delete the `.capidx` sidecar, if any. Returns whether one was removed.
+#[tauri::command]
+pub async fn clear_index(path: String) -> Result<bool, CapsuleError> {
+    match fs::remove_file(index_path(Path::new(&path))) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(CapsuleError::io("Failed to remove index", e)),
+    }
+}
+
+/// Managed state holding cancel flags of running extractions keyed by the frontend's
+/// operation id.
+#[derive(Default)]
+pub struct ExtractionTokens(Mutex<HashMap<String, Arc<AtomicBool>>>);
+
+/// Ask the extraction started with `operation_id` to stop after its current entry.
+/// Returns false when no such extraction is running (it may already have finished).
+#[tauri::command]
+pub async fn cancel_extraction(
+    tokens: State<'_, ExtractionTokens>,
+    operation_id: String,
+) -> Result<bool, CapsuleError> {
+    let map = tokens
+        .0
+        .lock()
+        .map_err(|_| CapsuleError::Other("Extraction state poisoned".into()))?;
+    Ok(match map.get(&operation_id) {
+        Some(flag) => {
+            flag.store(true, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    })
+}
+
+/// Extract a whole archive to a directory, emitting `extract://progress` after each entry.
+/// With `operation_id`, the extraction can be stopped through `cancel_extraction`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn extract_archive(
+    app: AppHandle,
+    tokens: State<'_, ExtractionTokens>,
+    path: String,
+    dest: String,
+    options: Option<ExtractOptions>,
+    operation_id: Option<String>,
+    password: Option<String>,
+    dry_run: Option<bool>,
+) -> Result<ExtractSummary, CapsuleError> {
+    let path_buf = PathBuf::from(&path);
+    let mut options = options.unwrap_or_default();
+    let mut dest_buf = PathBuf::from(&dest);
+    let subfolder = if options.extract_to_subfolder.unwrap_or(false) {
+        subfolder_dest(&path_buf, &dest_buf)?
+    } else {
+        None
+    };
+    if let Some(subfolder) = &subfolder {
+        dest_buf = subfolder.clone();
+    }
+    let dest = dest_buf.to_string_lossy().into_owned();
+    if dry_run.unwrap_or(false) {
+        let mut summary = plan_extraction(&path_buf, &dest_buf, &options)?;
+        summary.subfolder = subfolder.map(|p| p.to_string_lossy().into_owned());
+        return Ok(summary);
+    }
+    let kind = archive_type(&path_buf);
+    options.password = password;
+    let progress_app = app.clone();
+    let progress_id = operation_id.clone();
+    options.progress = Some(ProgressNotifier(Box::new(
+        move |index, total, bytes_written| {
+            let progress = ExtractProgress {
+                operation_id: progress_id.clone(),
+                index,
+                total,
+                bytes_written,
+            };
+            let _ = progress_app.emit("extract://progress", progress);
+        },
+    )));
+    if let Some(min_free_bytes) = options.min_free_bytes {
+        let dest = dest.clone();
+        options.low_space_notifier = Some(LowSpaceNotifier(Box::new(move |free_bytes| {
+            let warning = LowSpaceWarning {
+                dest: dest.clone(),
+                free_bytes,
+                min_free_bytes,
+            };
+            let _ = app.emit("extract://low-space", warning);
+        })));
+    }
+    if let Some(id) = &operation_id {
+        let mut map = tokens
+            .0
+            .lock()
+            .map_err(|_| CapsuleError::Other("Extraction state poisoned".into()))?;
+        if map.contains_key(id) {
+            return Err(CapsuleError::Other(format!(
+                "Extraction already running: {id}"
+            )));
+        }
+        let flag = Arc::new(AtomicBool::new(false));
+        map.insert(id.clone(), flag.clone());
+        options.cancel = Some(flag);
+    }
+
+    let result = match kind {
+        "zip" => extract_zip(&path_buf, &dest_buf, &options),
+        "7z" => extract_7z(&path_buf, &dest_buf, &options),
+        "rar" => extract_rar(&path_buf, &dest_buf, &options),
+        "gz" | "bz2" | "xz" | "zst" => extract_compressed(&path_buf, kind, &dest_buf, &options),
+        _ => open_tar_reader(&path_buf, kind)
+            .and_then(|archive| extract_tar_like(archive, &dest_buf, &options)),
+    };
+    if let Some(id) = &operation_id {
+        if let Ok(mut map) = tokens.0.lock() {
+            map.remove(id);
+        }
+    }
+    let mut summary = result?;
+    summary.subfolder = subfolder.map(|p| p.to_string_lossy().into_owned());
+
+    if let Some(action) = options.on_complete {
+        summary.post_action = Some(run_post_extract_action(action, &dest_buf, &summary)?);
+    }
+
+    Ok(summary)
+}
+
+/// Helper: the folder `extract_to_subfolder` extracts into, or `None` when the archive
+/// already has a single top-level directory and can go straight into `dest`.
+fn subfolder_dest(path: &Path, dest: &Path) -> Result<Option<PathBuf>, CapsuleError> {
+    if has_single_root(&list_archive(path)?) {
+        return Ok(None);
+    }
+    let subfolder = validate_extract_path(dest, Path::new(&archive_stem(path)))?;
+    Ok(Some(subfolder))
+}
+
+/// Helper: whether every entry lives under one top-level directory.
+fn has_single_root(entries: &[CapsuleEntry]) -> bool {
+    let mut root = None;
+    for entry in entries {
+        let path = entry.path.trim_start_matches("./").trim_end_matches('/');
+        if path.is_empty() {
+            continue;
+        }
+        let (first, rest) = path.split_once('/').unwrap_or((path, ""));
+        // A top-level file means there is no wrapper directory.
+        if rest.is_empty() && entry.kind != "dir" {
+            return false;
+        }
+        match root {
+            None => root = Some(first),
+            Some(root) if root == first => {}
+            Some(_) => return false,
+        }
+    }
+    root.is_some()
+}
+
+/// Helper: the archive's file name without its archive extensions ("report" for
+/// "report.tar.gz").
+fn archive_stem(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let lower = name.to_lowercase();
+    let stem = [
+        ".tar.gz", ".tar.bz2", ".tar.xz", ".tar.zst", ".tgz", ".tbz", ".txz", ".tzst", ".tar",
+        ".zip", ".7z", ".rar", ".gz", ".bz2", ".xz", ".zst",
+    ]
+    .iter()
+    .find(|ext| lower.ends_with(*ext) && lower.len() > ext.len())
+    .map(|ext| &name[..name.len() - ext.len()])
+    .unwrap_or(&name);
+    match stem {
+        "" | "." | ".." => "archive".to_string(),
+        stem => stem.to_string(),
+    }
+}
+
+/// Dry run for `extract_archive`: entries get the same dedupe, limit, flatten,
+/// case-collision and overwrite handling as in a real extraction, then
+/// `validate_extract_path`, but nothing is created on disk.
+fn plan_extraction(
+    path: &Path,
+    dest: &Path,
+    options: &ExtractOptions,
+) -> Result<ExtractSummary, CapsuleError> {
+    let entries = list_archive(path)?;
+    // Only zip extraction dedupes; the other formats write every entry in order.
+    let keep = if archive_type(path) == "zip" {
+        let names: Vec<String> = entries.iter().map(|entry| entry.path.clone()).collect();
+        dedupe_mask(&names, options.dedupe_by_name.as_deref())?
+    } else {
+        vec![true; entries.len()]
+    };
+    let mut limits = ExtractLimits::new(options);
+    let mut flattener = Flattener::new(options.flatten);
+    let mut case_guard = CaseCollisionGuard::new(options.case_collision_policy.as_deref())?;
+    let mut overwrite_guard = OverwriteGuard::new(options.overwrite.as_deref())?;
+    let mut summary = ExtractSummary::default();
+
+    for (entry, keep_entry) in entries.into_iter().zip(keep) {
+        if !keep_entry {
+            summary.skipped_duplicates.push(entry.path);
+            continue;
+        }
+        limits.admit(&entry.path, entry.size, entry.compressed_size)?;
+        let is_dir = entry.kind == "dir" || entry.path.ends_with('/');
+        if flattener.enabled && entry.kind == "symlink" {
+            summary
+                .warnings
+                .push(format!("{}: link skipped when flattening", entry.path));
+        }
+        if flattener.enabled && (is_dir || entry.kind == "symlink") {
+            continue;
+        }
+        let name = if is_dir {
+            entry.path.clone()
+        } else {
+            let flat = flattener.resolve(&entry.path, &mut summary);
+            let Some(name) = case_guard.resolve(&flat, &mut summary)? else {
+                continue;
+            };
+            match overwrite_guard.resolve(dest, &name, &mut summary) {
+                Ok(Some(name)) => name,
+                Ok(None) => continue,
+                Err(error) => {
+                    summary.failed.push(FailedEntry {
+                        name: entry.path,
+                        error: error.to_string(),
+                    });
+                    continue;
+                }
+            }
+        };
+        match validate_extract_path(dest, Path::new(&name)) {
+            Ok(outpath) => summary.planned.push(outpath.to_string_lossy().into_owned()),
+            Err(error) => summary.failed.push(FailedEntry {
+                name: entry.path,
+                error: error.to_string(),
+            }),
+        }
+    }
+    Ok(summary)
+}
+
+/// Extract only the named entries. With `rebase_root` (a directory inside the archive that
+/// every selected entry lives under), that prefix is dropped from the extracted paths.
+#[tauri::command]
+pub async fn extract_entries(
+    path: String,
+    dest: String,
+    entry_paths: Vec<String>,
+    rebase_root: Option<String>,
+) -> Result<ExtractSummary, CapsuleError> {
+    let path_buf = PathBuf::from(&path);
+    let dest_buf = PathBuf::from(&dest);
+    let rebase_root = rebase_root.as_deref();
+
+    // Reject a bad rebase root before anything is written.
+    for name in &entry_paths {
+        rebase_entry_path(name, rebase_root)?;
+    }
+
+    let summary = match archive_type(&path_buf) {
+        "zip" => extract_zip_entries(&path_buf, &dest_buf, &entry_paths, rebase_root),
+        kind if kind.starts_with("tar") => extract_tar_entries(
+            open_tar_reader(&path_buf, kind)?,
+            &dest_buf,
+            &entry_paths,
+            rebase_root,
+        ),
+        kind => Err(CapsuleError::Unsupported(format!(
+            "Selective extraction not supported for {kind} archives"
+        ))),
+    }?;
+    Ok(summary)
+}
+
+/// Helper: paths of the file entries whose full archive path matches the glob `pattern`.
+/// `*` stays within one directory; use `**/` to match at any depth.
+fn matching_entry_paths(path: &Path, pattern: &str) -> Result<Vec<String>, CapsuleError> {
+    let pattern = glob::Pattern::new(pattern)
+        .map_err(|e| CapsuleError::Other(format!("Invalid pattern {pattern}: {e}")))?;
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    Ok(list_archive(path)?
+        .into_iter()
+        .filter(|entry| entry.kind != "dir")
+        .filter(|entry| pattern.matches_with(entry.path.trim_start_matches("./"), options))
+        .map(|entry| entry.path)
+        .collect())
+}
+
+/// Extract every file entry whose path matches the glob `pattern` (e.g. "**/*.png").
+/// Matched tar links must stay inside `dest`, as in `extract_tar_entries`.
+#[tauri::command]
+pub async fn extract_matching(
+    path: String,
+    dest: String,
+    pattern: String,
+) -> Result<ExtractSummary, CapsuleError> {
+    let path_buf = PathBuf::from(&path);
+    let dest_buf = PathBuf::from(&dest);
+
+    let summary = match archive_type(&path_buf) {
+        "zip" => {
+            let selected = matching_entry_paths(&path_buf, &pattern)?;
+            extract_zip_entries(&path_buf, &dest_buf, &selected, None)
+        }
+        kind if kind.starts_with("tar") => {
+            let selected = matching_entry_paths(&path_buf, &pattern)?;
+            extract_tar_entries(
+                open_tar_reader(&path_buf, kind)?,
+                &dest_buf,
+                &selected,
+                None,
+            )
+        }
+        kind => Err(CapsuleError::Unsupported(format!(
+            "Pattern extraction not supported for {kind} archives"
+        ))),
+    }?;
+    Ok(summary)
+}
+
+/// Shape that matches how `create_zip_archive` is invoked from TypeScript (args: { ... }).
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateZipArgs {
+    pub output_path: String,
+    pub input_paths: Vec<String>,
+    /// "store" | "deflate" | "bzip2" | "zstd"; anything else deflates.
+    pub compression_mode: String,
+    /// Level for the chosen method (e.g. 1 fast .. 9 smallest for deflate); out-of-range
+    /// values are clamped, and stored entries ignore it.
+    pub compression_level: Option<i32>,
+    /// Compress files on worker threads (unencrypted archives only; see
+    /// `add_inputs_to_zip_parallel`).
+    pub parallel_compression: bool,
+    #[allow(dead_code)]
+    pub temp_dir: Option<String>,
+    /// Encrypt every file entry with this password.
+    pub password: Option<String>,
+    /// "aes256" (default) or "zipcrypto" for tools that can't read AES.
+    pub encryption_method: Option<String>,
+    /// Re-read every entry before putting the archive in place (see `validate_zip_file`).
+    pub strict_validate: Option<bool>,
+    /// Glob patterns (e.g. `node_modules`, `*.log`, `build/**`) for paths to leave out,
+    /// matched relative to each input directory; see `is_excluded`.
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
+    /// Split the finished zip into `<output>.001`, `.002`, ... volumes of at most this many
+    /// bytes, with a `<output>.volumes.json` manifest; `join_volumes` reassembles them.
+    pub split_size_bytes: Option<u64>,
+}
+
+/// Helper: the compression method for `CreateZipArgs::compression_mode`.
+fn zip_compression_method(mode: &str) -> CompressionMethod {
+    match mode {
+        "store" => CompressionMethod::Stored,
+        "bzip2" => CompressionMethod::Bzip2,
+        "zstd" => CompressionMethod::Zstd,
+        _ => CompressionMethod::Deflated,
+    }
+}
+
+/// Payload of the `create://progress` event, sent after each file is written.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateProgress {
+    pub operation_id: Option<String>,
+    pub files_done: usize,
+    pub total_files: usize,
+    pub bytes_done: u64,
+    pub total_bytes: u64,
+}
+
+/// Running totals behind `create://progress`. `notify` gets files done, total files, bytes
+/// done and total bytes; a default tracker reports nothing.
+#[derive(Default)]
+struct CreateProgressTracker<'a> {
+    notify: Option<&'a dyn Fn(usize, usize, u64, u64)>,
+    total_files: usize,
+    total_bytes: u64,
+    files_done: usize,
+    bytes_done: u64,
+}
+
+impl CreateProgressTracker<'_> {
+    /// Count one written file of `bytes` (uncompressed) and report it.
+    fn file_done(&mut self, bytes: u64) {
+        self.files_done += 1;
+        self.bytes_done += bytes;
+        if let Some(notify) = self.notify {
+            notify(
+                self.files_done,
+                self.total_files,
+                self.bytes_done,
+                self.total_bytes,
+            );
+        }
+    }
+}
+
+/// Result of `create_zip_archive`.
+#[derive(Debug, Default, Serialize)]
+pub struct CreateZipResult {
+    pub warnings: Vec<String>,
+    /// Volume files when `split_size_bytes` split the archive; empty otherwise.
+    pub volumes: Vec<String>,
+}
+
+/// Helper: reopen a finished zip with the reader and decompress every entry, so CRC and
+/// stream errors the writer couldn't see surface before anyone trusts the archive.
+fn validate_zip_file(path: &Path, password: Option<&str>) -> Result<(), CapsuleError> {
+    let file =
+        File::open(path).map_err(|e| CapsuleError::io("Validation: failed to reopen zip", e))?;
+    let mut archive = ZipArchive::new(file)
+        .map_err(|e| CapsuleError::zip("Validation: invalid zip structure", e))?;
+
+    for i in 0..archive.len() {
+        let mut entry = match password {
+            Some(password) => archive
+                .by_index_decrypt(i, password.as_bytes())
+                .map_err(|e| CapsuleError::zip(format!("Validation: entry {i} unreadable"), e))?
+                .map_err(|e| {
+                    CapsuleError::InvalidPassword(format!("Validation: entry {i}: {e}"))
+                })?,
+            None => archive
+                .by_index(i)
+                .map_err(|e| CapsuleError::zip(format!("Validation: entry {i} unreadable"), e))?,
+        };
+        io::copy(&mut entry, &mut io::sink()).map_err(|e| {
+            let context = format!(
+                "Validation: entry {} ({} bytes, CRC {:08x}) failed to decompress",
+                entry.name(),
+                entry.size(),
+                entry.crc32()
+            );
+            CapsuleError::io(context, e)
+        })?;
+    }
+
+    Ok(())
+}
+
+/// A uniquely named temp file beside `target`, for rewrite-then-replace operations. Two
+/// rewrites never share a name, and the file is deleted on drop unless `persist` moved it
+/// into place, so a failed rewrite leaves neither a stray temp file nor a damaged original.
+struct TempBeside {
+    path: PathBuf,
+    persisted: bool,
+}
+
+impl TempBeside {
+    fn new(target: &Path) -> Self {
+        let name = target
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let unique = format!(".{name}.{}.tmp", uuid::Uuid::new_v4().simple());
+        Self {
+            path: target.with_file_name(unique),
+            persisted: false,
+        }
+    }
+
+    fn create(&self) -> Result<File, CapsuleError> {
+        File::create(&self.path).map_err(|e| CapsuleError::io("Failed to create temp file", e))
+    }
+
+    /// Move the temp file over `dest`.
+    fn persist(mut self, dest: &Path) -> io::Result<()> {
+        fs::rename(&self.path, dest)?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Drop for TempBeside {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Helper: after a strict write to `temp`, validate it and move it to `dest`; a bad
+/// archive is dropped (and so deleted) so it never replaces or appears as the output.
+fn validate_and_replace(
+    temp: TempBeside,
+    dest: &Path,
+    password: Option<&str>,
+) -> Result<(), CapsuleError> {
+    validate_zip_file(&temp.path, password)?;
+    temp.persist(dest)
+        .map_err(|e| CapsuleError::io("Failed to move validated zip into place", e))
+}
+
+/// Create a new ZIP archive from a set of input paths.
+/// The input paths are counted first so each `create://progress` event carries totals.
+#[tauri::command]
+pub async fn create_zip_archive(
+    app: AppHandle,
+    args: CreateZipArgs,
+    operation_id: Option<String>,
+) -> Result<CreateZipResult, CapsuleError> {
+    let notify = move |files_done, total_files, bytes_done, total_bytes| {
+        let progress = CreateProgress {
+            operation_id: operation_id.clone(),
+            files_done,
+            total_files,
+            bytes_done,
+            total_bytes,
+        };
+        let _ = app.emit("create://progress", progress);
+    };
+    write_zip_archive(args, Some(&notify))
+}
+
+/// Body of `create_zip_archive`; `notify` is called after each file, as for
+/// `CreateProgressTracker`.
+fn write_zip_archive(
+    args: CreateZipArgs,
+    notify: Option<&dyn Fn(usize, usize, u64, u64)>,
+) -> Result<CreateZipResult, CapsuleError> {
+    let output = PathBuf::from(&args.output_path);
+    let mut result = CreateZipResult::default();
+
+    let encryption = match (&args.password, args.encryption_method.as_deref()) {
+        (None, _) => None,
+        (Some(password), _) if password.is_empty() => {
+            return Err(CapsuleError::Other("Password must not be empty".into()));
+        }
+        (Some(password), None | Some("aes256")) => {
+            Some((password.clone(), EncryptionMethod::Aes256))
+        }
+        (Some(password), Some("zipcrypto")) => {
+            result.warnings.push(
+                "ZipCrypto is weak encryption and can be broken easily; use AES-256 unless \
+                 the archive must open in legacy tools."
+                    .into(),
+            );
+            Some((password.clone(), EncryptionMethod::ZipCrypto))
+        }
+        (Some(_), Some(other)) => {
+            return Err(CapsuleError::Unsupported(format!(
+                "Unsupported encryption method: {other}"
+            )))
+        }
+    };
+    let compression = ZipCompression {
+        method: zip_compression_method(&args.compression_mode),
+        level: args.compression_level,
+    };
+    if encryption.is_some()
+        && matches!(
+            compression.method,
+            CompressionMethod::Bzip2 | CompressionMethod::Zstd
+        )
+    {
+        result.warnings.push(format!(
+            "{} compression isn't available for encrypted archives; entries were deflated \
+             instead.",
+            args.compression_mode
+        ));
+    }
+
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| CapsuleError::io("Failed to create output dir", e))?;
+    }
+
+    let excludes = compile_excludes(&args.exclude_patterns)?;
+    let (total_files, total_bytes) = zip_input_totals(&args.input_paths, &excludes)?;
+    let mut progress = CreateProgressTracker {
+        notify,
+        total_files,
+        total_bytes,
+        ..Default::default()
+    };
+
+    // Strict mode writes beside the output and only moves the archive in once it validates.
+    let strict = args
+        .strict_validate
+        .unwrap_or(false)
+        .then(|| TempBeside::new(&output));
+    let file = match &strict {
+        Some(temp) => temp.create()?,
+        None => File::create(&output)
+            .map_err(|e| CapsuleError::io("Failed to create archive file", e))?,
+    };
+    let password = encryption.as_ref().map(|(password, _)| password.clone());
+
+    match encryption {
+        None => {
+            let mut writer = ZipWriter::new(file);
+            if args.parallel_compression {
+                add_inputs_to_zip_parallel(
+                    &mut writer,
+                    &args.input_paths,
+                    compression,
+                    default_extract_concurrency(),
+                    &excludes,
+                    &mut progress,
+                )?;
+            } else {
+                add_inputs_to_zip(
+                    &mut writer,
+                    &args.input_paths,
+                    compression,
+                    &excludes,
+                    &mut progress,
+                )?;
+            }
+            writer
+                .finish()
+                .map_err(|e| CapsuleError::zip("Failed to finalize zip", e))?;
+        }
+        Some((password, method)) => {
+            let mut writer = EncryptedZipWriter {
+                writer: zip2::ZipWriter::new(file),
+                password,
+                method,
+            };
+            add_inputs_to_zip(
+                &mut writer,
+                &args.input_paths,
+                compression,
+                &excludes,
+                &mut progress,
+            )?;
+            writer
+                .writer
+                .finish()
+                .map_err(|e| CapsuleError::Io(format!("Failed to finalize zip: {e}")))?;
+        }
+    }
+
+    if let Some(temp) = strict {
+        validate_and_replace(temp, &output, password.as_deref())?;
+    }
+
+    if let Some(split_size) = args.split_size_bytes {
+        result.volumes = split_into_volumes(&output, split_size)?
+            .iter()
+            .map(|p| p.to_string_lossy().into_owned())
+            .collect();
+    }
+
+    Ok(result)
+}
+
+/// `<archive>.volumes.json`, written beside split volumes.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumeManifest {
+    /// File name of the reassembled archive.
+    pub file_name: String,
+    pub total_size: u64,
+    /// SHA-256 (hex) of the whole archive, checked after joining.
+    pub sha256: String,
+    /// Volume file names in order, relative to the manifest.
+    pub volumes: Vec<String>,
+}
+
+/// Helper: `path` with `suffix` appended to its file name.
+fn with_name_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Helper: cut `path` into volumes of at most `split_size` bytes plus a manifest, then
+/// remove the original. An archive that already fits is left whole and no volumes are made.
+/// On failure the volumes written so far are removed and the original is kept.
+fn split_into_volumes(path: &Path, split_size: u64) -> Result<Vec<PathBuf>, CapsuleError> {
+    if split_size == 0 {
+        return Err(CapsuleError::Other(
+            "Split size must be greater than zero".into(),
+        ));
+    }
+    let total_size = fs::metadata(path)
+        .map_err(|e| CapsuleError::open("Failed to stat archive", e))?
+        .len();
+    if total_size <= split_size {
+        return Ok(Vec::new());
+    }
+
+    let mut volumes = Vec::new();
+    if let Err(e) = write_volumes(path, split_size, total_size, &mut volumes) {
+        for volume in &volumes {
+            let _ = fs::remove_file(volume);
+        }
+        let _ = fs::remove_file(with_name_suffix(path, ".volumes.json"));
+        return Err(e);
+    }
+    fs::remove_file(path).map_err(|e| CapsuleError::io("Failed to remove unsplit archive", e))?;
+    Ok(volumes)
+}
+
+/// Helper: the writing half of `split_into_volumes`. Each volume is pushed onto `volumes`
+/// as soon as it is created, so the caller can clean up after a failure.
+fn write_volumes(
+    path: &Path,
+    split_size: u64,
+    total_size: u64,
+    volumes: &mut Vec<PathBuf>,
+) -> Result<(), CapsuleError> {
+    use sha2::{Digest, Sha256};
+
+    let mut source =
+        File::open(path).map_err(|e| CapsuleError::open("Failed to open archive", e))?;
+    let mut hasher = Sha256::new();
+    let mut remaining = total_size;
+    while remaining > 0 {
+        let volume = with_name_suffix(path, &format!(".{:03}", volumes.len() + 1));
+        let mut out =
+            File::create(&volume).map_err(|e| CapsuleError::io("Failed to create volume", e))?;
+        volumes.push(volume);
+        let mut chunk = (&mut source).take(split_size);
+        let mut buf = vec![0u8; DEFAULT_STREAM_BUFFER_SIZE];
+        loop {
+            let n = chunk
+                .read(&mut buf)
+                .map_err(|e| CapsuleError::io("Failed to read archive", e))?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            out.write_all(&buf[..n])
+                .map_err(|e| CapsuleError::io("Failed to write volume", e))?;
+            remaining -= n as u64;
+        }
+    }
+
+    let manifest = VolumeManifest {
+        file_name: path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        total_size,
+        sha256: hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect(),
+        volumes: volumes
+            .iter()
+            .filter_map(|v| v.file_name())
+            .map(|n| n.to_string_lossy().into_owned())
+            .collect(),
+    };
+    let json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| CapsuleError::Other(format!("Failed to encode volume manifest: {e}")))?;
+    fs::write(with_name_suffix(path, ".volumes.json"), json)
+        .map_err(|e| CapsuleError::io("Failed to write volume manifest", e))
+}
+
+/// Helper: concatenate the volumes listed in `manifest_path` into `output`, checking the
+/// size and SHA-256 before the result is moved into place.
+fn join_volume_files(manifest_path: &Path, output: Option<&Path>) -> Result<PathBuf, CapsuleError> {
+    use sha2::{Digest, Sha256};
+
+    let json = fs::read(manifest_path)
+        .map_err(|e| CapsuleError::io("Failed to read volume manifest", e))?;
+    let manifest: VolumeManifest = serde_json::from_slice(&json)
+        .map_err(|e| CapsuleError::Other(format!("Invalid volume manifest: {e}")))?;
+    let dir = manifest_path.parent().unwrap_or(Path::new(""));
+    let output = match output {
+        Some(output) => output.to_path_buf(),
+        None => validate_extract_path(dir, Path::new(&manifest.file_name))?,
+    };
+    let partial = with_name_suffix(&output, ".partial");
+
+    let joined = (|| -> Result<(u64, String), CapsuleError> {
+        let mut out = File::create(&partial)
+            .map_err(|e| CapsuleError::io("Failed to create output file", e))?;
+        let mut hasher = Sha256::new();
+        let mut total = 0;
+        let mut buf = vec![0u8; DEFAULT_STREAM_BUFFER_SIZE];
+        for name in &manifest.volumes {
+            let volume = validate_extract_path(dir, Path::new(name))?;
+            let mut input = File::open(&volume)
+                .map_err(|e| CapsuleError::open(format!("Missing volume {name}"), e))?;
+            loop {
+                let n = input
+                    .read(&mut buf)
+                    .map_err(|e| CapsuleError::io(format!("Failed to read volume {name}"), e))?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&buf[..n]);
+                out.write_all(&buf[..n])
+                    .map_err(|e| CapsuleError::io("Failed to write output file", e))?;
+                total += n as u64;
+            }
+        }
+        Ok((
+            total,
+            hasher
+                .finalize()
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect(),
+        ))
+    })();
+
+    let error = match joined {
+        Ok((total, _)) if total != manifest.total_size => CapsuleError::InvalidArchive(format!(
+            "Joined size {total} doesn't match the manifest ({})",
+            manifest.total_size
+        )),
+        Ok((_, digest)) if !digest.eq_ignore_ascii_case(&manifest.sha256) => {
+            CapsuleError::InvalidArchive("Joined archive failed its SHA-256 check".into())
+        }
+        Ok(_) => {
+            fs::rename(&partial, &output)
+                .map_err(|e| CapsuleError::io("Failed to move joined archive into place", e))?;
+            return Ok(output);
+        }
+        Err(e) => e,
+    };
+    let _ = fs::remove_file(&partial);
+    Err(error)
+}
+
+/// Reassemble volumes made by `create_zip_archive` with `splitSizeBytes`, given the
+/// `.volumes.json` manifest. Writes beside the manifest unless `output_path` is set and
+/// returns the joined archive's path.
+#[tauri::command]
+pub async fn join_volumes(
+    manifest_path: String,
+    output_path: Option<String>,
+) -> Result<String, CapsuleError> {
+    let joined = join_volume_files(
+        Path::new(&manifest_path),
+        output_path.as_deref().map(Path::new),
+    )?;
+    Ok(joined.to_string_lossy().into_owned())
+}
+
+/// Shape for `create_tar_archive({ args: { outputPath, inputPaths, compression } })`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTarArgs {
+    pub output_path: String,
+    pub input_paths: Vec<String>,
+    /// "none" | "gz" | "bz2" | "xz" | "zst".
+    pub compression: String,
+}
+
+/// Recursively add a file or directory to a tar builder, mirroring `add_path_to_zip`.
+fn add_path_to_tar<W: Write>(
+    builder: &mut tar::Builder<W>,
+    path: &Path,
+    base: &Path,
+) -> Result<(), CapsuleError> {
+    let rel = path
+        .strip_prefix(base)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/");
+
+    if path.is_dir() {
+        // The input directory itself is the archive root and gets no entry.
+        if !rel.is_empty() {
+            builder
+                .append_dir(&rel, path)
+                .map_err(|e| CapsuleError::io("Tar add dir error", e))?;
+        }
+        let mut children = fs::read_dir(path)
+            .map_err(|e| CapsuleError::io("Read dir error", e))?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| CapsuleError::io("Dir entry error", e))?;
+        children.sort();
+        for child in children {
+            add_path_to_tar(builder, &child, base)?;
+        }
+    } else {
+        builder
+            .append_path_with_name(path, &rel)
+            .map_err(|e| CapsuleError::io("Tar add file error", e))?;
+    }
+
+    Ok(())
+}
+
+/// Helper: write a tar of `inputs` (laid out like `add_inputs_to_zip`) into `writer` and
+/// hand the writer back so a compressor can be finished.
+fn write_tar<W: Write>(writer: W, inputs: &[String]) -> Result<W, CapsuleError> {
+    let mut builder = tar::Builder::new(writer);
+    add_inputs_to_tar(&mut builder, inputs)?;
+    builder
+        .into_inner()
+        .map_err(|e| CapsuleError::io("Failed to finalize tar", e))
+}
+
+/// Helper: add each input (a directory's contents, or a file by its name) to `builder`.
+/// Missing inputs are skipped.
+fn add_inputs_to_tar<W: Write>(
+    builder: &mut tar::Builder<W>,
+    inputs: &[String],
+) -> Result<(), CapsuleError> {
+    for input in inputs {
+        let path = PathBuf::from(input);
+        if !path.exists() {
+            continue;
+        }
+
+        let base = if path.is_dir() {
+            path.clone()
+        } else {
+            path.parent().unwrap_or(&path).to_path_buf()
+        };
+
+        add_path_to_tar(builder, &path, &base)?;
+    }
+    Ok(())
+}
+
+/// Helper: the compressor a tarball is written through, picked by the
+/// `CreateTarArgs::compression` name.
+enum TarEncoder {
+    None(File),
+    Gz(flate2::write::GzEncoder<File>),
+    Bz2(bzip2::write::BzEncoder<File>),
+    Xz(xz2::write::XzEncoder<File>),
+    Zst(zstd::Encoder<'static, File>),
+}
+
+impl TarEncoder {
+    fn new(file: File, compression: &str) -> Result<Self, CapsuleError> {
+        Ok(match compression {
+            "none" => Self::None(file),
+            "gz" => Self::Gz(flate2::write::GzEncoder::new(
+                file,
+                flate2::Compression::default(),
+            )),
+            "bz2" => Self::Bz2(bzip2::write::BzEncoder::new(
+                file,
+                bzip2::Compression::default(),
+            )),
+            "xz" => Self::Xz(xz2::write::XzEncoder::new(file, 6)),
+            // Level 0 picks zstd's default (3).
+            "zst" => Self::Zst(
+                zstd::Encoder::new(file, 0)
+                    .map_err(|e| CapsuleError::io("Failed to start zstd stream", e))?,
+            ),
+            other => {
+                return Err(CapsuleError::Unsupported(format!(
+                    "Unsupported tar compression: {other}"
+                )))
+            }
+        })
+    }
+
+    /// Flush the compressed stream's trailer.
+    fn finish(self) -> Result<File, CapsuleError> {
+        let finished = match self {
+            Self::None(file) => Ok(file),
+            Self::Gz(encoder) => encoder.finish(),
+            Self::Bz2(encoder) => encoder.finish(),
+            Self::Xz(encoder) => encoder.finish(),
+            Self::Zst(encoder) => encoder.finish(),
+        };
+        finished.map_err(|e| CapsuleError::io("Failed to finalize compressed tar", e))
+    }
+
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            Self::None(file) => file,
+            Self::Gz(encoder) => encoder,
+            Self::Bz2(encoder) => encoder,
+            Self::Xz(encoder) => encoder,
+            Self::Zst(encoder) => encoder,
+        }
+    }
+}
+
+impl Write for TarEncoder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer().flush()
+    }
+}
+
+/// Create a tar archive, optionally gzip/bzip2/xz/zstd compressed, from a set of input paths.
+#[tauri::command]
+pub async fn create_tar_archive(args: CreateTarArgs) -> Result<(), CapsuleError> {
+    let output = PathBuf::from(&args.output_path);
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| CapsuleError::io("Failed to create output dir", e))?;
+    }
+    let file =
+        File::create(&output).map_err(|e| CapsuleError::io("Failed to create archive file", e))?;
+    let encoder = match TarEncoder::new(file, &args.compression) {
+        Ok(encoder) => encoder,
+        Err(e) => {
+            let _ = fs::remove_file(&output);
+            return Err(e);
+        }
+    };
+    write_tar(encoder, &args.input_paths)?.finish()?;
+
+    Ok(())
+}
+
+/// Append files to an existing tarball. A plain .tar is appended to in place, over its
+/// end-of-archive blocks; compressed tarballs can't be appended to cheaply, so they are
+/// rewritten to a temp file that then replaces the original.
+#[tauri::command]
+pub async fn add_files_to_tar(tar_path: String, files: Vec<String>) -> Result<(), CapsuleError> {
+    let path = PathBuf::from(&tar_path);
+    match archive_type(&path) {
+        "tar" => append_to_tar(&path, &files)?,
+        kind if kind.starts_with("tar.") => rewrite_compressed_tar(&path, kind, &files)?,
+        kind => {
+            return Err(CapsuleError::Other(format!(
+                "Not a tar archive ({kind}): {tar_path}"
+            )))
+        }
+    }
+    Ok(())
+}
+
+/// Helper: append `files` to an uncompressed tar in place.
+fn append_to_tar(path: &Path, files: &[String]) -> Result<(), CapsuleError> {
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| CapsuleError::open("Failed to open tar for appending", e))?;
+    let end =
+        tar_data_end(&mut file).map_err(|e| CapsuleError::io("Failed to append to tar", e))?;
+    file.seek(SeekFrom::Start(end))
+        .map_err(|e| CapsuleError::io("Failed to append to tar", e))?;
+    let mut original = file
+        .try_clone()
+        .map_err(|e| CapsuleError::io("Failed to append to tar", e))?;
+
+    let mut builder = tar::Builder::new(file);
+    // `finish` writes fresh end-of-archive blocks after the new entries.
+    let result = add_inputs_to_tar(&mut builder, files).and_then(|()| {
+        builder
+            .finish()
+            .map_err(|e| CapsuleError::io("Failed to append to tar", e))
+    });
+    drop(builder);
+    if result.is_err() {
+        // Cut off the partial entries and put the end blocks back, so the tar reads as before.
+        let _ = original
+            .set_len(end)
+            .and_then(|()| original.seek(SeekFrom::Start(end)))
+            .and_then(|_| original.write_all(&[0; 1024]));
+    }
+    result
+}
+
+/// Helper: offset just past the last entry of a tar, i.e. where its zero end blocks start.
+/// Fails on an entry that runs past the end of the file rather than appending after it.
+fn tar_data_end(file: &mut File) -> io::Result<u64> {
+    let len = file.metadata()?.len();
+    let mut pos: u64 = 0;
+    let mut block = [0u8; 512];
+    while pos + 512 <= len {
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut block)?;
+        if block.iter().all(|&b| b == 0) {
+            break;
+        }
+        let size = tar::Header::from_byte_slice(&block).entry_size()?;
+        pos = size
+            .checked_next_multiple_of(512)
+            .and_then(|data| data.checked_add(512 + pos))
+            .filter(|&next| next <= len)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("entry at offset {pos} runs past the end of the file"),
+                )
+            })?;
+    }
+    Ok(pos)
+}
+
+/// Helper: rewrite a compressed tarball with `files` added after its existing entries.
+fn rewrite_compressed_tar(path: &Path, kind: &str, files: &[String]) -> Result<(), CapsuleError> {
+    let temp = TempBeside::new(path);
+    let encoder = TarEncoder::new(temp.create()?, kind.trim_start_matches("tar."))?;
+    let mut builder = tar::Builder::new(encoder);
+    copy_tar_entries(&mut builder, open_tar_reader(path, kind)?)?;
+    add_inputs_to_tar(&mut builder, files)?;
+    builder
+        .into_inner()
+        .map_err(|e| CapsuleError::io("Failed to rewrite compressed tar", e))?
+        .finish()?;
+    temp.persist(path)
+        .map_err(|e| CapsuleError::io("Failed to replace original tar", e))
+}
+
+/// Helper: copy every entry of `archive` into `builder`, keeping long names and link targets.
+fn copy_tar_entries<W: Write, R: Read>(
+    builder: &mut tar::Builder<W>,
+    mut archive: TarArchive<R>,
+) -> Result<(), CapsuleError> {
+    let entries = archive
+        .entries()
+        .map_err(|e| CapsuleError::InvalidArchive(format!("Failed to read tar entries: {e}")))?;
+    for entry in entries {
+        let mut entry =
+            entry.map_err(|e| CapsuleError::InvalidArchive(format!("Tar entry error: {e}")))?;
+        let path = entry
+            .path()
+            .map_err(|e| CapsuleError::InvalidArchive(format!("Tar path error: {e}")))?
+            .into_owned();
+        let link = entry
+            .link_name()
+            .map_err(|e| CapsuleError::InvalidArchive(format!("Tar link error: {e}")))?
+            .map(|link| link.into_owned());
+        let mut header = entry.header().clone();
+        let copied = match link {
+            Some(link) => builder.append_link(&mut header, &path, &link),
+            None => builder.append_data(&mut header, &path, &mut entry),
+        };
+        copied.map_err(|e| CapsuleError::io("Failed to rewrite compressed tar", e))?;
+    }
+    Ok(())
+}
+
+/// Helper: fail if any entry of the zip at `path` is encrypted. zip 0.6 rebuilds the header
+/// of a raw-copied entry without the encryption flag or the AES extra field, so a rewritten
+/// encrypted entry would no longer decrypt.
+fn reject_encrypted_entries(
+    path: &Path,
+    archive: &mut ZipArchive<File>,
+) -> Result<(), CapsuleError> {
+    let mut raw = File::open(path).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+    for i in 0..archive.len() {
+        let entry = archive
+            .by_index_raw(i)
+            .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+        if read_local_header_flags(&mut raw, entry.header_start())? & 0x0001 != 0 {
+            return Err(CapsuleError::Unsupported(format!(
+                "Cannot rewrite a zip with encrypted entries: {}",
+                zip_entry_name(&entry)
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Helper: copy `entry` into `writer` without recompressing it, under its listing name.
+/// `raw_copy_file` would re-emit the CP437 reading of a name stored without the UTF-8 flag.
+fn raw_copy_zip_entry<W: Write + Seek>(
+    writer: &mut ZipWriter<W>,
+    entry: zip::read::ZipFile,
+) -> zip::result::ZipResult<()> {
+    let name = zip_entry_name(&entry);
+    writer.raw_copy_file_rename(entry, name)
+}
+
+/// Shape for `add_files_to_zip({ args: { zip, files, strictValidate?, replaceExisting? } })`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddFilesArgs {
+    pub zip: String,
+    pub files: Vec<String>,
+    /// Re-read every entry of the rewritten archive before it replaces the original.
+    pub strict_validate: Option<bool>,
+    /// Replace entries that have the same name as an added file. Without it such a
+    /// collision is an error and the zip is left untouched.
+    pub replace_existing: Option<bool>,
+}
+
+/// Add files to an existing ZIP by rewriting it to a temp file and then replacing.
+#[tauri::command]
+pub async fn add_files_to_zip(args: AddFilesArgs) -> Result<(), CapsuleError> {
+    let zip_path = PathBuf::from(&args.zip);
+
+    // Names about to be written; existing entries with these names are replaced, not
+    // duplicated.
+    let added: HashSet<String> = plan_zip_inputs(&args.files, &[])?
+        .into_iter()
+        .map(|input| match input {
+            ZipInput::Dir(name) | ZipInput::File(_, name) => name,
+        })
+        .collect();
+
+    // 1. Open existing zip (if present) and check for name collisions.
+    let mut existing = None;
+    if zip_path.exists() {
+        let file = File::open(&zip_path)
+            .map_err(|e| CapsuleError::open("Failed to open existing zip", e))?;
+        let mut archive = ZipArchive::new(file)
+            .map_err(|e| CapsuleError::zip("Invalid existing zip", e))?;
+        reject_encrypted_entries(&zip_path, &mut archive)?;
+        let mut collisions: Vec<String> = zip_entry_names(&mut archive)?
+            .into_iter()
+            .filter(|name| !name.ends_with('/') && added.contains(name))
+            .collect();
+        if !collisions.is_empty() && !args.replace_existing.unwrap_or(false) {
+            collisions.sort();
+            return Err(CapsuleError::Other(format!(
+                "Entries already exist in the zip: {} (set replaceExisting to replace them)",
+                collisions.join(", ")
+            )));
+        }
+        existing = Some(archive);
+    }
+
+    // 2. Copy the entries that stay to a new writer.
+    let temp = TempBeside::new(&zip_path);
+    let mut writer = ZipWriter::new(temp.create()?);
+
+    if let Some(mut archive) = existing {
+        // Raw copies keep each entry's method, CRC, mtime and permissions as they were.
+        for i in 0..archive.len() {
+            let entry = archive
+                .by_index_raw(i)
+                .map_err(|e| CapsuleError::zip("Existing zip entry error", e))?;
+            if added.contains(&zip_entry_name(&entry)) {
+                continue;
+            }
+            raw_copy_zip_entry(&mut writer, entry)
+                .map_err(|e| CapsuleError::zip("Temp zip copy error", e))?;
+        }
+    }
+
+    // 3. Add new files.
+    add_inputs_to_zip(
+        &mut writer,
+        &args.files,
+        ZipCompression::default(),
+        &[],
+        &mut CreateProgressTracker::default(),
+    )?;
+
+    writer
+        .finish()
+        .map_err(|e| CapsuleError::zip("Failed to finalize temp zip", e))?;
+
+    // 4. Replace original zip.
+    if args.strict_validate.unwrap_or(false) {
+        return validate_and_replace(temp, &zip_path, None);
+    }
+    temp.persist(&zip_path)
+        .map_err(|e| CapsuleError::io("Failed to replace original zip", e))?;
+
+    Ok(())
+}
+
+/// Shape for `remove_files_from_zip({ args: { zipPath, entryNames } })`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveFilesArgs {
+    pub zip_path: String,
+    pub entry_names: Vec<String>,
+}
+
+/// Remove entries from an existing ZIP.
+#[tauri::command]
+pub async fn remove_files_from_zip(args: RemoveFilesArgs) -> Result<(), CapsuleError> {
+    let zip_path = PathBuf::from(&args.zip_path);
+
+    let file = File::open(&zip_path)
+        .map_err(|e| CapsuleError::open("Failed to open existing zip", e))?;
+    let mut archive = ZipArchive::new(file)
+        .map_err(|e| CapsuleError::zip("Invalid existing zip", e))?;
+    reject_encrypted_entries(&zip_path, &mut archive)?;
+
+    let temp = TempBeside::new(&zip_path);
+    let mut writer = ZipWriter::new(temp.create()?);
+
+    for i in 0..archive.len() {
+        let entry = archive
+            .by_index_raw(i)
+            .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+
+        let name = zip_entry_name(&entry);
+        if args.entry_names.contains(&name) {
+            // Skip entries that should be removed
+            continue;
+        }
+
+        // Copied raw, so kept entries aren't recompressed and keep their metadata.
+        raw_copy_zip_entry(&mut writer, entry)
+            .map_err(|e| CapsuleError::zip("Temp zip copy error", e))?;
+    }
+
+    writer
+        .finish()
+        .map_err(|e| CapsuleError::zip("Failed to finalize temp zip", e))?;
+    temp.persist(&zip_path)
+        .map_err(|e| CapsuleError::io("Failed to replace original zip", e))?;
+
+    Ok(())
+}
+
+/// Shape for `rename_zip_entry({ args: { zipPath, oldName, newName } })`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenameEntryArgs {
+    pub zip_path: String,
+    pub old_name: String,
+    pub new_name: String,
+}
+
+/// Helper: entry names of `archive` in central directory order, decoded as in listings.
+fn zip_entry_names<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> Result<Vec<String>, CapsuleError> {
+    (0..archive.len())
+        .map(|i| {
+            archive
+                .by_index_raw(i)
+                .map(|entry| zip_entry_name(&entry))
+                .map_err(|e| CapsuleError::zip("Zip entry error", e))
+        })
+        .collect()
+}
+
+/// Helper: the `(index, new name)` pairs for moving entry `old` to `new` within `names`.
+/// A directory brings its contents along. Fails if `old` is missing, `new` escapes the
+/// archive root, or a new name is already taken by an entry that isn't moving.
+fn plan_entry_move(
+    names: &[String],
+    old: &str,
+    new: &str,
+) -> Result<Vec<(usize, String)>, CapsuleError> {
+    let wanted = old.trim_end_matches('/');
+    let old_name = names
+        .iter()
+        .find(|name| name.trim_end_matches('/') == wanted)
+        .ok_or_else(|| CapsuleError::NotFound(format!("Entry not found: {old}")))?;
+    let mut new_name = validate_entry_name(new)?;
+    if old_name.ends_with('/') {
+        new_name.push('/');
+    }
+
+    let plan: Vec<(usize, String)> = names
+        .iter()
+        .enumerate()
+        .filter_map(|(i, name)| {
+            if name == old_name {
+                Some((i, new_name.clone()))
+            } else if old_name.ends_with('/') {
+                name.strip_prefix(old_name.as_str())
+                    .map(|rest| (i, format!("{new_name}{rest}")))
+            } else {
+                None
+            }
+        })
+        .collect();
+    for (_, target) in &plan {
+        let taken = names
+            .iter()
+            .enumerate()
+            .any(|(j, name)| name == target && !plan.iter().any(|(i, _)| *i == j));
+        if taken {
+            return Err(CapsuleError::Other(format!(
+                "An entry named {target} already exists"
+            )));
+        }
+    }
+    Ok(plan)
+}
+
+/// Helper: rewrite the zip at `zip_path` via a temp file, copying entry `i` raw under
+/// `names[i]`, then replace the original.
+fn rewrite_zip_renamed(
+    zip_path: &Path,
+    archive: &mut ZipArchive<File>,
+    names: &[String],
+) -> Result<(), CapsuleError> {
+    let temp = TempBeside::new(zip_path);
+    let mut writer = ZipWriter::new(temp.create()?);
+
+    for (i, name) in names.iter().enumerate() {
+        let entry = archive
+            .by_index_raw(i)
+            .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+        writer
+            .raw_copy_file_rename(entry, name.as_str())
+            .map_err(|e| CapsuleError::zip("Temp zip copy error", e))?;
+    }
+
+    writer
+        .finish()
+        .map_err(|e| CapsuleError::zip("Failed to finalize temp zip", e))?;
+    temp.persist(zip_path)
+        .map_err(|e| CapsuleError::io("Failed to replace original zip", e))
+}
+
+/// Rename one entry of an existing ZIP by rewriting it to a temp file and then replacing.
+/// Renaming a directory moves its contents along. Entries are copied raw, so nothing is
+/// recompressed; zips with encrypted entries are refused since the copy would lose the flag.
+#[tauri::command]
+pub async fn rename_zip_entry(args: RenameEntryArgs) -> Result<(), CapsuleError> {
+    let zip_path = PathBuf::from(&args.zip_path);
+    let file =
+        File::open(&zip_path).map_err(|e| CapsuleError::open("Failed to open existing zip", e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid existing zip", e))?;
+    reject_encrypted_entries(&zip_path, &mut archive)?;
+
+    let mut names = zip_entry_names(&mut archive)?;
+    for (i, name) in plan_entry_move(&names, &args.old_name, &args.new_name)? {
+        names[i] = name;
+    }
+    rewrite_zip_renamed(&zip_path, &mut archive, &names)
+}
+
+/// Shape for `move_zip_entries({ args: { zipPath, mapping: [[old, new], ...] } })`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveEntriesArgs {
+    pub zip_path: String,
+    pub mapping: Vec<(String, String)>,
+}
+
+/// Result of `move_zip_entries`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoveEntriesResult {
+    /// Entries written under a new name, counting the contents of moved directories.
+    pub moved: usize,
+    /// Pairs that were skipped, keyed by their source name.
+    pub errors: Vec<FailedEntry>,
+}
+
+/// Move several entries (old → new paths) in one rewrite of the zip. Pairs apply in order,
+/// so later pairs see earlier moves; a pair that can't apply is reported and skipped.
+/// Zips with encrypted entries are refused, as for `rename_zip_entry`.
+#[tauri::command]
+pub async fn move_zip_entries(args: MoveEntriesArgs) -> Result<MoveEntriesResult, CapsuleError> {
+    let zip_path = PathBuf::from(&args.zip_path);
+    let file =
+        File::open(&zip_path).map_err(|e| CapsuleError::open("Failed to open existing zip", e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid existing zip", e))?;
+    reject_encrypted_entries(&zip_path, &mut archive)?;
+
+    let original = zip_entry_names(&mut archive)?;
+    let mut names = original.clone();
+    let mut errors = Vec::new();
+    for (old, new) in &args.mapping {
+        match plan_entry_move(&names, old, new) {
+            Ok(plan) => {
+                for (i, name) in plan {
+                    names[i] = name;
+                }
+            }
+            Err(error) => errors.push(FailedEntry {
+                name: old.clone(),
+                error: error.to_string(),
+            }),
+        }
+    }
+
+    let moved = names.iter().zip(&original).filter(|(a, b)| a != b).count();
+    if moved > 0 {
+        rewrite_zip_renamed(&zip_path, &mut archive, &names)?;
+    }
+    Ok(MoveEntriesResult { moved, errors })
+}
+
+/// Shape for `update_zip_entry({ args: { zipPath, entryName, contentBase64 } })`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateEntryArgs {
+    pub zip_path: String,
+    pub entry_name: String,
+    pub content_base64: String,
+}
+
+/// Replace one file's contents inside an existing ZIP, keeping its name, compression
+/// method and permissions. Every other entry is copied raw, so zips with encrypted entries
+/// are refused.
+#[tauri::command]
+pub async fn update_zip_entry(args: UpdateEntryArgs) -> Result<(), CapsuleError> {
+    let zip_path = PathBuf::from(&args.zip_path);
+    let data = BASE64
+        .decode(args.content_base64.as_bytes())
+        .map_err(|e| CapsuleError::Other(format!("Invalid base64 for {}: {e}", args.entry_name)))?;
+
+    let file =
+        File::open(&zip_path).map_err(|e| CapsuleError::open("Failed to open existing zip", e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid existing zip", e))?;
+    reject_encrypted_entries(&zip_path, &mut archive)?;
+    if zip_entry_index(&mut archive, &args.entry_name)?.is_none() {
+        return Err(CapsuleError::NotFound(format!(
+            "Entry not found: {}",
+            args.entry_name
+        )));
+    }
+
+    let temp = TempBeside::new(&zip_path);
+    let mut writer = ZipWriter::new(temp.create()?);
+    for i in 0..archive.len() {
+        let entry = archive
+            .by_index_raw(i)
+            .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+        if zip_entry_name(&entry) != args.entry_name {
+            raw_copy_zip_entry(&mut writer, entry)
+                .map_err(|e| CapsuleError::zip("Temp zip copy error", e))?;
+            continue;
+        }
+        if entry.is_dir() {
+            return Err(CapsuleError::Other(format!(
+                "Cannot update a directory: {}",
+                args.entry_name
+            )));
+        }
+        let mut options = FileOptions::default().compression_method(entry.compression());
+        if let Some(mode) = entry.unix_mode() {
+            options = options.unix_permissions(mode);
+        }
+        writer
+            .start_file(args.entry_name.as_str(), options)
+            .map_err(|e| CapsuleError::zip(format!("Failed to write {}", args.entry_name), e))?;
+        writer
+            .write_all(&data)
+            .map_err(|e| CapsuleError::io(format!("Failed to write {}", args.entry_name), e))?;
+    }
+
+    writer
+        .finish()
+        .map_err(|e| CapsuleError::zip("Failed to finalize temp zip", e))?;
+    temp.persist(&zip_path)
+        .map_err(|e| CapsuleError::io("Failed to replace original zip", e))?;
+    Ok(())
+}
+
+/// Simple "copy file" helper.
+#[tauri::command]
+pub async fn copy_file(src: String, dest: String) -> Result<(), CapsuleError> {
+    fs::copy(&src, &dest)
+        .map_err(|e| CapsuleError::io("Failed to copy file", e))?;
+    Ok(())
+}
+
+/// Helper: move `old` to `new`, copying and deleting when they're on different volumes.
+/// An existing `new` is only replaced with `overwrite`. A `.capidx` sidecar moves along.
+fn move_archive_file(old: &Path, new: &Path, overwrite: bool) -> Result<(), CapsuleError> {
+    if !old.is_file() {
+        return Err(CapsuleError::NotFound(format!(
+            "Archive not found: {}",
+            old.display()
+        )));
+    }
+    if old == new {
+        return Ok(());
+    }
+    // A case-only rename on a case-insensitive volume sees `new` as existing already; it's
+    // the same file, not one to protect.
+    let same_file = matches!(
+        (old.canonicalize(), new.canonicalize()),
+        (Ok(a), Ok(b)) if a == b
+    );
+    if new.exists() && !overwrite && !same_file {
+        return Err(CapsuleError::Other(format!(
+            "Destination already exists: {}",
+            new.display()
+        )));
+    }
+    if let Some(parent) = new.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| CapsuleError::io("Failed to create output dir", e))?;
+    }
+
+    match fs::rename(old, new) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            if let Err(e) = fs::copy(old, new) {
+                let _ = fs::remove_file(new);
+                return Err(CapsuleError::io("Failed to copy archive", e));
+            }
+            fs::remove_file(old).map_err(|e| CapsuleError::io("Failed to remove original", e))?;
+        }
+        Err(e) => {
+            return Err(CapsuleError::Other(format!(
+                "Failed to rename archive: {e}"
+            )))
+        }
+    }
+
+    let sidecar = index_path(old);
+    if sidecar.is_file() {
+        let moved = fs::rename(&sidecar, index_path(new));
+        if moved.is_err() {
+            let _ = fs::remove_file(&sidecar);
+        }
+    }
+    Ok(())
+}
+
+/// Rename or move an archive on disk (copy + delete across volumes), refusing to replace an
+/// existing `new_path` unless `overwrite` is set. Returns the final path.
+#[tauri::command]
+pub async fn rename_archive(
+    old_path: String,
+    new_path: String,
+    overwrite: Option<bool>,
+) -> Result<String, CapsuleError> {
+    move_archive_file(
+        Path::new(&old_path),
+        Path::new(&new_path),
+        overwrite.unwrap_or(false),
+    )?;
+    Ok(new_path)
+}
+
+/// Get file size in bytes.
+#[tauri::command]
+pub async fn get_file_size(path: String) -> Result<u64, CapsuleError> {
+    let metadata = fs::metadata(&path)
+        .map_err(|e| CapsuleError::io("Failed to read file metadata", e))?;
+    Ok(metadata.len())
+}
+
+/// Preview result shape for frontend.
+#[derive(Debug, Serialize)]
+pub struct PreviewResult {
+    pub kind: String,          // "text" | "binary"
+    pub mime: String,
+    pub text: Option<String>,  // for text previews
+    pub data_base64: Option<String>, // for binary previews if you want
+    pub size: u64,
+    /// Line-range previews only: whether the entry has lines past the returned range.
+    pub has_more_lines: Option<bool>,
+}
+
+/// Lines returned by a line-range preview when `lineCount` isn't given.
+const DEFAULT_PREVIEW_LINES: usize = 1000;
+/// Longest line a line-range preview reads before treating the entry as binary.
+const MAX_PREVIEW_LINE: u64 = 1024 * 1024;
+
+/// Largest entry read whole into memory for a preview.
+const MAX_PREVIEW_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Helper: lines `start..start + count` (0-based) of `reader`, and whether more follow.
+/// `None` when a returned line isn't UTF-8 or runs past `MAX_PREVIEW_LINE`.
+fn read_line_range(
+    reader: &mut dyn Read,
+    start: usize,
+    count: usize,
+) -> io::Result<Option<(String, bool)>> {
+    let mut reader = io::BufReader::new(reader);
+    let mut line = Vec::new();
+    let mut text = String::new();
+    let mut index = 0;
+    loop {
+        line.clear();
+        let read = (&mut reader)
+            .take(MAX_PREVIEW_LINE)
+            .read_until(b'\n', &mut line)?;
+        if read == 0 {
+            return Ok(Some((text, false)));
+        }
+        if index >= start.saturating_add(count) {
+            return Ok(Some((text, true)));
+        }
+        if line.len() as u64 == MAX_PREVIEW_LINE && !line.ends_with(b"\n") {
+            return Ok(None);
+        }
+        if index >= start {
+            match std::str::from_utf8(&line) {
+                Ok(part) => text.push_str(part),
+                Err(_) => return Ok(None),
+            }
+        }
+        index += 1;
+    }
+}
+
+/// Detect MIME type from file extension
+pub(crate) fn detect_mime_type(filename: &str) -> String {
+    let ext = filename
+        .rfind('.')
+        .map(|i| &filename[i + 1..])
+        .unwrap_or("")
+        .to_lowercase();
+
+    match ext.as_str() {
+        "jpg" | "jpeg" => "image/jpeg".into(),
+        "png" => "image/png".into(),
+        "gif" => "image/gif".into(),
+        "webp" => "image/webp".into(),
+        "svg" => "image/svg+xml".into(),
+        "bmp" => "image/bmp".into(),
+        "tif" | "tiff" => "image/tiff".into(),
+        "ico" => "image/x-icon".into(),
+        "json" => "application/json".into(),
+        "xml" => "application/xml".into(),
+        "html" | "htm" => "text/html".into(),
+        "css" => "text/css".into(),
+        "js" => "application/javascript".into(),
+        "ts" => "application/typescript".into(),
+        "md" | "markdown" => "text/markdown".into(),
+        "py" => "text/x-python".into(),
+        "rs" => "text/x-rust".into(),
+        "sh" | "bash" => "text/x-shellscript".into(),
+        "c" => "text/x-c".into(),
+        "h" => "text/x-c".into(),
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" => "text/x-c++".into(),
+        "go" => "text/x-go".into(),
+        "java" => "text/x-java".into(),
+        "rb" => "text/x-ruby".into(),
+        "php" => "text/x-php".into(),
+        "sql" => "text/x-sql".into(),
+        "toml" => "text/x-toml".into(),
+        "yaml" | "yml" => "text/yaml".into(),
+        "ini" | "cfg" | "conf" => "text/plain".into(),
+        "csv" => "text/csv".into(),
+        "log" => "text/plain".into(),
+        "txt" => "text/plain".into(),
+        _ => "application/octet-stream".into(),
+    }
+}
+
+/// How much of an entry `sniff_mime_type` looks at.
+const MIME_SNIFF_LEN: usize = 8 * 1024;
+
+/// Helper: `mime`, except that an unknown (`application/octet-stream`) type becomes
+/// `text/plain` when `sample` looks like text: valid UTF-8 (a character cut off at the end
+/// is fine), no NUL bytes and few control characters.
+fn sniff_mime_type(mime: String, sample: &[u8]) -> String {
+    if mime != "application/octet-stream" || sample.is_empty() {
+        return mime;
+    }
+    let sample = &sample[..sample.len().min(MIME_SNIFF_LEN)];
+    let text = match std::str::from_utf8(sample) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&sample[..e.valid_up_to()]).unwrap_or_default()
+        }
+        Err(_) => return mime,
+    };
+    let controls = text
+        .chars()
+        .filter(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\x0c' | '\x1b'))
+        .count();
+    if text.is_empty() || text.contains('\0') || controls * 20 > text.len() {
+        return mime;
+    }
+    "text/plain".into()
+}
+
+/// Helper: if a JPEG/TIFF carries a non-default EXIF orientation, decode it, rotate/flip it
+/// upright and re-encode (JPEG stays JPEG, TIFF becomes PNG so the webview can show it).
+/// Returns the new bytes and MIME type, or `None` when nothing needs to change or the image
+/// can't be decoded (e.g. a truncated preview).
+fn apply_exif_orientation(bytes: &[u8], mime: &str) -> Option<(Vec<u8>, String)> {
+    if mime != "image/jpeg" && mime != "image/tiff" {
+        return None;
+    }
+
+    let exif = exif::Reader::new()
+        .read_from_container(&mut io::Cursor::new(bytes))
+        .ok()?;
+    let orientation = exif
+        .get_field(exif::Tag::Orientation, exif::In::PRIMARY)?
+        .value
+        .get_uint(0)?;
+    let orientation = image::metadata::Orientation::from_exif(u8::try_from(orientation).ok()?)?;
+    if orientation == image::metadata::Orientation::NoTransforms {
+        return None;
+    }
+
+    let mut img = image::load_from_memory(bytes).ok()?;
+    img.apply_orientation(orientation);
+
+    let mut out = io::Cursor::new(Vec::new());
+    if mime == "image/jpeg" {
+        let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, 90);
+        img.to_rgb8().write_with_encoder(encoder).ok()?;
+        Some((out.into_inner(), mime.to_string()))
+    } else {
+        img.write_to(&mut out, image::ImageFormat::Png).ok()?;
+        Some((out.into_inner(), "image/png".into()))
+    }
+}
+
+/// Error returned when a preview is requested for a directory entry.
+pub(crate) const CANNOT_PREVIEW_DIRECTORY: &str = "Cannot preview a directory";
+
+/// Helper: find the stored zip name for `entry_path`, ignoring a leading "./" and a trailing
+/// slash on either side. Directories, explicit or only implied by their children, are rejected.
+fn resolve_zip_entry_name<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    entry_path: &str,
+) -> Result<String, CapsuleError> {
+    fn normalize(mut name: &str) -> &str {
+        while let Some(rest) = name.strip_prefix("./") {
+            name = rest;
+        }
+        name.trim_end_matches('/')
+    }
+
+    let wanted = normalize(entry_path);
+    if wanted.is_empty() {
+        return Err(CapsuleError::InvalidArchive(
+            CANNOT_PREVIEW_DIRECTORY.into(),
+        ));
+    }
+
+    let mut is_dir = false;
+    for name in zip_entry_names(archive)? {
+        let normalized = normalize(&name);
+        if normalized == wanted {
+            if !name.ends_with('/') {
+                return Ok(name);
+            }
+            is_dir = true;
+        } else if normalized
+            .strip_prefix(wanted)
+            .is_some_and(|rest| rest.starts_with('/'))
+        {
+            is_dir = true;
+        }
+    }
+
+    if is_dir {
+        Err(CapsuleError::InvalidArchive(
+            CANNOT_PREVIEW_DIRECTORY.into(),
+        ))
+    } else {
+        Err(CapsuleError::NotFound(format!(
+            "Entry not found: {entry_path}"
+        )))
+    }
+}
+
+/// Basic preview: currently only supports ZIP entries. With `start_line` and/or
+/// `line_count`, a UTF-8 text entry is streamed and only those lines (0-based, defaulting
+/// to the first `DEFAULT_PREVIEW_LINES`) are returned.
+#[tauri::command]
+pub async fn preview_archive_entry(
+    cache: State<'_, ArchiveCache>,
+    archive_path: String,
+    entry_path: String,
+    password: Option<String>,
+    start_line: Option<usize>,
+    line_count: Option<usize>,
+) -> Result<PreviewResult, CapsuleError> {
+    let lines = (start_line.is_some() || line_count.is_some()).then(|| {
+        (
+            start_line.unwrap_or(0),
+            line_count.unwrap_or(DEFAULT_PREVIEW_LINES),
+        )
+    });
+    preview_archive_entry_cached(&cache, archive_path, entry_path, password, lines)
+}
+
+/// Helper: `preview_archive_entry` without the Tauri state wrapper.
+fn preview_archive_entry_cached(
+    cache: &ArchiveCache,
+    archive_path: String,
+    entry_path: String,
+    password: Option<String>,
+    lines: Option<(usize, usize)>,
+) -> Result<PreviewResult, CapsuleError> {
+    let path = PathBuf::from(&archive_path);
+    let kind = detect_archive_type(&path);
+
+    if kind != "zip" {
+        return Err(CapsuleError::Unsupported(
+            "Preview currently only implemented for ZIP archives".into(),
+        ));
+    }
+
+    cache.with_zip(&path, |archive| {
+        preview_zip_entry(archive, &entry_path, password.as_deref(), lines)
+    })
+}
+
+/// Helper: build the preview of `entry_path` in an open zip. `lines` asks for a
+/// (start, count) line range; entries that aren't UTF-8 text get the normal preview.
+fn preview_zip_entry(
+    archive: &mut ZipArchive<File>,
+    entry_path: &str,
+    password: Option<&str>,
+    lines: Option<(usize, usize)>,
+) -> Result<PreviewResult, CapsuleError> {
+    let name = resolve_zip_entry_name(archive, entry_path)?;
+    let mime = detect_mime_type(entry_path);
+
+    if let (Some((start, count)), false) = (lines, mime.starts_with("image/")) {
+        let mut entry = open_zip_entry_by_name(archive, &name, password)?;
+        let size = entry.size();
+        let range = read_line_range(&mut entry, start, count)
+            .map_err(|e| CapsuleError::io("Failed to read entry", e))?;
+        if let Some((text, more)) = range {
+            return Ok(PreviewResult {
+                kind: "text".into(),
+                mime: sniff_mime_type(mime, text.as_bytes()),
+                text: Some(text),
+                data_base64: None,
+                size,
+                has_more_lines: Some(more),
+            });
+        }
+    }
+
+    let mut entry = open_zip_entry_by_name(archive, &name, password)?;
+    let size = entry.size();
+
+    // A single `read` may stop short on compressed streams, so read until the cap.
+    let mut buf = Vec::with_capacity(size.min(MAX_PREVIEW_BYTES) as usize);
+    entry
+        .by_ref()
+        .take(MAX_PREVIEW_BYTES)
+        .read_to_end(&mut buf)
+        .map_err(|e| CapsuleError::io("Failed to read entry", e))?;
+    // Extensionless files like README or Makefile are usually text.
+    let mime = sniff_mime_type(mime, &buf);
+
+    // Check if it's an image based on MIME type
+    if mime.starts_with("image/") {
+        // Photos are often stored sideways with an EXIF rotation; send them upright.
+        let (data, mime) = apply_exif_orientation(&buf, &mime).unwrap_or((buf, mime));
+        return Ok(PreviewResult {
+            kind: "binary".into(),
+            mime,
+            text: None,
+            data_base64: Some(BASE64.encode(&data)),
+            size,
+            has_more_lines: None,
+        });
+    }
+
+    // Try to decode as UTF-8 text
+    match String::from_utf8(buf.clone()) {
+        Ok(text) => {
+            // It's valid UTF-8, treat as text
+            let short = if text.len() > 500 * 1024 {
+                // Limit text preview to 500KB for performance, cutting between characters.
+                let mut end = 500 * 1024;
+                while !text.is_char_boundary(end) {
+                    end -= 1;
+                }
+                format!("{}…\n\n[Preview truncated. Full file is {} bytes]", 
+                    &text[..end], size)
+            } else {
+                text
+            };
+
+            Ok(PreviewResult {
+                kind: "text".into(),
+                mime,
+                text: Some(short),
+                data_base64: None,
+                size,
+                has_more_lines: None,
+            })
+        }
+        Err(_) => {
+            // Not valid UTF-8, treat as binary
+            // For binary, only include first 64KB for hex preview
+            let hex_preview_size = if buf.len() > 64 * 1024 {
+                64 * 1024
+            } else {
+                buf.len()
+            };
+            let hex_buf = &buf[..hex_preview_size];
+
+            Ok(PreviewResult {
+                kind: "binary".into(),
+                mime,
+                text: None,
+                data_base64: Some(BASE64.encode(hex_buf)),
+                size,
+                has_more_lines: None,
+            })
+        }
+    }
+}
+
+/// Helper: open a zip entry by name for reading, decrypting it with `password` when given.
+fn open_zip_entry_by_name<'a>(
+    archive: &'a mut ZipArchive<File>,
+    name: &str,
+    password: Option<&str>,
+) -> Result<zip::read::ZipFile<'a>, CapsuleError> {
+    let index = zip_entry_index(archive, name)?
+        .ok_or_else(|| CapsuleError::NotFound(format!("Entry not found: {name}")))?;
+    open_zip_entry(archive, index, password)
+}
+
+/// Helper: read a whole entry, failing if it is larger than `MAX_PREVIEW_BYTES`.
+fn read_preview_bytes(reader: &mut dyn Read, entry_path: &str) -> Result<Vec<u8>, CapsuleError> {
+    let mut buf = Vec::new();
+    reader
+        .take(MAX_PREVIEW_BYTES + 1)
+        .read_to_end(&mut buf)
+        .map_err(|e| CapsuleError::io("Failed to read entry", e))?;
+    if buf.len() as u64 > MAX_PREVIEW_BYTES {
+        return Err(preview_too_large(entry_path));
+    }
+    Ok(buf)
+}
+
+/// Helper: the error for an entry over `MAX_PREVIEW_BYTES`.
+fn preview_too_large(entry_path: &str) -> CapsuleError {
+    CapsuleError::Other(format!(
+        "{entry_path} is larger than the {} MB preview limit",
+        MAX_PREVIEW_BYTES / (1024 * 1024)
+    ))
+}
+
+/// Helper: the contents of one file entry in any supported archive, up to
+/// `MAX_PREVIEW_BYTES`.
+fn read_archive_entry(
+    cache: &ArchiveCache,
+    path: &Path,
+    entry_path: &str,
+) -> Result<Vec<u8>, CapsuleError> {
+    with_archive_entry(cache, path, entry_path, Some(MAX_PREVIEW_BYTES), |reader| {
+        read_preview_bytes(reader, entry_path)
+    })
+}
+
+/// Helper: find one file entry in any supported archive and hand its decompressed data to
+/// `f`. Entries whose recorded size exceeds `size_limit` are rejected before any reading.
+/// unrar can only extract whole entries to disk, so a RAR entry goes through a temp file.
+fn with_archive_entry<T>(
+    cache: &ArchiveCache,
+    path: &Path,
+    entry_path: &str,
+    size_limit: Option<u64>,
+    f: impl FnOnce(&mut dyn Read) -> Result<T, CapsuleError>,
+) -> Result<T, CapsuleError> {
+    let wanted = entry_path.trim_start_matches("./");
+    let not_found = || CapsuleError::NotFound(format!("Entry not found: {entry_path}"));
+    let check_size = |size: u64| match size_limit {
+        Some(limit) if size > limit => Err(preview_too_large(entry_path)),
+        _ => Ok(()),
+    };
+    match archive_type(path) {
+        "zip" => cache.with_zip(path, |archive| {
+            let name = resolve_zip_entry_name(archive, entry_path)?;
+            let mut entry = open_zip_entry_by_name(archive, &name, None)?;
+            check_size(entry.size())?;
+            f(&mut entry)
+        }),
+        kind if kind.starts_with("tar") => {
+            let mut archive = open_tar_reader(path, kind)?;
+            let entries = archive.entries().map_err(|e| {
+                CapsuleError::InvalidArchive(format!("Failed to read tar entries: {e}"))
+            })?;
+            for entry_res in entries {
+                let mut entry = entry_res
+                    .map_err(|e| CapsuleError::InvalidArchive(format!("Tar entry error: {e}")))?;
+                let name = tar_entry_name(&entry, NameEncoding::default()).0;
+                if name.trim_start_matches("./") == wanted && entry.header().entry_type().is_file()
+                {
+                    check_size(entry.size())?;
+                    return f(&mut entry);
+                }
+            }
+            Err(not_found())
+        }
+        "7z" => {
+            let mut reader = SevenZReader::open(path, Password::empty())
+                .map_err(|e| CapsuleError::InvalidArchive(format!("Invalid 7z archive: {e}")))?;
+            let mut f = Some(f);
+            let mut found = None;
+            reader
+                .for_each_entries(|entry, data| {
+                    if entry.is_directory() || entry.name().replace('\\', "/") != wanted {
+                        return Ok(true);
+                    }
+                    if let Some(f) = f.take() {
+                        found = Some(check_size(entry.size()).and_then(|_| f(data)));
+                    }
+                    Ok(false)
+                })
+                .map_err(|e| CapsuleError::InvalidArchive(format!("7z read error: {e}")))?;
+            found.unwrap_or_else(|| Err(not_found()))
+        }
+        "rar" => {
+            let mut cursor = unrar::Archive::new(path)
+                .open_for_processing()
+                .map_err(|e| rar_error(e, "Invalid rar archive"))?;
+            while let Some(header) = cursor
+                .read_header()
+                .map_err(|e| rar_error(e, "Rar entry error"))?
+            {
+                let entry = header.entry();
+                let matches = !entry.is_directory()
+                    && entry.filename.to_string_lossy().replace('\\', "/") == wanted;
+                if !matches {
+                    cursor = header.skip().map_err(|e| rar_error(e, "Rar read error"))?;
+                    continue;
+                }
+                check_size(entry.unpacked_size)?;
+                let temp = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+                let result = header
+                    .extract_to(&temp)
+                    .map_err(|e| rar_error(e, "Rar read error"))
+                    .and_then(|_| {
+                        let mut file = File::open(&temp)
+                            .map_err(|e| CapsuleError::io("Failed to read extracted entry", e))?;
+                        f(&mut file)
+                    });
+                let _ = fs::remove_file(&temp);
+                return result;
+            }
+            Err(not_found())
+        }
+        kind @ ("gz" | "bz2" | "xz" | "zst") => {
+            if wanted != compressed_file_entry_name(path) {
+                return Err(not_found());
+            }
+            f(&mut open_compressed_file(path, kind)?)
+        }
+        _ => Err(CapsuleError::Unsupported("Unsupported archive type".into())),
+    }
+}
+
+/// Extract one file entry to `dest_file`, a path the caller chose (e.g. from a "Save
+/// As…" dialog), creating its parent directories. Returns the bytes written.
+#[tauri::command]
+pub async fn extract_single_entry(
+    cache: State<'_, ArchiveCache>,
+    archive_path: String,
+    entry_path: String,
+    dest_file: String,
+) -> Result<u64, CapsuleError> {
+    extract_entry_to_file(
+        &cache,
+        Path::new(&archive_path),
+        &entry_path,
+        Path::new(&dest_file),
+    )
+}
+
+/// Helper: `extract_single_entry` without the Tauri state wrapper. A failed write removes
+/// the partial output.
+fn extract_entry_to_file(
+    cache: &ArchiveCache,
+    path: &Path,
+    entry_path: &str,
+    dest_file: &Path,
+) -> Result<u64, CapsuleError> {
+    if dest_file.as_os_str().is_empty() || dest_file.is_dir() {
+        return Err(CapsuleError::Other(format!(
+            "Not a file path: {}",
+            dest_file.display()
+        )));
+    }
+    if let Some(parent) = dest_file.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| CapsuleError::io("Failed to create output dir", e))?;
+    }
+    with_archive_entry(cache, path, entry_path, None, |reader| {
+        stream_to_file(reader, dest_file, DEFAULT_STREAM_BUFFER_SIZE).map_err(|e| {
+            let _ = fs::remove_file(dest_file);
+            CapsuleError::io(format!("Failed to write {}", dest_file.display()), e)
+        })
+    })
+}
+
+/// A `data:` URL for an image entry in any supported archive, ready for an `<img>` src.
+/// Entries over the 10 MB preview cap and non-image entries are rejected.
+#[tauri::command]
+pub async fn preview_image_data_url(
+    cache: State<'_, ArchiveCache>,
+    archive_path: String,
+    entry_path: String,
+) -> Result<String, CapsuleError> {
+    image_data_url(&cache, &archive_path, &entry_path)
+}
+
+/// Helper: `preview_image_data_url` without the Tauri state wrapper.
+fn image_data_url(
+    cache: &ArchiveCache,
+    archive_path: &str,
+    entry_path: &str,
+) -> Result<String, CapsuleError> {
+    let mime = detect_mime_type(entry_path);
+    if !mime.starts_with("image/") {
+        return Err(CapsuleError::Other(format!(
+            "Not an image entry: {entry_path} ({mime})"
+        )));
+    }
+    let buf = read_archive_entry(cache, Path::new(archive_path), entry_path)?;
+    let (data, mime) = apply_exif_orientation(&buf, &mime).unwrap_or((buf, mime));
+    Ok(format!("data:{mime};base64,{}", BASE64.encode(&data)))
+}
+
+/// Result of `read_entry_text`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryText {
+    pub text: String,
+    /// The entry is longer than `max_bytes`; `text` holds only its start.
+    pub truncated: bool,
+}
+
+/// The complete UTF-8 text of an entry, for editing rather than previewing, so no
+/// preview cap applies. With `max_bytes`, reads at most that much (cut back to a whole
+/// character). Content that isn't UTF-8 fails with the `notText` code.
+#[tauri::command]
+pub async fn read_entry_text(
+    cache: State<'_, ArchiveCache>,
+    archive_path: String,
+    entry_path: String,
+    max_bytes: Option<u64>,
+) -> Result<EntryText, CapsuleError> {
+    entry_text(&cache, Path::new(&archive_path), &entry_path, max_bytes)
+}
+
+/// Helper: `read_entry_text` without the Tauri state wrapper.
+fn entry_text(
+    cache: &ArchiveCache,
+    path: &Path,
+    entry_path: &str,
+    max_bytes: Option<u64>,
+) -> Result<EntryText, CapsuleError> {
+    let (mut buf, truncated) = with_archive_entry(cache, path, entry_path, None, |reader| {
+        let mut buf = Vec::new();
+        let truncated = match max_bytes {
+            Some(max) => {
+                reader
+                    .take(max + 1)
+                    .read_to_end(&mut buf)
+                    .map_err(|e| CapsuleError::io("Failed to read entry", e))?;
+                let truncated = buf.len() as u64 > max;
+                buf.truncate(max as usize);
+                truncated
+            }
+            None => {
+                reader
+                    .read_to_end(&mut buf)
+                    .map_err(|e| CapsuleError::io("Failed to read entry", e))?;
+                false
+            }
+        };
+        Ok((buf, truncated))
+    })?;
+
+    if let Err(e) = std::str::from_utf8(&buf) {
+        // A cut at `max_bytes` may split a character; drop the partial one.
+        if !(truncated && e.error_len().is_none()) {
+            return Err(CapsuleError::NotText(format!(
+                "{entry_path} is not UTF-8 text (invalid byte at offset {})",
+                e.valid_up_to()
+            )));
+        }
+        buf.truncate(e.valid_up_to());
+    }
+    let text = String::from_utf8(buf).map_err(|e| CapsuleError::NotText(e.to_string()))?;
+    Ok(EntryText { text, truncated })
+}
+
+/// Payload of the `entry-text://chunk` event sent by `stream_entry_text`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryTextChunk {
+    pub operation_id: Option<String>,
+    pub index: usize,
+    pub text: String,
+}
+
+/// Payload of the `entry-text://done` event, sent once after the last chunk.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryTextDone {
+    pub operation_id: Option<String>,
+    pub chunks: usize,
+    pub bytes: u64,
+}
+
+/// Helper: read `reader` as UTF-8 and pass it to `emit` in pieces of about `chunk_bytes`.
+/// A character split by a chunk boundary is held back for the next piece. Returns the
+/// number of chunks and bytes sent.
+fn stream_text_chunks(
+    reader: &mut dyn Read,
+    chunk_bytes: usize,
+    emit: &mut dyn FnMut(usize, String),
+) -> Result<(usize, u64), CapsuleError> {
+    let not_text = |offset: u64| {
+        CapsuleError::NotText(format!(
+            "Entry is not UTF-8 text (invalid byte at offset {offset})"
+        ))
+    };
+    let mut pending = Vec::new();
+    let (mut chunks, mut bytes) = (0, 0);
+    loop {
+        let carried = pending.len();
+        reader
+            .take(chunk_bytes.max(4) as u64)
+            .read_to_end(&mut pending)
+            .map_err(|e| CapsuleError::io("Failed to read entry", e))?;
+        let at_end = pending.len() == carried;
+        let valid = match std::str::from_utf8(&pending) {
+            Ok(_) => pending.len(),
+            Err(e) if e.error_len().is_none() && !at_end => e.valid_up_to(),
+            Err(e) => return Err(not_text(bytes + e.valid_up_to() as u64)),
+        };
+        if valid > 0 {
+            let rest = pending.split_off(valid);
+            let text = String::from_utf8(std::mem::replace(&mut pending, rest))
+                .map_err(|_| not_text(bytes))?;
+            bytes += text.len() as u64;
+            emit(chunks, text);
+            chunks += 1;
+        }
+        if at_end {
+            return Ok((chunks, bytes));
+        }
+    }
+}
+
+/// Read a text entry and send it progressively as `entry-text://chunk` events of about
+/// `chunk_bytes` each (never splitting a character), then `entry-text://done`. For large
+/// logs that would stall a single `preview_archive_entry` call.
+#[tauri::command]
+pub async fn stream_entry_text(
+    app: AppHandle,
+    cache: State<'_, ArchiveCache>,
+    archive_path: String,
+    entry_path: String,
+    chunk_bytes: usize,
+    operation_id: Option<String>,
+) -> Result<(), CapsuleError> {
+    let path = Path::new(&archive_path);
+    let mut emit = |index, text| {
+        let chunk = EntryTextChunk {
+            operation_id: operation_id.clone(),
+            index,
+            text,
+        };
+        let _ = app.emit("entry-text://chunk", chunk);
+    };
+    let (chunks, bytes) = with_archive_entry(&cache, path, &entry_path, None, |reader| {
+        Ok(stream_text_chunks(reader, chunk_bytes, &mut emit))
+    })??;
+    let done = EntryTextDone {
+        operation_id,
+        chunks,
+        bytes,
+    };
+    let _ = app.emit("entry-text://done", done);
+    Ok(())
+}
+
+/// Extract a single entry to a temp file and return its path.
+/// You can later open it with the OS using `tauri-plugin-opener`.
+#[tauri::command]
+pub async fn extract_archive_entry_to_temp(
+    cache: State<'_, ArchiveCache>,
+    archive_path: String,
+    entry_path: String,
+    temp_dir: Option<String>,
+) -> Result<String, CapsuleError> {
+    extract_archive_entry_to_temp_cached(&cache, archive_path, entry_path, temp_dir)
+}
+
+/// Helper: `extract_archive_entry_to_temp` without the Tauri state wrapper.
+fn extract_archive_entry_to_temp_cached(
+    cache: &ArchiveCache,
+    archive_path: String,
+    entry_path: String,
+    temp_dir: Option<String>,
+) -> Result<String, CapsuleError> {
+    let path = PathBuf::from(&archive_path);
+    let kind = archive_type(&path);
+
+    let base_temp = temp_dir
+        .map(PathBuf::from)
+        .unwrap_or(std::env::temp_dir());
+
+    match kind {
+        "zip" => cache.with_zip(&path, |archive| {
+            let index = zip_entry_index(archive, &entry_path)?
+                .ok_or_else(|| CapsuleError::NotFound(format!("Entry not found: {entry_path}")))?;
+            let mut entry = archive
+                .by_index(index)
+                .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+            write_entry_to_temp(&base_temp, &entry_path, &mut entry)
+        }),
+        "tar" | "tar.gz" | "tar.bz2" | "tar.xz" | "tar.zst" => {
+            let wanted = entry_path.trim_start_matches("./");
+            let mut archive = open_tar_reader(&path, kind)?;
+            let entries = archive.entries().map_err(|e| {
+                CapsuleError::InvalidArchive(format!("Failed to read tar entries: {e}"))
+            })?;
+            for entry_res in entries {
+                let mut entry = entry_res
+                    .map_err(|e| CapsuleError::InvalidArchive(format!("Tar entry error: {e}")))?;
+                let name = tar_entry_name(&entry, NameEncoding::default()).0;
+                if name.trim_start_matches("./") != wanted {
+                    continue;
+                }
+                if !entry.header().entry_type().is_file() {
+                    return Err(CapsuleError::Other(format!(
+                        "Not a regular file: {entry_path}"
+                    )));
+                }
+                return write_entry_to_temp(&base_temp, &name, &mut entry);
+            }
+            Err(CapsuleError::NotFound(format!(
+                "Entry not found: {entry_path}"
+            )))
+        }
+        _ => Err(CapsuleError::Unsupported(
+            "Temp-entry extraction currently only implemented for ZIP and TAR".into(),
+        )),
+    }
+}
+
+/// Helper: stream an entry to `<base_temp>/<uuid>/<name>`, keeping the entry's
+/// directory structure. The fresh subdir keeps repeated previews from colliding.
+fn write_entry_to_temp(
+    base_temp: &Path,
+    name: &str,
+    entry: &mut dyn Read,
+) -> Result<String, CapsuleError> {
+    let temp_dir = base_temp.join(uuid::Uuid::new_v4().to_string());
+    let out_path = validate_extract_path(&temp_dir, Path::new(name))?;
+    if let Some(parent) = out_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| CapsuleError::io("Failed to create temp dir", e))?;
+    }
+
+    stream_to_file(entry, &out_path, DEFAULT_STREAM_BUFFER_SIZE)
+        .map_err(|e| CapsuleError::io("Failed to write temp file", e))?;
+
+    Ok(out_path.to_string_lossy().to_string())
+}
+
+/// Extract one zip entry to `output_path` only if its SHA-256 matches `expected_sha256`
+/// (hex, any case). The data is hashed while it is written to a `.partial` file beside the
+/// output, which is renamed into place on a match and deleted otherwise, so a tampered
+/// entry never appears at `output_path`.
+#[tauri::command]
+pub async fn extract_entry_verified(
+    archive_path: String,
+    entry_path: String,
+    output_path: String,
+    expected_sha256: String,
+) -> Result<(), CapsuleError> {
+    use sha2::{Digest, Sha256};
+
+    let expected = expected_sha256.trim().to_lowercase();
+    if expected.len() != 64 || !expected.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(CapsuleError::Other(
+            "Expected SHA-256 must be 64 hex characters".into(),
+        ));
+    }
+
+    let path = PathBuf::from(&archive_path);
+    if detect_archive_type(&path) != "zip" {
+        return Err(CapsuleError::Unsupported(
+            "Verified extraction currently only implemented for ZIP".into(),
+        ));
+    }
+
+    let file = File::open(&path).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip", e))?;
+    let index = zip_entry_index(&mut archive, &entry_path)?
+        .ok_or_else(|| CapsuleError::NotFound(format!("Entry not found: {entry_path}")))?;
+    let mut entry = archive
+        .by_index(index)
+        .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+
+    let output = PathBuf::from(&output_path);
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| CapsuleError::io("Failed to create output dir", e))?;
+    }
+    let mut partial_name = output.as_os_str().to_owned();
+    partial_name.push(".partial");
+    let partial = PathBuf::from(partial_name);
+
+    let written = (|| -> Result<String, CapsuleError> {
+        let mut outfile = File::create(&partial)
+            .map_err(|e| CapsuleError::io("Failed to create output file", e))?;
+        let mut hasher = Sha256::new();
+        let mut buf = [0u8; 64 * 1024];
+        loop {
+            let n = entry
+                .read(&mut buf)
+                .map_err(|e| CapsuleError::io("Failed to read entry", e))?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            outfile
+                .write_all(&buf[..n])
+                .map_err(|e| CapsuleError::io("Failed to write output file", e))?;
+        }
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect())
+    })();
+
+    match written {
+        Ok(actual) if actual == expected => fs::rename(&partial, &output)
+            .map_err(|e| CapsuleError::io("Failed to move verified file into place", e)),
+        Ok(actual) => {
+            let _ = fs::remove_file(&partial);
+            Err(CapsuleError::Other(format!(
+                "SHA-256 mismatch for {entry_path}: expected {expected}, got {actual}"
+            )))
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+/// Whole-file digest returned by `verify_archive_checksum`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecksumResult {
+    pub algorithm: String,
+    pub digest: String,
+    /// Set only when an expected digest was given.
+    pub matches: Option<bool>,
+}
+
+/// Helper: stream `path` through hasher `D` and return the lowercase hex digest.
+fn file_digest<D: sha2::Digest + Write>(path: &Path) -> Result<String, CapsuleError> {
+    let mut file = File::open(path).map_err(|e| CapsuleError::open("Failed to open file", e))?;
+    let mut hasher = D::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| CapsuleError::io("Failed to read file", e))?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+/// Hash the archive file itself (not its entries) with `md5`, `sha1` or `sha256`, e.g. to
+/// check a download before extracting. `expected` is compared as hex, ignoring case.
+#[tauri::command]
+pub async fn verify_archive_checksum(
+    path: String,
+    algorithm: String,
+    expected: Option<String>,
+) -> Result<ChecksumResult, CapsuleError> {
+    let path = PathBuf::from(&path);
+    let (algorithm, digest) = match algorithm.to_lowercase().replace('-', "").as_str() {
+        "md5" => ("md5", file_digest::<md5::Md5>(&path)?),
+        "sha1" => ("sha1", file_digest::<sha1::Sha1>(&path)?),
+        "sha256" => ("sha256", file_digest::<sha2::Sha256>(&path)?),
+        _ => {
+            return Err(CapsuleError::Unsupported(format!(
+                "Unsupported checksum algorithm: {algorithm}"
+            )))
+        }
+    };
+    let matches = expected.map(|expected| expected.trim().eq_ignore_ascii_case(&digest));
+
+    Ok(ChecksumResult {
+        algorithm: algorithm.into(),
+        digest,
+        matches,
+    })
+}
+
+/// One entry held in memory by `extract_all_to_memory`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InMemoryEntry {
+    pub name: String,
+    pub data_base64: String,
+}
+
+/// Result of `extract_all_to_memory`; `truncated` is set when the cap stopped it early.
+#[derive(Debug, Default, Serialize)]
+pub struct InMemoryExtraction {
+    pub entries: Vec<InMemoryEntry>,
+    pub truncated: bool,
+}
+
+/// Helper: read one entry into `out` unless it would push the total past `max_total_bytes`.
+/// Returns false once the cap is reached. `size` is only what the header declares, so the
+/// read itself is capped too.
+fn take_into_memory<R: Read>(
+    reader: &mut R,
+    name: String,
+    size: u64,
+    max_total_bytes: u64,
+    total: &mut u64,
+    out: &mut InMemoryExtraction,
+) -> Result<bool, CapsuleError> {
+    let remaining = max_total_bytes.saturating_sub(*total);
+    if size > remaining {
+        out.truncated = true;
+        return Ok(false);
+    }
+    let mut buf = Vec::with_capacity(size as usize);
+    reader
+        .take(remaining.saturating_add(1))
+        .read_to_end(&mut buf)
+        .map_err(|e| CapsuleError::io(format!("Failed to read entry {name}"), e))?;
+    if buf.len() as u64 > remaining {
+        out.truncated = true;
+        return Ok(false);
+    }
+    *total = total.saturating_add(buf.len() as u64);
+    out.entries.push(InMemoryEntry {
+        name,
+        data_base64: BASE64.encode(&buf),
+    });
+    Ok(true)
+}
+
+/// Extract file entries (optionally only images) into memory, in archive order, so the
+/// frontend can cache them for instant gallery navigation. Stops at the first entry that
+/// would exceed `max_total_bytes` of decompressed data.
+#[tauri::command]
+pub async fn extract_all_to_memory(
+    path: String,
+    max_total_bytes: u64,
+    images_only: Option<bool>,
+) -> Result<InMemoryExtraction, CapsuleError> {
+    let path_buf = PathBuf::from(&path);
+    let kind = archive_type(&path_buf);
+    let images_only = images_only.unwrap_or(false);
+    let wanted = |name: &str| !images_only || detect_mime_type(name).starts_with("image/");
+    let mut out = InMemoryExtraction::default();
+    let mut total = 0;
+
+    if kind == "zip" {
+        let file =
+            File::open(&path_buf).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+        let mut archive =
+            ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+        for i in 0..archive.len() {
+            let mut entry = archive
+                .by_index(i)
+                .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+            let name = zip_entry_name(&entry);
+            if entry.is_dir() || !wanted(&name) {
+                continue;
+            }
+            let size = entry.size();
+            if !take_into_memory(
+                &mut entry,
+                name,
+                size,
+                max_total_bytes,
+                &mut total,
+                &mut out,
+            )? {
+                break;
+            }
+        }
+    } else if kind.starts_with("tar") {
+        let mut archive = open_tar_reader(&path_buf, kind)?;
+        let entries = archive
+            .entries()
+            .map_err(|e| CapsuleError::InvalidArchive(format!("Failed to read tar entries: {e}")))?;
+        for entry_res in entries {
+            let mut entry = entry_res
+                .map_err(|e| CapsuleError::InvalidArchive(format!("Tar entry error: {e}")))?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = entry
+                .path()
+                .map_err(|e| CapsuleError::InvalidArchive(format!("Tar path error: {e}")))?
+                .to_string_lossy()
+                .to_string();
+            if !wanted(&name) {
+                continue;
+            }
+            let size = entry.size();
+            if !take_into_memory(
+                &mut entry,
+                name,
+                size,
+                max_total_bytes,
+                &mut total,
+                &mut out,
+            )? {
+                break;
+            }
+        }
+    } else {
+        return Err(CapsuleError::Unsupported(format!(
+            "In-memory extraction not supported for {kind} archives"
+        )));
+    }
+
+    Ok(out)
+}
+
+/// Show `path` selected in the OS file manager: Explorer, Finder, or on Linux the file
+/// manager's D-Bus interface, falling back to just opening the parent folder.
+#[tauri::command]
+pub async fn reveal_in_file_manager(path: String) -> Result<(), CapsuleError> {
+    let path = PathBuf::from(&path);
+    if path.symlink_metadata().is_err() {
+        return Err(CapsuleError::NotFound(format!(
+            "File not found: {}",
+            path.display()
+        )));
+    }
+    let revealed = tauri_plugin_opener::reveal_item_in_dir(&path);
+    // Without a session bus (or a file manager implementing it) selection isn't possible.
+    #[cfg(target_os = "linux")]
+    let revealed = revealed.or_else(|_| {
+        let parent = path.parent().unwrap_or(Path::new("/"));
+        tauri_plugin_opener::open_path(parent, None::<&str>)
+    });
+    revealed.map_err(|e| CapsuleError::Io(format!("Failed to reveal {}: {e}", path.display())))
+}
+
+/// Extensions Capsule offers to handle, mirroring `bundle.fileAssociations` in
+/// tauri.conf.json, with the MIME type each is registered under.
+const FILE_ASSOCIATIONS: &[(&str, &str)] = &[
+    ("zip", "application/zip"),
+    ("tar", "application/x-tar"),
+    ("gz", "application/gzip"),
+    ("tgz", "application/x-compressed-tar"),
+    ("bz2", "application/x-bzip2"),
+    ("7z", "application/x-7z-compressed"),
+    ("rar", "application/vnd.rar"),
+];
+
+/// Who currently opens one archive extension by default.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileAssociationStatus {
+    pub extension: String,
+    pub mime_type: String,
+    pub handler: Option<String>,
+    pub is_capsule: bool,
+}
+
+/// Outcome of registering one extension; failures (e.g. permissions) don't stop the rest.
+#[derive(Debug, Serialize)]
+pub struct FileAssociationResult {
+    pub extension: String,
+    pub registered: bool,
+    pub error: Option<String>,
+}
+
+/// Helper: run an external tool, turning a non-zero exit into its stderr.
+#[cfg(any(windows, target_os = "linux"))]
+fn run_tool(program: &str, args: &[&str]) -> Result<String, CapsuleError> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| CapsuleError::io(format!("Failed to run {program}"), e))?;
+    if !output.status.success() {
+        return Err(CapsuleError::Other(format!(
+            "{program} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// ProgID the Windows associations point at.
+#[cfg(windows)]
+const CAPSULE_PROG_ID: &str = "Capsule.Archive";
+
+/// Helper: per-user registration under HKCU\Software\Classes (no admin rights needed).
+#[cfg(windows)]
+fn register_association(ext: &str, _mime: &str, exe: &Path) -> Result<(), CapsuleError> {
+    let command_key = format!(r"HKCU\Software\Classes\{CAPSULE_PROG_ID}\shell\open\command");
+    let command = format!("\"{}\" \"%1\"", exe.display());
+    run_tool("reg", &["add", &command_key, "/ve", "/d", &command, "/f"])?;
+    let ext_key = format!(r"HKCU\Software\Classes\.{ext}");
+    run_tool(
+        "reg",
+        &["add", &ext_key, "/ve", "/d", CAPSULE_PROG_ID, "/f"],
+    )?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn current_association(ext: &str, _mime: &str) -> Result<(Option<String>, bool), CapsuleError> {
+    let ext_key = format!(r"HKCU\Software\Classes\.{ext}");
+    let handler = run_tool("reg", &["query", &ext_key, "/ve"])
+        .ok()
+        .and_then(|out| out.split_whitespace().last().map(String::from));
+    let is_capsule = handler.as_deref() == Some(CAPSULE_PROG_ID);
+    Ok((handler, is_capsule))
+}
+
+/// Desktop entry xdg-mime associations point at.
+#[cfg(target_os = "linux")]
+const CAPSULE_DESKTOP_FILE: &str = "capsule.desktop";
+
+/// Helper: install a user-level desktop entry (if missing) and make it the default for `mime`.
+#[cfg(target_os = "linux")]
+fn register_association(_ext: &str, mime: &str, exe: &Path) -> Result<(), CapsuleError> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")))
+        .ok_or_else(|| CapsuleError::Other("Cannot locate the user data directory".into()))?;
+    let apps = data_home.join("applications");
+    let desktop = apps.join(CAPSULE_DESKTOP_FILE);
+    if !desktop.exists() {
+        let mimes: Vec<&str> = FILE_ASSOCIATIONS.iter().map(|(_, m)| *m).collect();
+        fs::create_dir_all(&apps)
+            .map_err(|e| CapsuleError::io(format!("Failed to create {}", apps.display()), e))?;
+        let entry = format!(
+            "[Desktop Entry]\nType=Application\nName=Capsule\nExec=\"{}\" %f\nMimeType={};\nTerminal=false\n",
+            exe.display(),
+            mimes.join(";")
+        );
+        fs::write(&desktop, entry)
+            .map_err(|e| CapsuleError::io(format!("Failed to write {}", desktop.display()), e))?;
+    }
+    run_tool("xdg-mime", &["default", CAPSULE_DESKTOP_FILE, mime])?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn current_association(_ext: &str, mime: &str) -> Result<(Option<String>, bool), CapsuleError> {
+    let handler = run_tool("xdg-mime", &["query", "default", mime])?;
+    let is_capsule = handler == CAPSULE_DESKTOP_FILE;
+    Ok(((!handler.is_empty()).then_some(handler), is_capsule))
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn register_association(_ext: &str, _mime: &str, _exe: &Path) -> Result<(), CapsuleError> {
+    Err(CapsuleError::Other(
+        "Default apps on this platform come from the app bundle; use Get Info > Open with".into(),
+    ))
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn current_association(_ext: &str, _mime: &str) -> Result<(Option<String>, bool), CapsuleError> {
+    Err(CapsuleError::Unsupported(
+        "Checking default apps is not supported on this platform".into(),
+    ))
+}
+
+/// Make Capsule the default app for the archive types it handles, for the current user
+/// (registry on Windows, desktop entry + `xdg-mime` on Linux). Each extension is attempted
+/// independently and reports its own error.
+#[tauri::command]
+pub async fn register_file_associations() -> Result<Vec<FileAssociationResult>, CapsuleError> {
+    let exe = std::env::current_exe()
+        .map_err(|e| CapsuleError::io("Cannot locate Capsule executable", e))?;
+    Ok(FILE_ASSOCIATIONS
+        .iter()
+        .map(|(ext, mime)| {
+            let result = register_association(ext, mime, &exe);
+            FileAssociationResult {
+                extension: ext.to_string(),
+                registered: result.is_ok(),
+                error: result.err().map(|e| e.to_string()),
+            }
+        })
+        .collect())
+}
+
+/// Report the current default handler for each archive type Capsule handles.
+#[tauri::command]
+pub async fn check_file_associations() -> Result<Vec<FileAssociationStatus>, CapsuleError> {
+    FILE_ASSOCIATIONS
+        .iter()
+        .map(|(ext, mime)| {
+            let (handler, is_capsule) = current_association(ext, mime)?;
+            Ok(FileAssociationStatus {
+                extension: ext.to_string(),
+                mime_type: mime.to_string(),
+                handler,
+                is_capsule,
+            })
+        })
+        .collect()
+}
+
+/// An archive being built incrementally through `begin_zip` / `zip_add_bytes`.
+struct ZipSession {
+    writer: ZipWriter<File>,
+    output: PathBuf,
+    /// Entry currently receiving chunks; cleared when a chunk is flagged `is_last`.
+    current_entry: Option<String>,
+    written_names: HashSet<String>,
+}
+
+impl ZipSession {
+    /// Write `data` to `entry_path`, starting that entry if it isn't the current one.
+    fn add_bytes(
+        &mut self,
+        entry_path: &str,
+        data: &[u8],
+        is_last: bool,
+    ) -> Result<(), CapsuleError> {
+        let name = validate_entry_name(entry_path)?;
+        if self.current_entry.as_deref() != Some(name.as_str()) {
+            if !self.written_names.insert(name.clone()) {
+                return Err(CapsuleError::Other(format!(
+                    "Entry already written in this session: {name}"
+                )));
+            }
+            self.writer
+                .start_file(
+                    &name,
+                    FileOptions::default()
+                        .compression_method(CompressionMethod::Deflated)
+                        .unix_permissions(0o644),
+                )
+                .map_err(|e| CapsuleError::zip("Zip start file error", e))?;
+            self.current_entry = Some(name);
+        }
+
+        self.writer
+            .write_all(data)
+            .map_err(|e| CapsuleError::io("Zip write error", e))?;
+        if is_last {
+            self.current_entry = None;
+        }
+        Ok(())
+    }
+}
+
+/// Managed state holding open zip sessions keyed by the frontend's session id.
+#[derive(Default)]
+pub struct ZipSessions(Mutex<HashMap<String, ZipSession>>);
+
+impl ZipSessions {
+    /// Drop every open session and delete its partial output (used on app exit).
+    pub fn abort_all(&self) {
+        let sessions: Vec<ZipSession> = match self.0.lock() {
+            Ok(mut map) => map.drain().map(|(_, session)| session).collect(),
+            Err(_) => return,
+        };
+        for session in sessions {
+            let output = session.output.clone();
+            drop(session);
+            let _ = fs::remove_file(output);
+        }
+    }
+
+    /// `begin_zip` without the Tauri state wrapper.
+    fn begin(&self, session_id: String, output: PathBuf) -> Result<(), CapsuleError> {
+        let mut map = self
+            .0
+            .lock()
+            .map_err(|_| CapsuleError::Other("Zip session state poisoned".into()))?;
+        if map.contains_key(&session_id) {
+            return Err(CapsuleError::Other(format!(
+                "Zip session already exists: {session_id}"
+            )));
+        }
+
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| CapsuleError::io("Failed to create output dir", e))?;
+        }
+        let file = File::create(&output)
+            .map_err(|e| CapsuleError::io("Failed to create archive file", e))?;
+
+        map.insert(
+            session_id,
+            ZipSession {
+                writer: ZipWriter::new(file),
+                output,
+                current_entry: None,
+                written_names: Default::default(),
+            },
+        );
+        Ok(())
+    }
+
+    /// `zip_add_bytes` without the Tauri state wrapper, taking the decoded chunk.
+    fn add_bytes(
+        &self,
+        session_id: &str,
+        entry_path: &str,
+        data: &[u8],
+        is_last: bool,
+    ) -> Result<(), CapsuleError> {
+        let mut map = self
+            .0
+            .lock()
+            .map_err(|_| CapsuleError::Other("Zip session state poisoned".into()))?;
+        let session = map
+            .get_mut(session_id)
+            .ok_or_else(|| CapsuleError::Other(format!("Unknown zip session: {session_id}")))?;
+        session.add_bytes(entry_path, data, is_last)
+    }
+
+    /// `finish_zip` without the Tauri state wrapper. A session that fails to finalize is
+    /// dropped and its partial output deleted.
+    fn finish(&self, session_id: &str) -> Result<String, CapsuleError> {
+        let mut session = self
+            .0
+            .lock()
+            .map_err(|_| CapsuleError::Other("Zip session state poisoned".into()))?
+            .remove(session_id)
+            .ok_or_else(|| CapsuleError::Other(format!("Unknown zip session: {session_id}")))?;
+
+        if let Err(e) = session.writer.finish() {
+            let output = session.output.clone();
+            drop(session);
+            let _ = fs::remove_file(output);
+            return Err(CapsuleError::zip("Failed to finalize zip", e));
+        }
+        Ok(session.output.to_string_lossy().to_string())
+    }
+
+    /// `abort_zip` without the Tauri state wrapper.
+    fn abort(&self, session_id: &str) -> Result<(), CapsuleError> {
+        let session = self
+            .0
+            .lock()
+            .map_err(|_| CapsuleError::Other("Zip session state poisoned".into()))?
+            .remove(session_id)
+            .ok_or_else(|| CapsuleError::Other(format!("Unknown zip session: {session_id}")))?;
+
+        let output = session.output.clone();
+        drop(session);
+        fs::remove_file(&output)
+            .map_err(|e| CapsuleError::io("Failed to remove partial zip", e))?;
+        Ok(())
+    }
+}
+
+/// Start a new zip session writing to `output_path`.
+#[tauri::command]
+pub async fn begin_zip(
+    sessions: State<'_, ZipSessions>,
+    session_id: String,
+    output_path: String,
+) -> Result<(), CapsuleError> {
+    sessions.begin(session_id, PathBuf::from(&output_path))
+}
+
+/// Append a base64-encoded chunk to `entry_path` in an open session.
+/// A chunk for a different entry starts that entry; `is_last` closes the current one.
+#[tauri::command]
+pub async fn zip_add_bytes(
+    sessions: State<'_, ZipSessions>,
+    session_id: String,
+    entry_path: String,
+    chunk_base64: String,
+    is_last: bool,
+) -> Result<(), CapsuleError> {
+    let data = BASE64
+        .decode(chunk_base64.as_bytes())
+        .map_err(|e| CapsuleError::Other(format!("Invalid base64 chunk: {e}")))?;
+    sessions.add_bytes(&session_id, &entry_path, &data, is_last)
+}
+
+/// Finalize a session's archive and return its path. If finalizing fails, the partial
+/// output is deleted.
+#[tauri::command]
+pub async fn finish_zip(
+    sessions: State<'_, ZipSessions>,
+    session_id: String,
+) -> Result<String, CapsuleError> {
+    sessions.finish(&session_id)
+}
+
+/// Abandon a session and delete its partial output.
+#[tauri::command]
+pub async fn abort_zip(
+    sessions: State<'_, ZipSessions>,
+    session_id: String,
+) -> Result<(), CapsuleError> {
+    sessions.abort(&session_id)
+}
+
+/// One entry for `create_zip_from_entries`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryZipEntry {
+    pub name: String,
+    pub content_base64: String,
+}
+
+/// Helper: write `entries` to a new zip at `output`. Names and contents are all checked
+/// before the file is created, so a bad entry leaves nothing behind.
+fn write_zip_from_entries(output: &Path, entries: &[MemoryZipEntry]) -> Result<(), CapsuleError> {
+    let mut names = HashSet::new();
+    let mut decoded = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let name = validate_entry_name(&entry.name)?;
+        if !names.insert(name.clone()) {
+            return Err(CapsuleError::Other(format!("Duplicate entry name: {name}")));
+        }
+        let data = BASE64
+            .decode(entry.content_base64.as_bytes())
+            .map_err(|e| CapsuleError::Other(format!("Invalid base64 for {name}: {e}")))?;
+        decoded.push((name, data));
+    }
+
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| CapsuleError::io("Failed to create output dir", e))?;
+    }
+    let file =
+        File::create(output).map_err(|e| CapsuleError::io("Failed to create archive file", e))?;
+    let mut writer = ZipWriter::new(file);
+    for (name, data) in decoded {
+        writer
+            .start_file(
+                &name,
+                FileOptions::default()
+                    .compression_method(CompressionMethod::Deflated)
+                    .unix_permissions(0o644),
+            )
+            .map_err(|e| CapsuleError::zip("Zip start file error", e))?;
+        writer
+            .write_all(&data)
+            .map_err(|e| CapsuleError::io("Zip write error", e))?;
+    }
+    writer
+        .finish()
+        .map_err(|e| CapsuleError::zip("Zip finalize error", e))?;
+    Ok(())
+}
+
+/// Create a zip at `output_path` from base64-encoded contents, without temp files.
+#[tauri::command]
+pub async fn create_zip_from_entries(
+    output_path: String,
+    entries: Vec<MemoryZipEntry>,
+) -> Result<(), CapsuleError> {
+    write_zip_from_entries(Path::new(&output_path), &entries)
+}
+
+/// An archive entry that is itself an archive, suggested for opening directly.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NestedArchiveSuggestion {
+    pub inner_entry: String,
+    pub inner_kind: String,
+}
+
+/// Suggest opening the inner archive when an archive's only file is itself an archive
+/// (e.g. a `.zip` wrapping a single `.tar.gz`).
+#[tauri::command]
+pub async fn suggest_nested_open(
+    path: String,
+) -> Result<Option<NestedArchiveSuggestion>, CapsuleError> {
+    let entries = list_archive(&PathBuf::from(&path))?;
+    let mut files = entries
+        .iter()
+        .filter(|e| e.kind != "dir" && !e.path.ends_with('/'));
+
+    let (Some(only), None) = (files.next(), files.next()) else {
+        return Ok(None);
+    };
+
+    let inner_kind = detect_archive_type(Path::new(&only.path));
+    if inner_kind == "unknown" {
+        return Ok(None);
+    }
+
+    Ok(Some(NestedArchiveSuggestion {
+        inner_entry: only.path.clone(),
+        inner_kind: inner_kind.to_string(),
+    }))
+}
+
+/// Throughput stats from `benchmark_read`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadBenchmark {
+    pub entries: usize,
+    pub elapsed_ms: u64,
+    pub bytes_read: u64,
+    pub mb_per_sec: f64,
+}
+
+/// Diagnostics: time a full decompressing read of every entry, without writing anything.
+/// Run on demand only; this is much slower than `open_archive`.
+#[tauri::command]
+pub async fn benchmark_read(path: String) -> Result<ReadBenchmark, CapsuleError> {
+    let path_buf = PathBuf::from(&path);
+    let kind = detect_archive_type(&path_buf);
+    let started = std::time::Instant::now();
+    let mut entries = 0;
+    let mut bytes_read = 0;
+
+    if kind == "zip" {
+        let file =
+            File::open(&path_buf).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+        let mut archive =
+            ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+        for i in 0..archive.len() {
+            let mut entry = archive
+                .by_index(i)
+                .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+            bytes_read += io::copy(&mut entry, &mut io::sink())
+                .map_err(|e| CapsuleError::io("Zip read error", e))?;
+            entries += 1;
+        }
+    } else {
+        let mut archive = open_tar_reader(&path_buf, kind)?;
+        let tar_entries = archive
+            .entries()
+            .map_err(|e| CapsuleError::InvalidArchive(format!("Failed to read tar entries: {e}")))?;
+        for entry_res in tar_entries {
+            let mut entry = entry_res
+                .map_err(|e| CapsuleError::InvalidArchive(format!("Tar entry error: {e}")))?;
+            bytes_read += io::copy(&mut entry, &mut io::sink())
+                .map_err(|e| CapsuleError::InvalidArchive(format!("Tar read error: {e}")))?;
+            entries += 1;
+        }
+    }
+
+    let elapsed = started.elapsed();
+    let secs = elapsed.as_secs_f64();
+    let mb_per_sec = if secs > 0.0 {
+        bytes_read as f64 / (1024.0 * 1024.0) / secs
+    } else {
+        0.0
+    };
+
+    Ok(ReadBenchmark {
+        entries,
+        elapsed_ms: elapsed.as_millis() as u64,
+        bytes_read,
+        mb_per_sec,
+    })
+}
+
+/// Outcome of `test_archive`; `ok` is false if any entry failed to read back.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveTestReport {
+    pub ok: bool,
+    pub tested: usize,
+    pub failed: Vec<FailedEntry>,
+}
+
+/// Integrity check like `unzip -t`: read every entry to the end without writing anything,
+/// so zip CRCs and compressed streams are verified. A broken zip entry is reported and the
+/// rest are still tested; a broken tar stream ends the test since later headers are lost.
+#[tauri::command]
+pub async fn test_archive(path: String) -> Result<ArchiveTestReport, CapsuleError> {
+    let path_buf = PathBuf::from(&path);
+    let kind = archive_type(&path_buf);
+    let mut tested = 0;
+    let mut failed = Vec::new();
+
+    if kind == "zip" {
+        let file =
+            File::open(&path_buf).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+        let mut archive =
+            ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+        for i in 0..archive.len() {
+            let name = archive
+                .by_index_raw(i)
+                .map_err(|e| CapsuleError::zip("Zip entry error", e))?
+                .name()
+                .to_string();
+            let result = archive
+                .by_index(i)
+                .map_err(|e| CapsuleError::zip("Zip entry error", e))
+                .and_then(|mut entry| {
+                    io::copy(&mut entry, &mut io::sink())
+                        .map_err(|e| CapsuleError::io("Zip read error", e))
+                });
+            if let Err(error) = result {
+                failed.push(FailedEntry {
+                    name,
+                    error: error.to_string(),
+                });
+            }
+            tested += 1;
+        }
+    } else if kind.starts_with("tar") {
+        let mut archive = open_tar_reader(&path_buf, kind)?;
+        let tar_entries = archive
+            .entries()
+            .map_err(|e| CapsuleError::InvalidArchive(format!("Failed to read tar entries: {e}")))?;
+        for entry_res in tar_entries {
+            let mut entry = match entry_res {
+                Ok(entry) => entry,
+                Err(e) => {
+                    failed.push(FailedEntry {
+                        name: format!("entry #{}", tested + 1),
+                        error: format!("Tar entry error: {e}"),
+                    });
+                    break;
+                }
+            };
+            let name = entry.path_bytes().into_owned();
+            tested += 1;
+            if let Err(e) = io::copy(&mut entry, &mut io::sink()) {
+                failed.push(FailedEntry {
+                    name: String::from_utf8_lossy(&name).into_owned(),
+                    error: format!("Tar read error: {e}"),
+                });
+                break;
+            }
+        }
+    } else {
+        return Err(CapsuleError::Unsupported(format!(
+            "Integrity test not supported for {kind} archives"
+        )));
+    }
+
+    Ok(ArchiveTestReport {
+        ok: failed.is_empty(),
+        tested,
+        failed,
+    })
+}
+
+/// Byte range information for one zip entry, from the central directory.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryOffset {
+    pub name: String,
+    pub header_offset: u64,
+    pub compressed_size: u64,
+}
+
+/// List each zip entry's local header offset and compressed size, so external tools can
+/// range-request a single entry out of a remote archive.
+#[tauri::command]
+pub async fn entry_offsets(path: String) -> Result<Vec<EntryOffset>, CapsuleError> {
+    let file = File::open(&path).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+
+    let mut offsets = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let entry = archive
+            .by_index_raw(i)
+            .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+        offsets.push(EntryOffset {
+            name: zip_entry_name(&entry),
+            header_offset: entry.header_start(),
+            compressed_size: entry.compressed_size(),
+        });
+    }
+
+    Ok(offsets)
+}
+
+/// What a base archive recorded about one file, for change detection.
+struct EntryFingerprint {
+    size: u64,
+    /// ZIP only; tar entries are compared by mtime instead.
+    crc32: Option<u32>,
+    mtime: Option<i64>,
+}
+
+/// Helper: fingerprints of every file entry in an archive, keyed by path.
+fn archive_fingerprints(path: &Path) -> Result<HashMap<String, EntryFingerprint>, CapsuleError> {
+    let kind = detect_archive_type(path);
+    let mut prints = HashMap::new();
+
+    if kind == "zip" {
+        let file = File::open(path).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+        let mut archive =
+            ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+        for i in 0..archive.len() {
+            let entry = archive
+                .by_index_raw(i)
+                .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+            if entry.is_dir() {
+                continue;
+            }
+            prints.insert(
+                zip_entry_name(&entry).trim_start_matches("./").to_string(),
+                EntryFingerprint {
+                    size: entry.size(),
+                    crc32: Some(entry.crc32()),
+                    mtime: Some(zip_mtime_secs(entry.last_modified())),
+                },
+            );
+        }
+    } else {
+        let mut archive = open_tar_reader(path, kind)?;
+        let entries = archive
+            .entries()
+            .map_err(|e| CapsuleError::InvalidArchive(format!("Failed to read tar entries: {e}")))?;
+        for entry_res in entries {
+            let entry = entry_res
+                .map_err(|e| CapsuleError::InvalidArchive(format!("Tar entry error: {e}")))?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = entry
+                .path()
+                .map_err(|e| CapsuleError::InvalidArchive(format!("Tar path error: {e}")))?
+                .to_string_lossy()
+                .trim_start_matches("./")
+                .to_string();
+            prints.insert(
+                name,
+                EntryFingerprint {
+                    size: entry.size(),
+                    crc32: None,
+                    mtime: entry.header().mtime().ok().map(|t| t as i64),
+                },
+            );
+        }
+    }
+
+    Ok(prints)
+}
+
+/// Helper: every file under `dir`, as (`/`-separated relative path, full path), sorted.
+fn collect_dir_files(dir: &Path) -> Result<Vec<(String, PathBuf)>, CapsuleError> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current).map_err(|e| CapsuleError::io("Read dir error", e))? {
+            let path = entry
+                .map_err(|e| CapsuleError::io("Dir entry error", e))?
+                .path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                let rel = path
+                    .strip_prefix(dir)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                files.push((rel, path));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Helper: whether a file on disk differs from its fingerprint in the base archive.
+fn file_differs(path: &Path, print: &EntryFingerprint) -> Result<bool, CapsuleError> {
+    let meta = fs::metadata(path)
+        .map_err(|e| CapsuleError::open(format!("Failed to stat {}", path.display()), e))?;
+    if meta.len() != print.size {
+        return Ok(true);
+    }
+    if let Some(crc) = print.crc32 {
+        let actual = file_crc32(path)
+            .map_err(|e| CapsuleError::io(format!("Failed to read {}", path.display()), e))?;
+        return Ok(actual != crc);
+    }
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64);
+    Ok(mtime != print.mtime)
+}
+
+/// Name of the manifest listing files removed since the base archive, one path per line.
+pub(crate) const DELETIONS_MANIFEST: &str = ".capsule-deleted";
+
+/// Counts from `create_diff_archive`.
+#[derive(Debug, Default, Serialize)]
+pub struct DiffArchiveReport {
+    pub added: usize,
+    pub changed: usize,
+    pub deleted: usize,
+    pub unchanged: usize,
+}
+
+/// Incremental backup: zip only the files in `source_dir` that are new or changed relative
+/// to `base_archive` (size + CRC for zip bases, size + mtime for tar), plus a
+/// `DELETIONS_MANIFEST` entry listing base files that no longer exist.
+#[tauri::command]
+pub async fn create_diff_archive(
+    base_archive: String,
+    source_dir: String,
+    output_path: String,
+) -> Result<DiffArchiveReport, CapsuleError> {
+    let source = PathBuf::from(&source_dir);
+    let output = PathBuf::from(&output_path);
+    if !source.is_dir() {
+        return Err(CapsuleError::Other(format!(
+            "Not a directory: {source_dir}"
+        )));
+    }
+
+    let mut base = archive_fingerprints(Path::new(&base_archive))?;
+    let mut report = DiffArchiveReport::default();
+    let mut to_write = Vec::new();
+
+    for (rel, path) in collect_dir_files(&source)? {
+        if path == output {
+            continue;
+        }
+        match base.remove(&rel) {
+            None => {
+                report.added += 1;
+                to_write.push(path);
+            }
+            Some(print) if file_differs(&path, &print)? => {
+                report.changed += 1;
+                to_write.push(path);
+            }
+            Some(_) => report.unchanged += 1,
+        }
+    }
+
+    let mut deleted: Vec<String> = base.into_keys().collect();
+    deleted.sort();
+    report.deleted = deleted.len();
+
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| CapsuleError::io("Failed to create output dir", e))?;
+    }
+    let file =
+        File::create(&output).map_err(|e| CapsuleError::io("Failed to create archive file", e))?;
+    let mut writer = ZipWriter::new(file);
+    let mut dirs = HashSet::new();
+    for path in &to_write {
+        add_path_to_zip(
+            &mut writer,
+            path,
+            &source,
+            &mut dirs,
+            ZipCompression::default(),
+            &[],
+            &mut CreateProgressTracker::default(),
+        )?;
+    }
+    writer.start_entry(DELETIONS_MANIFEST, ZipCompression::default())?;
+    writer
+        .write_all(deleted.join("\n").as_bytes())
+        .map_err(|e| CapsuleError::io("Failed to write deletions manifest", e))?;
+    writer
+        .finish()
+        .map_err(|e| CapsuleError::zip("Failed to finalize zip", e))?;
+
+    Ok(report)
+}
+
+/// Bumped whenever the manifest layout changes.
+const MANIFEST_VERSION: u32 = 1;
+
+/// One file in an exported manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    pub name: String,
+    pub size: u64,
+    pub crc32: u32,
+}
+
+/// JSON manifest of an archive's files, written by `export_manifest`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveManifest {
+    pub version: u32,
+    pub archive: String,
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// Entry-level differences between two archives (or their manifests), sorted by name.
+#[derive(Debug, Default, Serialize)]
+pub struct ArchiveDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+/// Helper: compare two file listings by name, then size and CRC.
+fn diff_entries(before: &[ManifestEntry], after: &[ManifestEntry]) -> ArchiveDiff {
+    let old: HashMap<&str, &ManifestEntry> = before.iter().map(|e| (e.name.as_str(), e)).collect();
+    let new: HashMap<&str, &ManifestEntry> = after.iter().map(|e| (e.name.as_str(), e)).collect();
+    let mut diff = ArchiveDiff::default();
+
+    for (name, entry) in &new {
+        match old.get(name) {
+            None => diff.added.push(name.to_string()),
+            Some(prev) if prev.size != entry.size || prev.crc32 != entry.crc32 => {
+                diff.changed.push(name.to_string())
+            }
+            Some(_) => {}
+        }
+    }
+    diff.removed = old
+        .keys()
+        .filter(|name| !new.contains_key(*name))
+        .map(|name| name.to_string())
+        .collect();
+
+    diff.added.sort();
+    diff.removed.sort();
+    diff.changed.sort();
+    diff
+}
+
+/// Helper: name, size and CRC-32 of every file entry. Tar has no stored checksums, so its
+/// entries are read and hashed.
+fn manifest_entries(path: &Path) -> Result<Vec<ManifestEntry>, CapsuleError> {
+    let kind = detect_archive_type(path);
+    let mut entries = Vec::new();
+
+    if kind == "zip" {
+        let file = File::open(path).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+        let mut archive =
+            ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+        for i in 0..archive.len() {
+            let entry = archive
+                .by_index_raw(i)
+                .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+            if !entry.is_dir() {
+                entries.push(ManifestEntry {
+                    name: zip_entry_name(&entry),
+                    size: entry.size(),
+                    crc32: entry.crc32(),
+                });
+            }
+        }
+    } else {
+        let mut archive = open_tar_reader(path, kind)?;
+        let tar_entries = archive
+            .entries()
+            .map_err(|e| CapsuleError::InvalidArchive(format!("Failed to read tar entries: {e}")))?;
+        for entry_res in tar_entries {
+            let mut entry = entry_res
+                .map_err(|e| CapsuleError::InvalidArchive(format!("Tar entry error: {e}")))?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let name = entry
+                .path()
+                .map_err(|e| CapsuleError::InvalidArchive(format!("Tar path error: {e}")))?
+                .to_string_lossy()
+                .to_string();
+            let mut hasher = crc32fast::Hasher::new();
+            let mut buf = [0u8; 64 * 1024];
+            let mut size = 0;
+            loop {
+                let n = entry
+                    .read(&mut buf)
+                    .map_err(|e| CapsuleError::InvalidArchive(format!("Tar read error: {e}")))?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&buf[..n]);
+                size += n as u64;
+            }
+            entries.push(ManifestEntry {
+                name,
+                size,
+                crc32: hasher.finalize(),
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Write a JSON manifest (name, size, CRC-32 per file) of an archive, for offline comparison
+/// with `diff_manifests`. Returns the number of entries written.
+#[tauri::command]
+pub async fn export_manifest(path: String, output_path: String) -> Result<usize, CapsuleError> {
+    let path_buf = PathBuf::from(&path);
+    let manifest = ArchiveManifest {
+        version: MANIFEST_VERSION,
+        archive: path_buf
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        entries: manifest_entries(&path_buf)?,
+    };
+    let json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| CapsuleError::Other(format!("Failed to encode manifest: {e}")))?;
+    fs::write(&output_path, json).map_err(|e| CapsuleError::io("Failed to write manifest", e))?;
+    Ok(manifest.entries.len())
+}
+
+/// Helper: read and check an exported manifest.
+fn read_manifest(path: &Path) -> Result<ArchiveManifest, CapsuleError> {
+    let json = fs::read(path)
+        .map_err(|e| CapsuleError::io(format!("Failed to read manifest {}", path.display()), e))?;
+    let manifest: ArchiveManifest = serde_json::from_slice(&json).map_err(|e| {
+        CapsuleError::InvalidArchive(format!("{} is not a Capsule manifest: {e}", path.display()))
+    })?;
+    if manifest.version != MANIFEST_VERSION {
+        return Err(CapsuleError::Other(format!(
+            "{} has manifest version {}, expected {MANIFEST_VERSION}",
+            path.display(),
+            manifest.version
+        )));
+    }
+    Ok(manifest)
+}
+
+/// Compare two exported manifests: entries added in, removed from, or changed (size or
+/// CRC) in `manifest_b` relative to `manifest_a`.
+#[tauri::command]
+pub async fn diff_manifests(
+    manifest_a: String,
+    manifest_b: String,
+) -> Result<ArchiveDiff, CapsuleError> {
+    let a = read_manifest(Path::new(&manifest_a))?;
+    let b = read_manifest(Path::new(&manifest_b))?;
+    Ok(diff_entries(&a.entries, &b.entries))
+}
+
+/// Compare two archives directly: `removed` is only in `path_a`, `added` only in `path_b`,
+/// and `changed` in both with a different size or CRC-32. Nothing is extracted to disk.
+#[tauri::command]
+pub async fn diff_archives(path_a: String, path_b: String) -> Result<ArchiveDiff, CapsuleError> {
+    // Tarballs made with `tar -C dir .` store names as "./name"; compare without it.
+    let entries = |path: &str| -> Result<Vec<ManifestEntry>, CapsuleError> {
+        let mut entries = manifest_entries(Path::new(path))?;
+        for entry in &mut entries {
+            entry.name = entry.name.trim_start_matches("./").to_string();
+        }
+        Ok(entries)
+    };
+    Ok(diff_entries(&entries(&path_a)?, &entries(&path_b)?))
+}
+
+/// A non-empty per-entry zip comment.
+#[derive(Debug, Serialize)]
+pub struct EntryComment {
+    pub name: String,
+    pub comment: String,
+}
+
+/// Archive and entry comments collected by `dump_comments`.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommentReport {
+    pub archive_comment: Option<String>,
+    pub entry_comments: Vec<EntryComment>,
+}
+
+/// Collect the zip archive comment and every non-empty entry comment. Other formats
+/// have no comments and give an empty report.
+#[tauri::command]
+pub async fn dump_comments(path: String) -> Result<CommentReport, CapsuleError> {
+    let path_buf = PathBuf::from(&path);
+    let mut report = CommentReport::default();
+    if detect_archive_type(&path_buf) != "zip" {
+        return Ok(report);
+    }
+
+    let file = File::open(&path_buf).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+
+    let comment = String::from_utf8_lossy(archive.comment());
+    if !comment.trim().is_empty() {
+        report.archive_comment = Some(comment.into_owned());
+    }
+
+    for i in 0..archive.len() {
+        let entry = archive
+            .by_index_raw(i)
+            .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+        if !entry.comment().trim().is_empty() {
+            report.entry_comments.push(EntryComment {
+                name: zip_entry_name(&entry),
+                comment: entry.comment().to_string(),
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+/// The archive-level comment of a zip, empty when it has none.
+#[tauri::command]
+pub async fn get_zip_comment(path: String) -> Result<String, CapsuleError> {
+    let file = File::open(&path).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+    let archive = ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+    Ok(String::from_utf8_lossy(archive.comment()).into_owned())
+}
+
+/// Replace the archive-level comment of a zip. Entries are copied raw, so nothing is
+/// recompressed; zips with encrypted entries are refused since the copy would lose the flag.
+#[tauri::command]
+pub async fn set_zip_comment(path: String, comment: String) -> Result<(), CapsuleError> {
+    if comment.len() > u16::MAX as usize {
+        return Err(CapsuleError::Other(
+            "Zip comments are limited to 65535 bytes".into(),
+        ));
+    }
+    let zip_path = PathBuf::from(&path);
+    let file = File::open(&zip_path).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+    reject_encrypted_entries(&zip_path, &mut archive)?;
+
+    let temp = TempBeside::new(&zip_path);
+    let mut writer = ZipWriter::new(temp.create()?);
+    writer.set_comment(comment);
+    for i in 0..archive.len() {
+        let entry = archive
+            .by_index_raw(i)
+            .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+        raw_copy_zip_entry(&mut writer, entry)
+            .map_err(|e| CapsuleError::zip("Temp zip copy error", e))?;
+    }
+
+    writer
+        .finish()
+        .map_err(|e| CapsuleError::zip("Failed to finalize temp zip", e))?;
+    temp.persist(&zip_path)
+        .map_err(|e| CapsuleError::io("Failed to replace original zip", e))?;
+    Ok(())
+}
+
+/// Zip timestamps are local time of unknown zone; allow the widest UTC offset (+14h) before
+/// calling one later than the archive file.
+const ZIP_TIMEZONE_SLACK_SECS: i64 = 14 * 3600;
+
+/// Forensics: entries whose stored modification time is later than the archive file's own,
+/// which points at tampering or clock skew.
+#[tauri::command]
+pub async fn entries_modified_after_archive(path: String) -> Result<Vec<String>, CapsuleError> {
+    let path_buf = PathBuf::from(&path);
+    let kind = detect_archive_type(&path_buf);
+    let archive_mtime = fs::metadata(&path_buf)
+        .and_then(|m| m.modified())
+        .map_err(|e| CapsuleError::io("Failed to read archive modification time", e))?;
+    let archive_secs = match archive_mtime.duration_since(std::time::UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+
+    let mut newer = Vec::new();
+    if kind == "zip" {
+        let file =
+            File::open(&path_buf).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+        let mut archive =
+            ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+        for i in 0..archive.len() {
+            let entry = archive
+                .by_index_raw(i)
+                .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+            if zip_mtime_secs(entry.last_modified()) > archive_secs + ZIP_TIMEZONE_SLACK_SECS {
+                newer.push(zip_entry_name(&entry));
+            }
+        }
+    } else {
+        let mut archive = open_tar_reader(&path_buf, kind)?;
+        let entries = archive
+            .entries()
+            .map_err(|e| CapsuleError::InvalidArchive(format!("Failed to read tar entries: {e}")))?;
+        for entry_res in entries {
+            let entry = entry_res
+                .map_err(|e| CapsuleError::InvalidArchive(format!("Tar entry error: {e}")))?;
+            let mtime = entry
+                .header()
+                .mtime()
+                .map_err(|e| CapsuleError::InvalidArchive(format!("Tar mtime error: {e}")))?;
+            if mtime as i64 > archive_secs {
+                let name = entry
+                    .path()
+                    .map_err(|e| CapsuleError::InvalidArchive(format!("Tar path error: {e}")))?;
+                newer.push(name.to_string_lossy().to_string());
+            }
+        }
+    }
+
+    Ok(newer)
+}
+
+/// Summary of what `normalize_zip` changed while rewriting an archive.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NormalizeReport {
+    pub entries: usize,
+    pub data_descriptors_removed: usize,
+    pub utf8_flags_set: usize,
+    pub reordered: bool,
+}
+
+/// Helper: read the general purpose bit flags from an entry's local file header.
+fn read_local_header_flags(file: &mut File, header_start: u64) -> Result<u16, CapsuleError> {
+    let mut header = [0u8; 8];
+    file.seek(SeekFrom::Start(header_start))
+        .map_err(|e| CapsuleError::io("Failed to seek to local header", e))?;
+    file.read_exact(&mut header)
+        .map_err(|e| CapsuleError::io("Failed to read local header", e))?;
+
+    if header[..4] != [0x50, 0x4b, 0x03, 0x04] {
+        return Err(CapsuleError::Other(format!(
+            "Invalid local header signature at offset {header_start}"
+        )));
+    }
+
+    Ok(u16::from_le_bytes([header[6], header[7]]))
+}
+
+/// Rewrite a ZIP so strict readers accept it: every local header carries its
+/// sizes and CRC (no data descriptors), entries are sorted by name, and
+/// non-ASCII names get the UTF-8 flag.
+#[tauri::command]
+pub async fn normalize_zip(
+    src_path: String,
+    dest_path: String,
+) -> Result<NormalizeReport, CapsuleError> {
+    let src = PathBuf::from(&src_path);
+    let dest = PathBuf::from(&dest_path);
+
+    let file = File::open(&src).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+    let mut raw = File::open(&src).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+
+    let mut report = NormalizeReport {
+        entries: archive.len(),
+        data_descriptors_removed: 0,
+        utf8_flags_set: 0,
+        reordered: false,
+    };
+
+    // 1. Inspect local headers and verify every entry's CRC before copying.
+    let mut order = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let (name, header_start) = {
+            let entry = archive
+                .by_index_raw(i)
+                .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+            (zip_entry_name(&entry), entry.header_start())
+        };
+        let flags = read_local_header_flags(&mut raw, header_start)?;
+
+        if flags & 0x0001 != 0 {
+            return Err(CapsuleError::Other(format!(
+                "Cannot normalize encrypted entry: {name}"
+            )));
+        }
+        if flags & 0x0008 != 0 {
+            report.data_descriptors_removed += 1;
+        }
+        if !name.is_ascii() && flags & 0x0800 == 0 {
+            report.utf8_flags_set += 1;
+        }
+
+        // Reading the decoded stream to the end makes the zip crate check the CRC.
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+        io::copy(&mut entry, &mut io::sink()).map_err(|e| {
+            CapsuleError::InvalidArchive(format!("CRC check failed for {name}: {e}"))
+        })?;
+
+        order.push((name, i));
+    }
+
+    // 2. Canonical order: byte-wise by name, so directories precede their contents.
+    let original: Vec<usize> = order.iter().map(|(_, i)| *i).collect();
+    order.sort_by(|a, b| a.0.cmp(&b.0));
+    report.reordered = order.iter().map(|(_, i)| *i).ne(original);
+
+    // 3. Copy the compressed data untouched; the writer emits fresh headers.
+    let temp = TempBeside::new(&dest);
+    let mut writer = ZipWriter::new(temp.create()?);
+
+    for (name, i) in &order {
+        let entry = archive
+            .by_index_raw(*i)
+            .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+        raw_copy_zip_entry(&mut writer, entry)
+            .map_err(|e| CapsuleError::zip(format!("Failed to copy {name}"), e))?;
+    }
+
+    writer
+        .finish()
+        .map_err(|e| CapsuleError::zip("Failed to finalize temp zip", e))?;
+    temp.persist(&dest)
+        .map_err(|e| CapsuleError::io("Failed to write normalized zip", e))?;
+
+    Ok(report)
+}
+
+/// Helper: look up an encoding by WHATWG label, plus the Windows `cpNNN` code page names
+/// that legacy archivers report.
+fn lookup_encoding(label: &str) -> Result<&'static encoding_rs::Encoding, CapsuleError> {
+    let label = label.trim().to_lowercase();
+    let canonical = match label.as_str() {
+        "cp932" | "windows-31j" => "shift_jis",
+        "cp936" => "gbk",
+        "cp949" => "euc-kr",
+        "cp950" => "big5",
+        other => other,
+    };
+    encoding_rs::Encoding::for_label(canonical.as_bytes())
+        .ok_or_else(|| CapsuleError::Unsupported(format!("Unsupported encoding: {label}")))
+}
+
+/// An entry name as currently decoded and as decoded with the chosen encoding.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NameMapping {
+    pub before: String,
+    pub after: String,
+    /// The raw bytes weren't valid in the encoding; `after` contains replacement characters.
+    pub had_errors: bool,
+}
+
+/// Helper: re-decode every entry name from its raw bytes with `encoding`. Entries already
+/// flagged as UTF-8 keep their names.
+fn transcoded_names(
+    path: &Path,
+    encoding: &'static encoding_rs::Encoding,
+) -> Result<Vec<NameMapping>, CapsuleError> {
+    let file = File::open(path).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+    let mut raw = File::open(path).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+
+    let mut mappings = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let entry = archive
+            .by_index_raw(i)
+            .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+        let before = entry.name().to_string();
+        let flags = read_local_header_flags(&mut raw, entry.header_start())?;
+        let (after, had_errors) = if flags & 0x0800 != 0 {
+            (before.clone(), false)
+        } else {
+            let (decoded, had_errors) = encoding.decode_without_bom_handling(entry.name_raw());
+            (decoded.into_owned(), had_errors)
+        };
+        mappings.push(NameMapping {
+            before,
+            after,
+            had_errors,
+        });
+    }
+
+    Ok(mappings)
+}
+
+/// Show how entry names would read if decoded as `from_encoding`, without writing anything.
+#[tauri::command]
+pub async fn preview_transcoded_names(
+    src_path: String,
+    from_encoding: String,
+) -> Result<Vec<NameMapping>, CapsuleError> {
+    transcoded_names(Path::new(&src_path), lookup_encoding(&from_encoding)?)
+}
+
+/// Repair mojibake names: rewrite the archive with names decoded as `from_encoding` and
+/// stored as UTF-8 (the writer sets the UTF-8 flag), copying entry data untouched. Like
+/// `normalize_zip`, this refuses encrypted entries, whose flag the raw copy would drop.
+#[tauri::command]
+pub async fn transcode_names(
+    src_path: String,
+    dest_path: String,
+    from_encoding: String,
+) -> Result<Vec<NameMapping>, CapsuleError> {
+    let src = PathBuf::from(&src_path);
+    let dest = PathBuf::from(&dest_path);
+
+    let mappings = transcoded_names(&src, lookup_encoding(&from_encoding)?)?;
+    if let Some(bad) = mappings.iter().find(|m| m.had_errors) {
+        return Err(CapsuleError::Other(format!(
+            "Entry name {} is not valid {from_encoding}",
+            bad.before
+        )));
+    }
+
+    let file = File::open(&src).map_err(|e| CapsuleError::open("Failed to open zip", e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| CapsuleError::zip("Invalid zip archive", e))?;
+    reject_encrypted_entries(&src, &mut archive)?;
+    let temp = TempBeside::new(&dest);
+    let mut writer = ZipWriter::new(temp.create()?);
+
+    for (i, mapping) in mappings.iter().enumerate() {
+        let entry = archive
+            .by_index_raw(i)
+            .map_err(|e| CapsuleError::zip("Zip entry error", e))?;
+        writer
+            .raw_copy_file_rename(entry, mapping.after.as_str())
+            .map_err(|e| CapsuleError::zip(format!("Failed to copy {}", mapping.after), e))?;
+    }
+
+    writer
+        .finish()
+        .map_err(|e| CapsuleError::zip("Failed to finalize temp zip", e))?;
+    temp.persist(&dest)
+        .map_err(|e| CapsuleError::io("Failed to write transcoded zip", e))?;
+
+    Ok(mappings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_archive_type() {
+        assert_eq!(detect_archive_type(&PathBuf::from("test.zip")), "zip");
+        assert_eq!(detect_archive_type(&PathBuf::from("test.tar")), "tar");
+        assert_eq!(detect_archive_type(&PathBuf::from("test.tar.gz")), "tar.gz");
+        assert_eq!(detect_archive_type(&PathBuf::from("test.tgz")), "tar.gz");
+        assert_eq!(detect_archive_type(&PathBuf::from("test.tar.bz2")), "tar.bz2");
+        assert_eq!(detect_archive_type(&PathBuf::from("test.tar.xz")), "tar.xz");
+        assert_eq!(
+            detect_archive_type(&PathBuf::from("test.tar.zst")),
+            "tar.zst"
+        );
+        assert_eq!(detect_archive_type(&PathBuf::from("test.tzst")), "tar.zst");
+        assert_eq!(detect_archive_type(&PathBuf::from("test.7z")), "7z");
+        assert_eq!(detect_archive_type(&PathBuf::from("server.log.gz")), "gz");
+        assert_eq!(detect_archive_type(&PathBuf::from("dump.sql.bz2")), "bz2");
+        assert_eq!(detect_archive_type(&PathBuf::from("image.raw.xz")), "xz");
+        assert_eq!(detect_archive_type(&PathBuf::from("dump.sql.zst")), "zst");
+        assert_eq!(detect_archive_type(&PathBuf::from("test.unknown")), "unknown");
+    }
+
+    #[test]
+    fn test_archive_type_prefers_magic_bytes() {
+        let dir = test_dir("magic");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(dir.join("backup")).unwrap(),
+            Default::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(2);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "hi.txt", &b"hi"[..])
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap();
+        write_test_zip(&dir.join("really-a-zip.tar"), &[("a.txt", b"a")]);
+        fs::write(dir.join("notes.zip"), b"not an archive").unwrap();
+
+        assert_eq!(archive_type(&dir.join("backup")), "tar.gz");
+        assert_eq!(archive_type(&dir.join("really-a-zip.tar")), "zip");
+        assert_eq!(archive_type(&dir.join("notes.zip")), "zip");
+        assert_eq!(list_archive(&dir.join("backup")).unwrap()[0].path, "hi.txt");
+        assert_eq!(
+            list_archive(&dir.join("really-a-zip.tar")).unwrap()[0].path,
+            "a.txt"
+        );
+    }
+
+    #[test]
+    fn test_validate_extract_path_prevents_traversal() {
+        let dest = PathBuf::from("/tmp/extract");
+        
+        // Normal path should work
+        let normal = PathBuf::from("file.txt");
+        assert!(validate_extract_path(&dest, &normal).is_ok());
+        
+        // Path with .. leaving the destination is rejected before anything touches disk
+        let traversal = PathBuf::from("../../etc/passwd");
+        let result = validate_extract_path(&dest, &traversal);
+        assert_eq!(result.unwrap_err().code(), "traversal");
+    }
+
+    #[test]
+    fn test_validate_extract_path_rejects_windows_prefixes() {
+        let dest = PathBuf::from("/tmp/extract");
+        for path in [
+            "C:..\\..\\Windows",
+            "C:foo\\bar",
+            "c:",
+            "dir/D:evil.txt",
+            "dir\\E:\\evil.txt",
+            "\\\\?\\C:\\Windows\\win.ini",
+            "\\\\.\\PhysicalDrive0",
+            "\\\\server\\share\\file",
+        ] {
+            assert!(
+                validate_extract_path(&dest, &PathBuf::from(path)).is_err(),
+                "{path} should be rejected"
+            );
+        }
+        assert!(validate_extract_path(&dest, Path::new("notes:v2.txt")).is_ok());
+    }
+
+    #[test]
+    fn test_validate_entry_name() {
+        assert_eq!(validate_entry_name("dir/file.txt").unwrap(), "dir/file.txt");
+        assert_eq!(validate_entry_name("./a/../b.txt").unwrap(), "b.txt");
+        assert!(validate_entry_name("../escape.txt").is_err());
+        assert!(validate_entry_name("/abs.txt").is_err());
+        assert!(validate_entry_name("").is_err());
+    }
+
+    #[test]
+    fn test_detect_mime_type() {
+        assert_eq!(detect_mime_type("file.jpg"), "image/jpeg");
+        assert_eq!(detect_mime_type("file.png"), "image/png");
+        assert_eq!(detect_mime_type("file.json"), "application/json");
         assert_eq!(detect_mime_type("file.txt"), "text/plain");
+        assert_eq!(detect_mime_type("Cargo.toml"), "text/x-toml");
+        assert_eq!(detect_mime_type("ci.YML"), "text/yaml");
+        assert_eq!(detect_mime_type("server.log"), "text/plain");
+        assert_eq!(detect_mime_type("data.csv"), "text/csv");
+        assert_eq!(detect_mime_type("main.cpp"), "text/x-c++");
+        assert_eq!(detect_mime_type("schema.sql"), "text/x-sql");
         assert_eq!(detect_mime_type("file.unknown"), "application/octet-stream");
     }
+
+    /// Fresh scratch directory for tests that touch the filesystem.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("capsule-test-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// `CreateZipArgs` for a plain deflated zip of `inputs` at `output`; tests override the
+    /// fields they exercise with `..test_zip_args(...)`.
+    fn test_zip_args(output: &Path, inputs: &[&Path]) -> CreateZipArgs {
+        CreateZipArgs {
+            output_path: output.to_string_lossy().into(),
+            input_paths: inputs.iter().map(|p| p.to_string_lossy().into()).collect(),
+            compression_mode: "deflate".into(),
+            compression_level: None,
+            parallel_compression: false,
+            temp_dir: None,
+            password: None,
+            encryption_method: None,
+            strict_validate: None,
+            exclude_patterns: vec![],
+            split_size_bytes: None,
+        }
+    }
+
+    /// Replace every occurrence of `from` in the file at `path` with `to` (same length), e.g.
+    /// to store a name's raw bytes without the flags the zip writer would set.
+    fn patch_test_zip(path: &Path, from: &[u8], to: &[u8]) {
+        assert_eq!(from.len(), to.len());
+        let mut bytes = fs::read(path).unwrap();
+        let mut at = 0;
+        while let Some(found) = bytes[at..].windows(from.len()).position(|w| w == from) {
+            at += found;
+            bytes[at..at + to.len()].copy_from_slice(to);
+            at += to.len();
+        }
+        fs::write(path, bytes).unwrap();
+    }
+
+    /// Write a zip containing `entries` in the given order; names ending in `/` become directories.
+    fn write_test_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut writer = ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in entries {
+            if name.ends_with('/') {
+                writer.add_directory(*name, FileOptions::default()).unwrap();
+            } else {
+                writer.start_file(*name, FileOptions::default()).unwrap();
+                writer.write_all(data).unwrap();
+            }
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_extract_zip_dedupe_by_name() {
+        let dir = test_dir("dedupe");
+        let src = dir.join("dupes.zip");
+        write_test_zip(
+            &src,
+            &[("a.txt", b"one"), ("b.txt", b"b"), ("a.txt", b"two")],
+        );
+
+        for (mode, expected) in [("first", "one"), ("last", "two")] {
+            let dest = dir.join(mode);
+            let options = ExtractOptions {
+                dedupe_by_name: Some(mode.into()),
+                ..Default::default()
+            };
+            let summary = extract_zip(&src, &dest, &options).unwrap();
+            assert_eq!(summary.extracted, 2);
+            assert_eq!(summary.skipped_duplicates, vec!["a.txt".to_string()]);
+            assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), expected);
+        }
+
+        let options = ExtractOptions {
+            dedupe_by_name: Some("middle".into()),
+            ..Default::default()
+        };
+        assert!(extract_zip(&src, &dir.join("bad"), &options).is_err());
+    }
+
+    #[test]
+    fn test_extract_zip_concurrency_matches_sequential() {
+        let dir = test_dir("concurrency");
+        let src = dir.join("many.zip");
+        let names: Vec<String> = (0..80).map(|i| format!("d{}/f{i}.txt", i % 5)).collect();
+        let mut entries: Vec<(&str, &[u8])> =
+            names.iter().map(|n| (n.as_str(), n.as_bytes())).collect();
+        entries.push(("d0/f0.txt", b"second copy"));
+        write_test_zip(&src, &entries);
+
+        for (label, concurrency, adaptive) in
+            [("seq", 1, false), ("par", 4, false), ("adaptive", 4, true)]
+        {
+            let dest = dir.join(label);
+            let options = ExtractOptions {
+                concurrency: Some(concurrency),
+                adaptive_concurrency: Some(adaptive),
+                ..Default::default()
+            };
+            let summary = extract_zip(&src, &dest, &options).unwrap();
+            assert_eq!(summary.extracted, 81);
+            assert_eq!(fs::read(dest.join("d0/f0.txt")).unwrap(), b"second copy");
+            assert_eq!(fs::read(dest.join("d3/f78.txt")).unwrap(), b"d3/f78.txt");
+            if !adaptive {
+                assert_eq!(summary.concurrency, Some(concurrency));
+            }
+        }
+
+        let options = ExtractOptions {
+            concurrency: Some(0),
+            ..Default::default()
+        };
+        assert!(extract_zip(&src, &dir.join("zero"), &options).is_err());
+    }
+
+    #[test]
+    fn test_format_epoch() {
+        assert_eq!(format_epoch(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_epoch(951_782_400 + 3661), "2000-02-29T01:01:01Z");
+        let dos = zip::DateTime::from_date_and_time(2024, 12, 31, 23, 59, 58).unwrap();
+        assert_eq!(zip_modified(dos).as_deref(), Some("2024-12-31T23:59:58Z"));
+        assert_eq!(zip_modified(zip::DateTime::from_msdos(0, 0)), None);
+    }
+
+    #[test]
+    fn test_entries_modified_after_archive() {
+        let dir = test_dir("mtime");
+        let src = dir.join("stamped.zip");
+        let mut writer = ZipWriter::new(File::create(&src).unwrap());
+        for (name, year) in [("old.txt", 2001), ("future.txt", 2100)] {
+            let time = zip::DateTime::from_date_and_time(year, 6, 1, 12, 0, 0).unwrap();
+            writer
+                .start_file(name, FileOptions::default().last_modified_time(time))
+                .unwrap();
+        }
+        writer.finish().unwrap();
+
+        let entries = list_archive(&src).unwrap();
+        assert_eq!(entries[0].modified.as_deref(), Some("2001-06-01T12:00:00Z"));
+
+        let newer = tauri::async_runtime::block_on(entries_modified_after_archive(
+            src.to_string_lossy().into(),
+        ))
+        .unwrap();
+        assert_eq!(newer, ["future.txt"]);
+    }
+
+    #[test]
+    fn test_dump_comments() {
+        let dir = test_dir("comments");
+        let plain = dir.join("plain.zip");
+        write_test_zip(&plain, &[("a.txt", b"a")]);
+        let report =
+            tauri::async_runtime::block_on(dump_comments(plain.to_string_lossy().into())).unwrap();
+        assert!(report.archive_comment.is_none());
+        assert!(report.entry_comments.is_empty());
+
+        let commented = dir.join("commented.zip");
+        let mut writer = ZipWriter::new(File::create(&commented).unwrap());
+        writer.set_comment("Release build 42");
+        writer.start_file("a.txt", FileOptions::default()).unwrap();
+        writer.finish().unwrap();
+        let report =
+            tauri::async_runtime::block_on(dump_comments(commented.to_string_lossy().into()))
+                .unwrap();
+        assert_eq!(report.archive_comment.as_deref(), Some("Release build 42"));
+    }
+
+    #[test]
+    fn test_create_diff_archive() {
+        let dir = test_dir("diff-archive");
+        let source = dir.join("src");
+        fs::create_dir_all(source.join("docs")).unwrap();
+        fs::write(source.join("same.txt"), b"same").unwrap();
+        fs::write(source.join("docs/edit.txt"), b"before").unwrap();
+        fs::write(source.join("gone.txt"), b"gone").unwrap();
+
+        let base = dir.join("base.zip");
+        write_zip_archive(test_zip_args(&base, &[&source]), None).unwrap();
+
+        fs::write(source.join("docs/edit.txt"), b"after!").unwrap();
+        fs::remove_file(source.join("gone.txt")).unwrap();
+        fs::write(source.join("new.txt"), b"new").unwrap();
+
+        let output = dir.join("diff.zip");
+        let report = tauri::async_runtime::block_on(create_diff_archive(
+            base.to_string_lossy().into(),
+            source.to_string_lossy().into(),
+            output.to_string_lossy().into(),
+        ))
+        .unwrap();
+        assert_eq!(
+            (
+                report.added,
+                report.changed,
+                report.deleted,
+                report.unchanged
+            ),
+            (1, 1, 1, 1)
+        );
+
+        let mut archive = ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        let mut text = String::new();
+        archive
+            .by_name("docs/edit.txt")
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "after!");
+        assert!(archive.by_name("new.txt").is_ok());
+        assert!(archive.by_name("same.txt").is_err());
+        text.clear();
+        archive
+            .by_name(DELETIONS_MANIFEST)
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "gone.txt");
+    }
+
+    #[test]
+    fn test_entry_offsets() {
+        let dir = test_dir("offsets");
+        let src = dir.join("two.zip");
+        write_test_zip(&src, &[("a.txt", b"aaaa"), ("b.txt", b"bb")]);
+        let bytes = fs::read(&src).unwrap();
+
+        let offsets =
+            tauri::async_runtime::block_on(entry_offsets(src.to_string_lossy().into())).unwrap();
+        assert_eq!(offsets.len(), 2);
+        for entry in &offsets {
+            let start = entry.header_offset as usize;
+            // Every offset points at a local file header signature.
+            assert_eq!(&bytes[start..start + 4], b"PK\x03\x04");
+        }
+        assert_eq!(offsets[0].header_offset, 0);
+        assert!(offsets[1].header_offset > offsets[0].compressed_size);
+    }
+
+    #[test]
+    fn test_extract_tar_sparse_entries() {
+        let dir = test_dir("sparse");
+        let src = dir.join("disk.tar");
+        // GNU sparse entry: a 1 MiB file whose only data is 512 bytes at offset 4096.
+        let real_size = 1024 * 1024;
+        let data = [7u8; 512];
+        let mut header = tar::Header::new_gnu();
+        header.set_path("disk.img").unwrap();
+        header.set_entry_type(tar::EntryType::GNUSparse);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        let gnu = header.as_gnu_mut().unwrap();
+        gnu.set_real_size(real_size);
+        gnu.sparse[0].set_offset(4096);
+        gnu.sparse[0].set_length(data.len() as u64);
+        // Trailing hole: GNU tar ends the map with an empty chunk at the real size.
+        gnu.sparse[1].set_offset(real_size);
+        gnu.sparse[1].set_length(0);
+        header.set_cksum();
+        let mut builder = tar::Builder::new(File::create(&src).unwrap());
+        builder.append(&header, &data[..]).unwrap();
+        builder.finish().unwrap();
+
+        for preserve in [true, false] {
+            let dest = dir.join(format!("out-{preserve}"));
+            let options = ExtractOptions {
+                preserve_sparse: Some(preserve),
+                ..Default::default()
+            };
+            let archive = open_tar_reader(&src, "tar").unwrap();
+            let summary = extract_tar_like(archive, &dest, &options).unwrap();
+            let expected_saved = if preserve { real_size - 512 } else { 0 };
+            assert_eq!(summary.sparse_bytes_saved, expected_saved);
+
+            let out = fs::read(dest.join("disk.img")).unwrap();
+            assert_eq!(out.len() as u64, real_size);
+            assert!(out[..4096].iter().all(|&b| b == 0));
+            assert_eq!(&out[4096..4608], &data[..]);
+            assert!(out[4608..].iter().all(|&b| b == 0));
+        }
+    }
+
+    #[test]
+    fn test_build_index_sidecar() {
+        let dir = test_dir("capidx");
+        let src = dir.join("big.zip");
+        write_test_zip(&src, &[("a.txt", b"a"), ("b.txt", b"bb")]);
+        let path = src.to_string_lossy().to_string();
+
+        let count = tauri::async_runtime::block_on(build_index(path.clone())).unwrap();
+        assert_eq!(count, 2);
+        let index = load_index(&src).unwrap();
+        assert_eq!(index.entries[1].entry.path, "b.txt");
+        assert!(index.entries[1].header_offset > 0);
+
+        // A current sidecar is served as-is, without scanning the archive.
+        let mut index = index;
+        index.entries.truncate(1);
+        fs::write(index_path(&src), serde_json::to_vec(&index).unwrap()).unwrap();
+        let entries =
+            open_archive_cached(&ArchiveCache::default(), path.clone(), None, None).unwrap();
+        assert_eq!(entries.len(), 1);
+
+        // Changing the archive invalidates it.
+        write_test_zip(
+            &src,
+            &[("a.txt", b"a"), ("b.txt", b"bb"), ("c.txt", b"ccc")],
+        );
+        let entries =
+            open_archive_cached(&ArchiveCache::default(), path.clone(), None, None).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        assert!(tauri::async_runtime::block_on(clear_index(path.clone())).unwrap());
+        assert!(!tauri::async_runtime::block_on(clear_index(path)).unwrap());
+
+        // Formats without entry offsets to record are refused, not read as a tarball.
+        let content = dir.join("content");
+        fs::create_dir_all(&content).unwrap();
+        fs::write(content.join("a.txt"), b"a").unwrap();
+        let sevenz = dir.join("big.7z");
+        sevenz_rust::compress_to_path(&content, &sevenz).unwrap();
+        let err = tauri::async_runtime::block_on(build_index(sevenz.to_string_lossy().into()))
+            .unwrap_err();
+        assert_eq!(err.code(), "unsupported");
+        assert!(!index_path(&sevenz).exists());
+    }
+
+    #[test]
+    fn test_extract_zip_best_effort() {
+        let dir = test_dir("best-effort");
+        let src = dir.join("damaged.zip");
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let mut writer = ZipWriter::new(File::create(&src).unwrap());
+        for (name, data) in [("good.txt", "intact"), ("bad.txt", "corrupt me")] {
+            writer.start_file(name, stored).unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+        let mut bytes = fs::read(&src).unwrap();
+        let pos = bytes.windows(7).position(|w| w == b"corrupt").unwrap();
+        bytes[pos] ^= 0xff;
+        fs::write(&src, bytes).unwrap();
+
+        assert!(extract_zip(&src, &dir.join("strict"), &ExtractOptions::default()).is_err());
+
+        let dest = dir.join("recovered");
+        let options = ExtractOptions {
+            best_effort: Some(true),
+            ..Default::default()
+        };
+        let summary = extract_zip(&src, &dest, &options).unwrap();
+        assert_eq!(summary.extracted, 1);
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].name, "bad.txt");
+        assert_eq!(fs::read(dest.join("good.txt")).unwrap(), b"intact");
+        assert!(!dest.join("bad.txt").exists());
+    }
+
+    #[test]
+    fn test_diff_manifests() {
+        let dir = test_dir("manifests");
+        let a = dir.join("a.zip");
+        let b = dir.join("b.tar");
+        write_test_zip(
+            &a,
+            &[
+                ("same.txt", b"same"),
+                ("edit.txt", b"v1"),
+                ("gone.txt", b"x"),
+            ],
+        );
+        let mut builder = tar::Builder::new(File::create(&b).unwrap());
+        for (name, data) in [("same.txt", "same"), ("edit.txt", "v2"), ("new.txt", "n")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, data.as_bytes())
+                .unwrap();
+        }
+        builder.finish().unwrap();
+
+        let (ma, mb) = (dir.join("a.json"), dir.join("b.json"));
+        for (archive, manifest) in [(&a, &ma), (&b, &mb)] {
+            tauri::async_runtime::block_on(export_manifest(
+                archive.to_string_lossy().into(),
+                manifest.to_string_lossy().into(),
+            ))
+            .unwrap();
+        }
+
+        let diff = tauri::async_runtime::block_on(diff_manifests(
+            ma.to_string_lossy().into(),
+            mb.to_string_lossy().into(),
+        ))
+        .unwrap();
+        assert_eq!(diff.added, ["new.txt"]);
+        assert_eq!(diff.removed, ["gone.txt"]);
+        assert_eq!(diff.changed, ["edit.txt"]);
+
+        let bogus = dir.join("bogus.json");
+        fs::write(&bogus, br#"{"entries": 3}"#).unwrap();
+        let err = tauri::async_runtime::block_on(diff_manifests(
+            ma.to_string_lossy().into(),
+            bogus.to_string_lossy().into(),
+        ))
+        .unwrap_err();
+        assert!(err.message().contains("not a Capsule manifest"), "{err}");
+    }
+
+    #[test]
+    fn test_extract_entry_verified() {
+        let dir = test_dir("verified");
+        let src = dir.join("dl.zip");
+        write_test_zip(&src, &[("payload.bin", b"abc")]);
+        // SHA-256("abc")
+        let digest = "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD";
+        let run = |output: &Path, expected: &str| {
+            tauri::async_runtime::block_on(extract_entry_verified(
+                src.to_string_lossy().into(),
+                "payload.bin".into(),
+                output.to_string_lossy().into(),
+                expected.into(),
+            ))
+        };
+
+        let good = dir.join("good.bin");
+        run(&good, digest).unwrap();
+        assert_eq!(fs::read(&good).unwrap(), b"abc");
+
+        let bad = dir.join("bad.bin");
+        let wrong = digest.replace('B', "C");
+        let err = run(&bad, &wrong).unwrap_err();
+        assert!(err.message().contains("mismatch"), "{err}");
+        assert!(!bad.exists());
+        assert!(!dir.join("bad.bin.partial").exists());
+
+        assert!(run(&bad, "abc").is_err());
+    }
+
+    #[test]
+    fn test_space_guard() {
+        let dir = test_dir("space-guard");
+        let src = dir.join("a.zip");
+        write_test_zip(&src, &[("a.txt", b"a")]);
+
+        let options = |min_free_bytes| ExtractOptions {
+            min_free_bytes: Some(min_free_bytes),
+            ..Default::default()
+        };
+        let err = extract_zip(&src, &dir.join("full"), &options(u64::MAX)).unwrap_err();
+        assert!(err.message().starts_with(LOW_SPACE_ERROR), "{err}");
+        assert!(!dir.join("full/a.txt").exists());
+        extract_zip(&src, &dir.join("ok"), &options(1)).unwrap();
+
+        // Tripping removes what was written and reports through the notifier.
+        let notified = std::sync::Arc::new(AtomicBool::new(false));
+        let flag = notified.clone();
+        let mut options = options(0);
+        options.low_space_notifier = Some(LowSpaceNotifier(Box::new(move |_| {
+            flag.store(true, Ordering::Relaxed)
+        })));
+        let mut guard = SpaceGuard::new(&dir, &options).unwrap().unwrap();
+        let written = dir.join("written.bin");
+        fs::write(&written, b"x").unwrap();
+        guard.record(&written, 1).unwrap();
+        guard.min_free_bytes = u64::MAX;
+        assert!(guard.check().is_err());
+        assert!(!written.exists());
+        assert!(notified.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn test_find_archives() {
+        let dir = test_dir("find-archives");
+        fs::create_dir_all(dir.join("sub/deep")).unwrap();
+        write_test_zip(&dir.join("a.zip"), &[("x", b"x")]);
+        write_test_zip(&dir.join("download"), &[("x", b"x")]);
+        fs::write(dir.join("notes.txt"), b"not an archive").unwrap();
+        fs::write(dir.join("sub/b.tar.gz"), b"").unwrap();
+        fs::write(dir.join("sub/deep/c.tar"), b"").unwrap();
+
+        let find = |recursive, depth| {
+            let found = tauri::async_runtime::block_on(find_archives(
+                dir.to_string_lossy().into(),
+                recursive,
+                depth,
+            ))
+            .unwrap();
+            found
+                .archives
+                .iter()
+                .map(|a| {
+                    let rel = Path::new(&a.path).strip_prefix(&dir).unwrap();
+                    format!("{}:{}", rel.to_string_lossy().replace('\\', "/"), a.kind)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(find(false, None), ["a.zip:zip", "download:zip"]);
+        assert_eq!(
+            find(true, Some(1)),
+            ["a.zip:zip", "download:zip", "sub/b.tar.gz:tar.gz"]
+        );
+        assert_eq!(find(true, None).len(), 4);
+    }
+
+    #[test]
+    fn test_apply_exif_orientation() {
+        // 2x1 JPEG with an APP1 segment saying "rotate 90° clockwise" (orientation 6).
+        let mut jpeg = io::Cursor::new(Vec::new());
+        image::DynamicImage::new_rgb8(2, 1)
+            .write_to(&mut jpeg, image::ImageFormat::Jpeg)
+            .unwrap();
+        let jpeg = jpeg.into_inner();
+        let mut tiff = b"Exif\0\0II*\0\x08\0\0\0\x01\0".to_vec();
+        tiff.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0, 0, 0, 0, 0]);
+        let mut rotated = jpeg[..2].to_vec();
+        rotated.extend_from_slice(&[0xff, 0xe1]);
+        rotated.extend_from_slice(&((tiff.len() + 2) as u16).to_be_bytes());
+        rotated.extend_from_slice(&tiff);
+        rotated.extend_from_slice(&jpeg[2..]);
+
+        let (out, mime) = apply_exif_orientation(&rotated, "image/jpeg").unwrap();
+        assert_eq!(mime, "image/jpeg");
+        let img = image::load_from_memory(&out).unwrap();
+        assert_eq!((img.width(), img.height()), (1, 2));
+
+        assert!(apply_exif_orientation(&jpeg, "image/jpeg").is_none());
+        assert!(apply_exif_orientation(&rotated, "image/png").is_none());
+    }
+
+    #[test]
+    fn test_numbered_name() {
+        assert_eq!(numbered_name("docs/Readme.txt", 1), "docs/Readme (1).txt");
+        assert_eq!(numbered_name("Makefile", 2), "Makefile (2)");
+        assert_eq!(numbered_name(".env", 1), ".env (1)");
+    }
+
+    #[test]
+    fn test_case_collisions_and_policy() {
+        let dir = test_dir("case");
+        let src = dir.join("case.zip");
+        write_test_zip(
+            &src,
+            &[
+                ("Readme.txt", b"one"),
+                ("README.TXT", b"two"),
+                ("x.txt", b"x"),
+            ],
+        );
+
+        let groups = find_case_collisions(&open_zip(&src, NameEncoding::default()).unwrap());
+        assert_eq!(
+            groups,
+            vec![vec!["README.TXT".to_string(), "Readme.txt".to_string()]]
+        );
+
+        let options = |policy: &str| ExtractOptions {
+            case_collision_policy: Some(policy.into()),
+            ..Default::default()
+        };
+
+        let summary = extract_zip(&src, &dir.join("rename"), &options("rename")).unwrap();
+        assert_eq!(summary.renamed[0].to, "README (1).TXT");
+        assert_eq!(
+            fs::read_to_string(dir.join("rename/README (1).TXT")).unwrap(),
+            "two"
+        );
+
+        let summary = extract_zip(&src, &dir.join("skip"), &options("skip")).unwrap();
+        assert_eq!(
+            summary.skipped_case_collisions,
+            vec!["README.TXT".to_string()]
+        );
+        assert!(!dir.join("skip/README.TXT").exists());
+
+        assert!(extract_zip(&src, &dir.join("error"), &options("error")).is_err());
+    }
+
+    #[test]
+    fn test_extract_entries_with_rebase_root() {
+        let dir = test_dir("rebase");
+        let src = dir.join("project.zip");
+        write_test_zip(
+            &src,
+            &[
+                ("project/src/a.rs", b"a"),
+                ("project/src/lib/b.rs", b"b"),
+                ("project/README", b"readme"),
+            ],
+        );
+        let selected = vec![
+            "project/src/a.rs".to_string(),
+            "project/src/lib/b.rs".to_string(),
+            "project/missing.rs".to_string(),
+        ];
+
+        let dest = dir.join("out");
+        let summary =
+            extract_zip_entries(&src, &dest, &selected[..2], Some("project/src/")).unwrap();
+        assert_eq!(summary.extracted, 2);
+        assert_eq!(fs::read_to_string(dest.join("a.rs")).unwrap(), "a");
+        assert_eq!(fs::read_to_string(dest.join("lib/b.rs")).unwrap(), "b");
+
+        let summary = extract_zip_entries(&src, &dir.join("plain"), &selected, None).unwrap();
+        assert_eq!(summary.not_found, vec!["project/missing.rs".to_string()]);
+        assert!(dir.join("plain/project/src/a.rs").exists());
+
+        assert!(rebase_entry_path("project/README", Some("project/src")).is_err());
+        assert!(rebase_entry_path("project/srcfoo/x", Some("project/src")).is_err());
+    }
+
+    #[test]
+    fn test_extract_tar_entries_checks_links() {
+        let dir = test_dir("tar-entries-links");
+        let src = dir.join("links.tar");
+        let mut builder = tar::Builder::new(File::create(&src).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "a.txt", &b"alpha"[..])
+            .unwrap();
+        for (kind, link, target) in [
+            (tar::EntryType::Link, "copy.txt", "a.txt"),
+            (tar::EntryType::Link, "passwd", "/etc/passwd"),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(kind);
+            header.set_size(0);
+            header.set_mode(0o644);
+            builder.append_link(&mut header, link, target).unwrap();
+        }
+        builder.finish().unwrap();
+        let extract = |dest: &Path, names: &[&str]| {
+            let names: Vec<String> = names.iter().map(|n| n.to_string()).collect();
+            extract_tar_entries(open_tar_reader(&src, "tar").unwrap(), dest, &names, None)
+        };
+
+        let dest = dir.join("good");
+        let summary = extract(&dest, &["a.txt", "copy.txt"]).unwrap();
+        assert_eq!(summary.extracted, 2);
+        assert_eq!(fs::read(dest.join("copy.txt")).unwrap(), b"alpha");
+
+        let dest = dir.join("evil");
+        let err = extract(&dest, &["passwd"]).unwrap_err();
+        assert_eq!(err.code(), "traversal");
+        assert!(dest.join("passwd").symlink_metadata().is_err());
+    }
+
+    #[test]
+    fn test_post_extract_verify_and_list() {
+        let dir = test_dir("post-extract");
+        let src = dir.join("src.zip");
+        let dest = dir.join("out");
+        write_test_zip(
+            &src,
+            &[("docs/", b""), ("docs/a.txt", b"alpha"), ("b.txt", b"beta")],
+        );
+        let summary = extract_zip(&src, &dest, &ExtractOptions::default()).unwrap();
+
+        let result = run_post_extract_action(PostExtractAction::Verify, &dest, &summary).unwrap();
+        let PostExtractResult::Verify {
+            checked,
+            mismatched,
+        } = result
+        else {
+            panic!("expected verify result");
+        };
+        assert_eq!(checked, 2);
+        assert!(mismatched.is_empty());
+
+        fs::write(dest.join("b.txt"), b"tampered").unwrap();
+        let (_, mismatched) = verify_extracted(&summary.written);
+        assert_eq!(mismatched, vec!["b.txt".to_string()]);
+
+        let result =
+            run_post_extract_action(PostExtractAction::ListFiles, &dest, &summary).unwrap();
+        let PostExtractResult::ListFiles { files } = result else {
+            panic!("expected file list");
+        };
+        assert_eq!(files.len(), 2);
+
+        // A renamed entry is checked and listed where it was written, not at its stored name.
+        let options = ExtractOptions {
+            overwrite: Some("rename".into()),
+            ..Default::default()
+        };
+        let summary = extract_zip(&src, &dest, &options).unwrap();
+        let (checked, mismatched) = verify_extracted(&summary.written);
+        assert_eq!(checked, 2);
+        assert!(mismatched.is_empty());
+        let result =
+            run_post_extract_action(PostExtractAction::ListFiles, &dest, &summary).unwrap();
+        let PostExtractResult::ListFiles { files } = result else {
+            panic!("expected file list");
+        };
+        assert!(files.contains(&dest.join("b (1).txt").to_string_lossy().to_string()));
+        assert_eq!(fs::read_to_string(dest.join("b.txt")).unwrap(), "tampered");
+    }
+
+    #[test]
+    fn test_suggest_nested_open() {
+        let dir = test_dir("nested");
+        let single = dir.join("single.zip");
+        write_test_zip(&single, &[("wrap/", b""), ("wrap/inner.tar.gz", b"gz")]);
+        let suggestion =
+            tauri::async_runtime::block_on(suggest_nested_open(single.to_string_lossy().into()))
+                .unwrap()
+                .unwrap();
+        assert_eq!(suggestion.inner_entry, "wrap/inner.tar.gz");
+        assert_eq!(suggestion.inner_kind, "tar.gz");
+
+        let mixed = dir.join("mixed.zip");
+        write_test_zip(&mixed, &[("inner.zip", b"zip"), ("readme.txt", b"hi")]);
+        let suggestion =
+            tauri::async_runtime::block_on(suggest_nested_open(mixed.to_string_lossy().into()))
+                .unwrap();
+        assert!(suggestion.is_none());
+    }
+
+    #[test]
+    fn test_create_encrypted_zip_round_trip() {
+        let dir = test_dir("encrypted");
+        let input = dir.join("secret.txt");
+        fs::write(&input, b"top secret").unwrap();
+
+        for method in ["aes256", "zipcrypto"] {
+            let output = dir.join(format!("{method}.zip"));
+            let result = write_zip_archive(
+                CreateZipArgs {
+                    password: Some("hunter2".into()),
+                    encryption_method: Some(method.into()),
+                    strict_validate: Some(true),
+                    ..test_zip_args(&output, &[&input])
+                },
+                None,
+            )
+            .unwrap();
+            assert_eq!(result.warnings.is_empty(), method == "aes256");
+
+            let mut archive = ZipArchive::new(File::open(&output).unwrap()).unwrap();
+            assert!(archive.by_name("secret.txt").is_err());
+            assert!(archive
+                .by_name_decrypt("secret.txt", b"wrong")
+                .unwrap()
+                .is_err());
+
+            let mut text = String::new();
+            archive
+                .by_name_decrypt("secret.txt", b"hunter2")
+                .unwrap()
+                .unwrap()
+                .read_to_string(&mut text)
+                .unwrap();
+            assert_eq!(text, "top secret");
+        }
+    }
+
+    #[test]
+    fn test_extract_all_to_memory_respects_cap() {
+        let dir = test_dir("in-memory");
+        let zip_path = dir.join("comic.zip");
+        write_test_zip(
+            &zip_path,
+            &[
+                ("pages/", b""),
+                ("pages/01.png", b"aaaa"),
+                ("notes.txt", b"hello"),
+                ("pages/02.png", b"bbbb"),
+                ("pages/03.png", b"cccc"),
+            ],
+        );
+        let path = zip_path.to_string_lossy().to_string();
+
+        let all =
+            tauri::async_runtime::block_on(extract_all_to_memory(path.clone(), 100, None)).unwrap();
+        assert_eq!(all.entries.len(), 4);
+        assert!(!all.truncated);
+
+        let images =
+            tauri::async_runtime::block_on(extract_all_to_memory(path, 9, Some(true))).unwrap();
+        let names: Vec<_> = images.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["pages/01.png", "pages/02.png"]);
+        assert_eq!(images.entries[1].data_base64, BASE64.encode(b"bbbb"));
+        assert!(images.truncated);
+
+        // A header that understates the size can't read past the cap.
+        let mut out = InMemoryExtraction::default();
+        let mut total = 0;
+        let mut reader = &b"far more than declared"[..];
+        let taken =
+            take_into_memory(&mut reader, "lie.bin".into(), 2, 8, &mut total, &mut out).unwrap();
+        assert!(!taken && out.truncated && out.entries.is_empty());
+        assert_eq!(total, 0);
+
+        // The format comes from the content, and ones without a reader here are refused.
+        let tar_path = dir.join("comic-tar.zip");
+        let mut builder = tar::Builder::new(File::create(&tar_path).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "01.png", &b"aaaa"[..])
+            .unwrap();
+        builder.finish().unwrap();
+        let from_tar = tauri::async_runtime::block_on(extract_all_to_memory(
+            tar_path.to_string_lossy().into(),
+            100,
+            None,
+        ))
+        .unwrap();
+        assert_eq!(from_tar.entries.len(), 1);
+        let sevenz_src = dir.join("pages");
+        fs::create_dir_all(&sevenz_src).unwrap();
+        fs::write(sevenz_src.join("01.png"), b"aaaa").unwrap();
+        let sevenz = dir.join("comic.7z");
+        sevenz_rust::compress_to_path(&sevenz_src, &sevenz).unwrap();
+        let err = tauri::async_runtime::block_on(extract_all_to_memory(
+            sevenz.to_string_lossy().into(),
+            100,
+            None,
+        ))
+        .unwrap_err();
+        assert_eq!(err.code(), "unsupported");
+    }
+
+    #[test]
+    fn test_create_zip_dirs_precede_files() {
+        let dir = test_dir("dir-order");
+        let one = dir.join("one");
+        let two = dir.join("two");
+        fs::create_dir_all(one.join("sub/deep")).unwrap();
+        fs::create_dir_all(two.join("sub")).unwrap();
+        fs::write(one.join("sub/deep/a.txt"), b"a").unwrap();
+        fs::write(one.join("top.txt"), b"t").unwrap();
+        fs::write(two.join("sub/b.txt"), b"b").unwrap();
+
+        let output = dir.join("out.zip");
+        write_zip_archive(test_zip_args(&output, &[&one, &two]), None).unwrap();
+
+        let mut archive = ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        let names: Vec<String> = (0..archive.len())
+            .map(|i| archive.by_index(i).unwrap().name().to_string())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "sub/",
+                "sub/deep/",
+                "sub/deep/a.txt",
+                "top.txt",
+                "sub/b.txt"
+            ]
+        );
+        for (i, name) in names.iter().enumerate() {
+            if let Some(slash) = name.trim_end_matches('/').rfind('/') {
+                let parent = &name[..=slash];
+                assert!(
+                    names[..i].iter().any(|n| n == parent),
+                    "{name} before {parent}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_transcode_shift_jis_names() {
+        let dir = test_dir("transcode");
+        let src = dir.join("legacy.zip");
+        write_test_zip(&src, &[("XXXXXX.txt", b"hello"), ("plain.txt", b"p")]);
+        // Swap in the Shift_JIS bytes for "テスト" in both local and central headers,
+        // the way old Japanese archivers stored names (no UTF-8 flag).
+        let sjis: &[u8] = &[0x83, 0x65, 0x83, 0x58, 0x83, 0x67];
+        let mut bytes = fs::read(&src).unwrap();
+        let mut at = 0;
+        while let Some(pos) = bytes[at..].windows(6).position(|w| w == b"XXXXXX") {
+            bytes[at + pos..at + pos + 6].copy_from_slice(sjis);
+            at += pos + 6;
+        }
+        fs::write(&src, bytes).unwrap();
+        let src_str = src.to_string_lossy().to_string();
+
+        let preview = tauri::async_runtime::block_on(preview_transcoded_names(
+            src_str.clone(),
+            "cp932".into(),
+        ))
+        .unwrap();
+        assert_eq!(preview[0].after, "テスト.txt");
+        assert_ne!(preview[0].before, preview[0].after);
+        assert_eq!(preview[1].after, "plain.txt");
+
+        let dest = dir.join("fixed.zip");
+        tauri::async_runtime::block_on(transcode_names(
+            src_str,
+            dest.to_string_lossy().into(),
+            "shift_jis".into(),
+        ))
+        .unwrap();
+        let mut archive = ZipArchive::new(File::open(&dest).unwrap()).unwrap();
+        let mut text = String::new();
+        archive
+            .by_name("テスト.txt")
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "hello");
+
+        assert!(lookup_encoding("klingon").is_err());
+    }
+
+    #[test]
+    fn test_validate_zip_file_catches_corruption() {
+        let dir = test_dir("strict-validate");
+        let src = dir.join("ok.zip");
+        let data = b"stored data that will be damaged ".repeat(8);
+        let mut writer = ZipWriter::new(File::create(&src).unwrap());
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file("data.txt", stored).unwrap();
+        writer.write_all(&data).unwrap();
+        writer.finish().unwrap();
+        assert!(validate_zip_file(&src, None).is_ok());
+
+        let mut bytes = fs::read(&src).unwrap();
+        let pos = bytes.windows(6).position(|w| w == b"stored").unwrap();
+        bytes[pos] ^= 0xff;
+        let bad = dir.join("bad.zip");
+        fs::write(&bad, bytes).unwrap();
+        let err = validate_zip_file(&bad, None).unwrap_err();
+        assert!(err.message().contains("data.txt"), "{err}");
+    }
+
+    #[test]
+    fn test_normalize_zip_sorts_and_preserves_data() {
+        let dir = test_dir("normalize");
+        let src = dir.join("src.zip");
+        let dest = dir.join("dest.zip");
+        write_test_zip(
+            &src,
+            &[
+                ("b.txt", b"bee"),
+                ("a/x.txt", b"ex"),
+                ("a/", b""),
+                ("ü.txt", b"u"),
+                ("cafXX.txt", b"cafe"),
+            ],
+        );
+        // A UTF-8 name stored without the flag keeps its spelling and gains the flag.
+        patch_test_zip(&src, b"cafXX", "café".as_bytes());
+
+        let report = tauri::async_runtime::block_on(normalize_zip(
+            src.to_string_lossy().to_string(),
+            dest.to_string_lossy().to_string(),
+        ))
+        .unwrap();
+        assert_eq!(report.entries, 5);
+        assert!(report.reordered);
+        assert_eq!(report.data_descriptors_removed, 0);
+        assert_eq!(report.utf8_flags_set, 1);
+
+        let mut archive = ZipArchive::new(File::open(&dest).unwrap()).unwrap();
+        let names: Vec<_> = (0..archive.len())
+            .map(|i| archive.by_index(i).unwrap().name().to_string())
+            .collect();
+        assert_eq!(names, ["a/", "a/x.txt", "b.txt", "café.txt", "ü.txt"]);
+
+        let mut text = String::new();
+        archive
+            .by_name("b.txt")
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "bee");
+    }
+
+    #[test]
+    fn test_7z_list_and_extract() {
+        let dir = test_dir("sevenz");
+        let src = dir.join("src");
+        fs::create_dir_all(src.join("docs")).unwrap();
+        fs::write(src.join("docs/readme.txt"), b"hello 7z").unwrap();
+        fs::write(src.join("top.bin"), vec![7u8; 4096]).unwrap();
+        let archive = dir.join("test.7z");
+        sevenz_rust::compress_to_path(&src, &archive).unwrap();
+
+        let entries = list_archive(&archive).unwrap();
+        let docs = entries.iter().find(|e| e.path == "docs").unwrap();
+        assert_eq!(docs.kind, "dir");
+        let readme = entries
+            .iter()
+            .find(|e| e.path == "docs/readme.txt")
+            .unwrap();
+        assert_eq!(
+            (readme.name.as_str(), readme.kind.as_str(), readme.size),
+            ("readme.txt", "file", 8)
+        );
+        assert!(readme.modified.is_some());
+
+        let dest = dir.join("out");
+        let summary = extract_7z(&archive, &dest, &ExtractOptions::default()).unwrap();
+        assert_eq!(summary.extracted, 2);
+        assert_eq!(fs::read(dest.join("docs/readme.txt")).unwrap(), b"hello 7z");
+        assert_eq!(fs::read(dest.join("top.bin")).unwrap(), vec![7u8; 4096]);
+    }
+
+    #[test]
+    fn test_tar_listing_modified() {
+        let dir = test_dir("tar-mtime");
+        let src = dir.join("stamped.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&src).unwrap(),
+            Default::default(),
+        ));
+        for (name, mtime) in [("dated.txt", 951_782_400 + 3661), ("far.txt", 1u64 << 62)] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(1);
+            header.set_mode(0o644);
+            header.set_mtime(mtime);
+            header.set_cksum();
+            builder.append_data(&mut header, name, &b"x"[..]).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let entries = list_archive(&src).unwrap();
+        assert_eq!(entries[0].modified.as_deref(), Some("2000-02-29T01:01:01Z"));
+        assert_eq!(entries[1].modified, None);
+    }
+
+    #[test]
+    fn test_tar_listing_kinds() {
+        let dir = test_dir("tar-kinds");
+        let src = dir.join("kinds.tar");
+        let mut builder = tar::Builder::new(File::create(&src).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_size(0);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "src/", io::empty())
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(2);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "src/main.rs", &b"//"[..])
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_mode(0o777);
+        builder
+            .append_link(&mut header, "latest", "src/main.rs")
+            .unwrap();
+        builder.finish().unwrap();
+
+        let kinds: Vec<_> = list_archive(&src)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.kind)
+            .collect();
+        assert_eq!(kinds, ["dir", "file", "symlink"]);
+    }
+
+    #[test]
+    fn test_extraction_stops_when_cancelled() {
+        let dir = test_dir("cancel");
+        let src = dir.join("src.zip");
+        write_test_zip(&src, &[("a.txt", b"a"), ("b.txt", b"b")]);
+        let options = ExtractOptions {
+            cancel: Some(Arc::new(AtomicBool::new(true))),
+            ..Default::default()
+        };
+
+        let err = extract_zip(&src, &dir.join("zip-out"), &options).unwrap_err();
+        assert_eq!(err.message(), EXTRACTION_CANCELLED);
+        assert!(!dir.join("zip-out/a.txt").exists());
+
+        let tar_path = dir.join("src.tar");
+        let mut builder = tar::Builder::new(File::create(&tar_path).unwrap());
+        builder.append_path_with_name(&src, "src.zip").unwrap();
+        builder.finish().unwrap();
+        let archive = open_tar_reader(&tar_path, "tar").unwrap();
+        let err = extract_tar_like(archive, &dir.join("tar-out"), &options).unwrap_err();
+        assert_eq!(err.message(), EXTRACTION_CANCELLED);
+    }
+
+    #[test]
+    fn test_extract_progress_reports_each_entry() {
+        let dir = test_dir("progress");
+        let src = dir.join("src.zip");
+        write_test_zip(
+            &src,
+            &[("a/", b""), ("a/one.txt", b"one"), ("two.txt", b"two!")],
+        );
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let options = ExtractOptions {
+            concurrency: Some(1),
+            progress: Some(ProgressNotifier(Box::new(move |index, total, bytes| {
+                sink.lock().unwrap().push((index, total, bytes));
+            }))),
+            ..Default::default()
+        };
+
+        extract_zip(&src, &dir.join("out"), &options).unwrap();
+        let seen = seen.lock().unwrap();
+        assert_eq!(*seen, [(1, Some(3), 0), (2, Some(3), 3), (3, Some(3), 7)]);
+    }
+
+    #[test]
+    fn test_extract_encrypted_zip() {
+        let dir = test_dir("decrypt");
+        let input = dir.join("secret.txt");
+        fs::write(&input, "top secret").unwrap();
+        let src = dir.join("locked.zip");
+        write_zip_archive(
+            CreateZipArgs {
+                password: Some("hunter2".into()),
+                encryption_method: Some("aes256".into()),
+                ..test_zip_args(&src, &[&input])
+            },
+            None,
+        )
+        .unwrap();
+
+        let entries = list_archive(&src).unwrap();
+        assert_eq!(entries[0].path, "secret.txt");
+        assert_eq!(
+            check_zip_password(&src, "nope").unwrap_err().message(),
+            INVALID_PASSWORD
+        );
+        assert!(check_zip_password(&src, "hunter2").is_ok());
+
+        let extract = |password: Option<&str>| {
+            let options = ExtractOptions {
+                password: password.map(String::from),
+                ..Default::default()
+            };
+            extract_zip(&src, &dir.join("out"), &options)
+        };
+        let err = extract(None).unwrap_err();
+        assert_eq!(
+            (err.code(), err.message()),
+            ("passwordRequired", PASSWORD_REQUIRED)
+        );
+        let err = extract(Some("nope")).unwrap_err();
+        assert_eq!(
+            (err.code(), err.message()),
+            ("invalidPassword", INVALID_PASSWORD)
+        );
+        extract(Some("hunter2")).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("out/secret.txt")).unwrap(),
+            "top secret"
+        );
+    }
+
+    #[test]
+    fn test_create_tar_archive() {
+        let dir = test_dir("create-tar");
+        let input = dir.join("input");
+        fs::create_dir_all(input.join("sub")).unwrap();
+        fs::write(input.join("sub/a.txt"), "aaa").unwrap();
+        fs::write(dir.join("b.txt"), "bb").unwrap();
+
+        for (compression, name) in [
+            ("none", "out.tar"),
+            ("gz", "out.tar.gz"),
+            ("bz2", "out.tar.bz2"),
+            ("xz", "out.tar.xz"),
+            ("zst", "out.tar.zst"),
+        ] {
+            let output = dir.join(name);
+            tauri::async_runtime::block_on(create_tar_archive(CreateTarArgs {
+                output_path: output.to_string_lossy().into(),
+                input_paths: vec![
+                    input.to_string_lossy().into(),
+                    dir.join("b.txt").to_string_lossy().into(),
+                ],
+                compression: compression.into(),
+            }))
+            .unwrap();
+
+            let listed: Vec<_> = list_archive(&output)
+                .unwrap()
+                .into_iter()
+                .map(|e| (e.path, e.kind, e.size))
+                .collect();
+            assert_eq!(
+                listed,
+                [
+                    ("sub".to_string(), "dir".to_string(), 0),
+                    ("sub/a.txt".to_string(), "file".to_string(), 3),
+                    ("b.txt".to_string(), "file".to_string(), 2),
+                ],
+                "{compression}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_create_zip_compression_mode() {
+        let dir = test_dir("compression-mode");
+        let input = dir.join("data.txt");
+        fs::write(&input, "squeeze me ".repeat(200)).unwrap();
+
+        for (mode, expected) in [
+            ("store", CompressionMethod::Stored),
+            ("deflate", CompressionMethod::Deflated),
+            ("bzip2", CompressionMethod::Bzip2),
+            ("zstd", CompressionMethod::Zstd),
+            ("balanced", CompressionMethod::Deflated),
+        ] {
+            let output = dir.join(format!("{mode}.zip"));
+            write_zip_archive(
+                CreateZipArgs {
+                    compression_mode: mode.into(),
+                    strict_validate: Some(true),
+                    ..test_zip_args(&output, &[&input])
+                },
+                None,
+            )
+            .unwrap();
+
+            let mut archive = ZipArchive::new(File::open(&output).unwrap()).unwrap();
+            assert_eq!(
+                archive.by_name("data.txt").unwrap().compression(),
+                expected,
+                "{mode}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_zip_compression_level_clamped() {
+        let level = |method, level| ZipCompression { method, level }.level();
+        assert_eq!(level(CompressionMethod::Deflated, Some(9)), Some(9));
+        assert_eq!(level(CompressionMethod::Deflated, Some(42)), Some(9));
+        assert_eq!(level(CompressionMethod::Bzip2, Some(0)), Some(1));
+        assert_eq!(level(CompressionMethod::Zstd, Some(-5)), Some(1));
+        assert_eq!(level(CompressionMethod::Stored, Some(9)), None);
+        assert_eq!(level(CompressionMethod::Deflated, None), None);
+
+        let dir = test_dir("compression-level");
+        let input = dir.join("data.txt");
+        fs::write(&input, "abcdefgh".repeat(4096)).unwrap();
+        let sizes: Vec<u64> = [Some(0), Some(99)]
+            .into_iter()
+            .map(|compression_level| {
+                let output = dir.join(format!("{compression_level:?}.zip"));
+                write_zip_archive(
+                    CreateZipArgs {
+                        compression_level,
+                        strict_validate: Some(true),
+                        ..test_zip_args(&output, &[&input])
+                    },
+                    None,
+                )
+                .unwrap();
+                fs::metadata(&output).unwrap().len()
+            })
+            .collect();
+        assert!(sizes[1] < sizes[0], "{sizes:?}");
+    }
+
+    #[test]
+    fn test_parallel_compression_matches_sequential() {
+        let dir = test_dir("parallel-compress");
+        let input = dir.join("input");
+        for i in 0..150 {
+            let sub = input.join(format!("d{}", i % 7));
+            fs::create_dir_all(&sub).unwrap();
+            fs::write(
+                sub.join(format!("f{i}.txt")),
+                format!("file {i} ").repeat(i + 1),
+            )
+            .unwrap();
+        }
+        fs::create_dir_all(input.join("empty")).unwrap();
+
+        let contents = |parallel_compression: bool| {
+            let output = dir.join(format!("{parallel_compression}.zip"));
+            write_zip_archive(
+                CreateZipArgs {
+                    parallel_compression,
+                    strict_validate: Some(true),
+                    ..test_zip_args(&output, &[&input])
+                },
+                None,
+            )
+            .unwrap();
+            let mut archive = ZipArchive::new(File::open(&output).unwrap()).unwrap();
+            (0..archive.len())
+                .map(|i| {
+                    let mut entry = archive.by_index(i).unwrap();
+                    let mut data = Vec::new();
+                    entry.read_to_end(&mut data).unwrap();
+                    (entry.name().to_string(), entry.compression(), data)
+                })
+                .collect::<Vec<_>>()
+        };
+        let sequential = contents(false);
+        assert_eq!(sequential.len(), 158);
+        assert_eq!(contents(true), sequential);
+    }
+
+    #[test]
+    fn test_parallel_batches_bounded_by_bytes() {
+        let dir = test_dir("parallel-batches");
+        let file = |name: &str, size: usize| {
+            let path = dir.join(name);
+            fs::write(&path, vec![b'x'; size]).unwrap();
+            ZipInput::File(path, name.into())
+        };
+        let plan = vec![
+            ZipInput::Dir("d/".into()),
+            file("a", 40),
+            file("b", 40),
+            file("big", 500),
+            file("c", 10),
+        ];
+
+        assert_eq!(parallel_batch_len(&plan, 100), 3);
+        assert_eq!(parallel_batch_len(&plan[3..], 100), 0);
+        assert_eq!(parallel_batch_len(&plan[4..], 100), 1);
+        assert_eq!(parallel_batch_len(&plan, u64::MAX), plan.len());
+        let many: Vec<_> = (0..100).map(|i| ZipInput::Dir(format!("{i}/"))).collect();
+        assert_eq!(parallel_batch_len(&many, 100), PARALLEL_COMPRESS_BATCH);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_zip_restores_unix_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = test_dir("unix-mode");
+        let src = dir.join("modes.zip");
+        let mut writer = ZipWriter::new(File::create(&src).unwrap());
+        for (name, mode) in [("run.sh", 0o755), ("notes.txt", 0o640), ("locked", 0o444)] {
+            writer
+                .start_file(name, FileOptions::default().unix_permissions(mode))
+                .unwrap();
+            writer.write_all(b"#!/bin/sh\n").unwrap();
+        }
+        writer.finish().unwrap();
+
+        let dest = dir.join("out");
+        extract_zip(&src, &dest, &ExtractOptions::default()).unwrap();
+        let mode =
+            |name: &str| fs::metadata(dest.join(name)).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(mode("run.sh"), 0o755);
+        assert_eq!(mode("notes.txt"), 0o640);
+        assert_eq!(mode("locked"), 0o644);
+        extract_zip(&src, &dest, &ExtractOptions::default()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_tar_restores_links() {
+        let dir = test_dir("tar-links");
+        let build = |name: &str, links: &[(tar::EntryType, &str, &str)]| {
+            let path = dir.join(name);
+            let mut builder = tar::Builder::new(File::create(&path).unwrap());
+            let mut header = tar::Header::new_gnu();
+            header.set_size(7);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, "src/main.rs", &b"fn main"[..])
+                .unwrap();
+            for (kind, link, target) in links {
+                let mut header = tar::Header::new_gnu();
+                header.set_entry_type(*kind);
+                header.set_size(0);
+                header.set_mode(0o777);
+                builder.append_link(&mut header, link, target).unwrap();
+            }
+            builder.finish().unwrap();
+            path
+        };
+
+        let good = build(
+            "good.tar",
+            &[
+                (tar::EntryType::Symlink, "src/alias.rs", "main.rs"),
+                (tar::EntryType::Link, "copy.rs", "src/main.rs"),
+            ],
+        );
+        let dest = dir.join("good");
+        let archive = open_tar_reader(&good, "tar").unwrap();
+        let summary = extract_tar_like(archive, &dest, &ExtractOptions::default()).unwrap();
+        assert_eq!(summary.extracted, 3);
+        assert_eq!(
+            fs::read_link(dest.join("src/alias.rs")).unwrap(),
+            Path::new("main.rs")
+        );
+        assert_eq!(fs::read(dest.join("src/alias.rs")).unwrap(), b"fn main");
+        assert_eq!(fs::read(dest.join("copy.rs")).unwrap(), b"fn main");
+
+        for (kind, target) in [
+            (tar::EntryType::Symlink, "../../outside"),
+            (tar::EntryType::Link, "../outside"),
+        ] {
+            let evil = build("evil.tar", &[(kind, "src/evil", target)]);
+            let archive = open_tar_reader(&evil, "tar").unwrap();
+            let err = extract_tar_like(archive, &dir.join("evil"), &ExtractOptions::default())
+                .unwrap_err();
+            assert!(err.message().contains("escapes destination"), "{err}");
+            assert!(dir.join("evil/src/evil").symlink_metadata().is_err());
+        }
+    }
+
+    #[test]
+    fn test_single_compressed_file() {
+        let dir = test_dir("single-gz");
+        let log = b"line one\nline two\n".repeat(50);
+        let gz = dir.join("server.log.gz");
+        let mut encoder =
+            flate2::write::GzEncoder::new(File::create(&gz).unwrap(), Default::default());
+        encoder.write_all(&log).unwrap();
+        encoder.finish().unwrap();
+        fs::copy(&gz, dir.join("rotated")).unwrap();
+        let xz = dir.join("notes.xz");
+        let mut encoder = xz2::write::XzEncoder::new(File::create(&xz).unwrap(), 6);
+        encoder.write_all(b"xz text").unwrap();
+        encoder.finish().unwrap();
+        let zst = dir.join("notes.txt.zst");
+        fs::write(&zst, zstd::encode_all(&b"zstd text"[..], 0).unwrap()).unwrap();
+
+        assert_eq!(archive_type(&gz), "gz");
+        assert_eq!(archive_type(&dir.join("rotated")), "gz");
+        let entries = list_archive(&gz).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            (entries[0].name.as_str(), entries[0].size),
+            ("server.log", log.len() as u64)
+        );
+
+        let dest = dir.join("out");
+        let summary = extract_compressed(&gz, "gz", &dest, &ExtractOptions::default()).unwrap();
+        assert_eq!(summary.extracted, 1);
+        assert_eq!(fs::read(dest.join("server.log")).unwrap(), log);
+        extract_compressed(&xz, "xz", &dest, &ExtractOptions::default()).unwrap();
+        assert_eq!(fs::read(dest.join("notes")).unwrap(), b"xz text");
+        assert_eq!(archive_type(&zst), "zst");
+        assert_eq!(list_archive(&zst).unwrap()[0].size, 9);
+        extract_compressed(&zst, "zst", &dest, &ExtractOptions::default()).unwrap();
+        assert_eq!(fs::read(dest.join("notes.txt")).unwrap(), b"zstd text");
+    }
+
+    #[test]
+    fn test_extract_tar_entry_to_temp() {
+        let dir = test_dir("tar-temp");
+        let src = dir.join("src.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&src).unwrap(),
+            Default::default(),
+        ));
+        for (name, data) in [("docs/a.txt", "alpha"), ("docs/b.txt", "beta")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, data.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let temp = dir.join("temp");
+        let extract = |entry: &str| {
+            extract_archive_entry_to_temp_cached(
+                &ArchiveCache::default(),
+                src.to_string_lossy().into(),
+                entry.into(),
+                Some(temp.to_string_lossy().into()),
+            )
+        };
+        let out = PathBuf::from(extract("docs/b.txt").unwrap());
+        assert!(out.starts_with(&temp));
+        assert!(out.ends_with("docs/b.txt"));
+        assert_eq!(fs::read_to_string(&out).unwrap(), "beta");
+        let again = PathBuf::from(extract("docs/b.txt").unwrap());
+        assert_ne!(out, again);
+        assert_eq!(fs::read_to_string(&out).unwrap(), "beta");
+        assert!(extract("docs/missing.txt")
+            .unwrap_err()
+            .message()
+            .contains("not found"));
+    }
+
+    #[test]
+    fn test_preview_normalizes_entry_path() {
+        let dir = test_dir("preview-lookup");
+        let src = dir.join("src.zip");
+        write_test_zip(
+            &src,
+            &[
+                ("./docs/", b""),
+                ("./docs/a.txt", b"alpha"),
+                ("loose/b.txt", b"beta"),
+            ],
+        );
+
+        let preview = |entry: &str| {
+            preview_archive_entry_cached(
+                &ArchiveCache::default(),
+                src.to_string_lossy().into(),
+                entry.into(),
+                None,
+                None,
+            )
+        };
+        for entry in ["docs/a.txt", "./docs/a.txt", "docs/a.txt/"] {
+            assert_eq!(preview(entry).unwrap().text.as_deref(), Some("alpha"));
+        }
+        for entry in ["docs", "./docs/", "loose", "loose/"] {
+            assert_eq!(
+                preview(entry).unwrap_err().message(),
+                CANNOT_PREVIEW_DIRECTORY
+            );
+        }
+        assert!(preview("docs/missing.txt")
+            .unwrap_err()
+            .message()
+            .starts_with("Entry not found"));
+    }
+
+    #[test]
+    fn test_preview_reads_whole_deflated_entry() {
+        let dir = test_dir("preview-deflate");
+        let src = dir.join("src.zip");
+        let text: String = (0..20_000).map(|i| format!("line {i}\n")).collect();
+        write_test_zip(&src, &[("big.txt", text.as_bytes())]);
+
+        let preview = preview_archive_entry_cached(
+            &ArchiveCache::default(),
+            src.to_string_lossy().into(),
+            "big.txt".into(),
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(preview.size, text.len() as u64);
+        assert_eq!(preview.text.as_deref(), Some(text.as_str()));
+
+        // 500 KB falls inside a three-byte character; the cut moves back to the one before.
+        let wide = "文字".repeat(100_000);
+        write_test_zip(&src, &[("wide.txt", wide.as_bytes())]);
+        let preview = preview_archive_entry_cached(
+            &ArchiveCache::default(),
+            src.to_string_lossy().into(),
+            "wide.txt".into(),
+            None,
+            None,
+        )
+        .unwrap();
+        let shown = preview.text.unwrap();
+        let (head, note) = shown.split_once('…').unwrap();
+        assert_eq!(head.len(), 500 * 1024 - 2);
+        assert!(wide.starts_with(head));
+        assert!(note.contains("Full file is 600000 bytes"));
+    }
+
+    #[test]
+    fn test_verify_archive_checksum() {
+        let dir = test_dir("checksum");
+        let src = dir.join("abc.bin");
+        fs::write(&src, "abc").unwrap();
+
+        let verify = |algorithm: &str, expected: Option<&str>| {
+            tauri::async_runtime::block_on(verify_archive_checksum(
+                src.to_string_lossy().into(),
+                algorithm.into(),
+                expected.map(Into::into),
+            ))
+        };
+        let md5 = verify("MD5", None).unwrap();
+        assert_eq!(md5.digest, "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(md5.matches, None);
+        assert_eq!(
+            verify("sha-1", None).unwrap().digest,
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        let sha256 = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(
+            verify("sha256", Some(&sha256.to_uppercase()))
+                .unwrap()
+                .matches,
+            Some(true)
+        );
+        assert_eq!(verify("sha256", Some("00")).unwrap().matches, Some(false));
+        assert!(verify("crc32", None).is_err());
+    }
+
+    #[test]
+    fn test_test_archive_reports_corrupt_entries() {
+        let dir = test_dir("integrity");
+        let src = dir.join("src.zip");
+        let mut writer = ZipWriter::new(File::create(&src).unwrap());
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        for (name, data) in [("good.txt", "all fine"), ("bad.txt", "corrupt me")] {
+            writer.start_file(name, stored).unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let test = || tauri::async_runtime::block_on(test_archive(src.to_string_lossy().into()));
+        let report = test().unwrap();
+        assert!(report.ok);
+        assert_eq!(report.tested, 2);
+
+        let mut bytes = fs::read(&src).unwrap();
+        let at = bytes.windows(10).position(|w| w == b"corrupt me").unwrap();
+        bytes[at] = b'C';
+        fs::write(&src, bytes).unwrap();
+        let report = test().unwrap();
+        assert!(!report.ok);
+        assert_eq!(report.tested, 2);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].name, "bad.txt");
+    }
+
+    #[test]
+    fn test_zip_listing_crc32() {
+        let dir = test_dir("crc-listing");
+        let src = dir.join("src.zip");
+        write_test_zip(&src, &[("docs/", b""), ("docs/a.txt", b"hello")]);
+        let entries = open_zip(&src, NameEncoding::default()).unwrap();
+        assert_eq!(entries[0].crc32, Some(0));
+        assert_eq!(entries[1].crc32, Some(0x3610_a686));
+
+        let tar = dir.join("src.tar");
+        let mut builder = tar::Builder::new(File::create(&tar).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "a.txt", &b"hello"[..])
+            .unwrap();
+        builder.finish().unwrap();
+        assert_eq!(list_archive(&tar).unwrap()[0].crc32, None);
+    }
+
+    #[test]
+    fn test_zip_listing_compression() {
+        let dir = test_dir("method-listing");
+        let src = dir.join("src.zip");
+        let mut writer = ZipWriter::new(File::create(&src).unwrap());
+        let text = "repeat ".repeat(1000);
+        for (name, method) in [
+            ("stored.txt", CompressionMethod::Stored),
+            ("deflated.txt", CompressionMethod::Deflated),
+        ] {
+            let options = FileOptions::default().compression_method(method);
+            writer.start_file(name, options).unwrap();
+            writer.write_all(text.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let entries = open_zip(&src, NameEncoding::default()).unwrap();
+        assert_eq!(entries[0].method.as_deref(), Some("store"));
+        assert_eq!(entries[0].compressed_size, Some(text.len() as u64));
+        assert_eq!(entries[1].method.as_deref(), Some("deflate"));
+        assert!(entries[1].compressed_size.unwrap() < text.len() as u64 / 10);
+    }
+
+    #[test]
+    fn test_rename_zip_entry() {
+        let dir = test_dir("rename-entry");
+        let src = dir.join("src.zip");
+        write_test_zip(
+            &src,
+            &[("docs/", b""), ("docs/a.txt", b"alpha"), ("b.txt", b"beta")],
+        );
+
+        let rename = |old: &str, new: &str| {
+            tauri::async_runtime::block_on(rename_zip_entry(RenameEntryArgs {
+                zip_path: src.to_string_lossy().into(),
+                old_name: old.into(),
+                new_name: new.into(),
+            }))
+        };
+        rename("b.txt", "notes/c.txt").unwrap();
+        rename("docs", "papers").unwrap();
+        let names: Vec<String> = open_zip(&src, NameEncoding::default())
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(names, ["papers/", "papers/a.txt", "notes/c.txt"]);
+
+        let mut archive = ZipArchive::new(File::open(&src).unwrap()).unwrap();
+        let mut text = String::new();
+        archive
+            .by_name("notes/c.txt")
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "beta");
+        drop(archive);
+
+        assert!(rename("missing.txt", "x.txt")
+            .unwrap_err()
+            .message()
+            .starts_with("Entry not found"));
+        assert!(rename("notes/c.txt", "../escape.txt").is_err());
+        assert!(rename("notes/c.txt", "papers/a.txt")
+            .unwrap_err()
+            .message()
+            .contains("already exists"));
+    }
+
+    #[test]
+    fn test_rewrites_preserve_existing_entries() {
+        let dir = test_dir("raw-rewrite");
+        let src = dir.join("src.zip");
+        let mtime = zip::DateTime::from_date_and_time(2001, 2, 3, 4, 5, 6).unwrap();
+        let mut writer = ZipWriter::new(File::create(&src).unwrap());
+        for name in ["photo.jpg", "drop.txt"] {
+            let options = FileOptions::default()
+                .compression_method(CompressionMethod::Stored)
+                .last_modified_time(mtime)
+                .unix_permissions(0o600);
+            writer.start_file(name, options).unwrap();
+            writer.write_all(b"already compressed").unwrap();
+        }
+        writer.finish().unwrap();
+        let added = dir.join("new.txt");
+        fs::write(&added, "fresh").unwrap();
+
+        tauri::async_runtime::block_on(add_files_to_zip(AddFilesArgs {
+            zip: src.to_string_lossy().into(),
+            files: vec![added.to_string_lossy().into()],
+            strict_validate: None,
+            replace_existing: None,
+        }))
+        .unwrap();
+        tauri::async_runtime::block_on(remove_files_from_zip(RemoveFilesArgs {
+            zip_path: src.to_string_lossy().into(),
+            entry_names: vec!["drop.txt".into()],
+        }))
+        .unwrap();
+
+        let mut archive = ZipArchive::new(File::open(&src).unwrap()).unwrap();
+        assert_eq!(archive.len(), 2);
+        let kept = archive.by_name("photo.jpg").unwrap();
+        assert_eq!(kept.compression(), CompressionMethod::Stored);
+        assert_eq!(kept.unix_mode().map(|m| m & 0o777), Some(0o600));
+        assert_eq!(zip_mtime_secs(kept.last_modified()), zip_mtime_secs(mtime));
+        drop(kept);
+        assert_eq!(
+            archive.by_name("new.txt").unwrap().compression(),
+            CompressionMethod::Deflated
+        );
+    }
+
+    #[test]
+    fn test_move_zip_entries() {
+        let dir = test_dir("move-entries");
+        let src = dir.join("src.zip");
+        write_test_zip(
+            &src,
+            &[
+                ("a.txt", b"alpha"),
+                ("b.txt", b"beta"),
+                ("old/", b""),
+                ("old/c.txt", b"gamma"),
+            ],
+        );
+
+        let pair = |old: &str, new: &str| (old.to_string(), new.to_string());
+        let result = tauri::async_runtime::block_on(move_zip_entries(MoveEntriesArgs {
+            zip_path: src.to_string_lossy().into(),
+            mapping: vec![
+                pair("a.txt", "docs/a.txt"),
+                pair("missing.txt", "docs/missing.txt"),
+                pair("b.txt", "../b.txt"),
+                pair("old", "new"),
+                pair("docs/a.txt", "docs/first.txt"),
+            ],
+        }))
+        .unwrap();
+        assert_eq!(result.moved, 3);
+        let failed: Vec<&str> = result.errors.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(failed, ["missing.txt", "b.txt"]);
+
+        let names: Vec<String> = open_zip(&src, NameEncoding::default())
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(names, ["docs/first.txt", "b.txt", "new/", "new/c.txt"]);
+    }
+
+    #[test]
+    fn test_create_zip_progress() {
+        let dir = test_dir("create-progress");
+        let input = dir.join("input");
+        fs::create_dir_all(input.join("sub")).unwrap();
+        fs::write(input.join("a.txt"), "alpha").unwrap();
+        fs::write(input.join("sub/b.txt"), "beta!!").unwrap();
+
+        for parallel_compression in [false, true] {
+            let events = Mutex::new(Vec::new());
+            let notify = |files_done, total_files, bytes_done, total_bytes| {
+                events
+                    .lock()
+                    .unwrap()
+                    .push((files_done, total_files, bytes_done, total_bytes));
+            };
+            write_zip_archive(
+                CreateZipArgs {
+                    parallel_compression,
+                    ..test_zip_args(&dir.join("out.zip"), &[&input])
+                },
+                Some(&notify),
+            )
+            .unwrap();
+            assert_eq!(
+                events.into_inner().unwrap(),
+                [(1, 2, 5, 11), (2, 2, 11, 11)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_create_zip_exclude_patterns() {
+        let dir = test_dir("exclude");
+        let input = dir.join("project");
+        fs::create_dir_all(input.join("node_modules/pkg")).unwrap();
+        fs::create_dir_all(input.join("src/node_modules")).unwrap();
+        fs::create_dir_all(input.join("build/out")).unwrap();
+        fs::write(input.join("node_modules/pkg/index.js"), "x").unwrap();
+        fs::write(input.join("src/node_modules/dep.js"), "x").unwrap();
+        fs::write(input.join("build/out/app"), "x").unwrap();
+        fs::write(input.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(input.join("src/debug.log"), "log").unwrap();
+
+        let output = dir.join("out.zip");
+        write_zip_archive(
+            CreateZipArgs {
+                exclude_patterns: vec!["node_modules".into(), "*.log".into(), "build".into()],
+                ..test_zip_args(&output, &[&input])
+            },
+            None,
+        )
+        .unwrap();
+
+        let names: Vec<String> = open_zip(&output, NameEncoding::default())
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(names, ["src/", "src/main.rs"]);
+        assert!(compile_excludes(&["[".into()]).is_err());
+    }
+
+    #[test]
+    fn test_stream_to_file_small_buffer() {
+        let dir = test_dir("stream-buffer");
+        let src = dir.join("src.zip");
+        let text: String = (0..5000).map(|i| format!("{i},")).collect();
+        write_test_zip(&src, &[("data.csv", text.as_bytes())]);
+
+        let mut archive = ZipArchive::new(File::open(&src).unwrap()).unwrap();
+        let out = dir.join("direct.csv");
+        let written = stream_to_file(&mut archive.by_index(0).unwrap(), &out, 0).unwrap();
+        assert_eq!(written, text.len() as u64);
+        assert_eq!(fs::read_to_string(&out).unwrap(), text);
+
+        let dest = dir.join("out");
+        let options = ExtractOptions {
+            stream_buffer_size: Some(7),
+            ..Default::default()
+        };
+        extract_zip(&src, &dest, &options).unwrap();
+        assert_eq!(fs::read_to_string(dest.join("data.csv")).unwrap(), text);
+        assert_eq!(options.buffer_size(), MIN_STREAM_BUFFER_SIZE);
+        let huge = ExtractOptions {
+            stream_buffer_size: Some(usize::MAX),
+            ..Default::default()
+        };
+        assert_eq!(huge.buffer_size(), MAX_STREAM_BUFFER_SIZE);
+    }
+
+    #[test]
+    fn test_extract_tar_entries_selected() {
+        let dir = test_dir("tar-select");
+        let src = dir.join("src.tar");
+        let mut builder = tar::Builder::new(File::create(&src).unwrap());
+        for (name, data) in [
+            ("./conf/app.toml", "key = 1"),
+            ("./data/big.bin", "0123456789"),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, data.as_bytes())
+                .unwrap();
+        }
+        builder.finish().unwrap();
+
+        let dest = dir.join("out");
+        let summary = tauri::async_runtime::block_on(extract_entries(
+            src.to_string_lossy().into(),
+            dest.to_string_lossy().into(),
+            vec!["conf/app.toml".into(), "conf/missing.toml".into()],
+            None,
+        ))
+        .unwrap();
+        assert_eq!(summary.extracted, 1);
+        assert_eq!(summary.not_found, ["conf/missing.toml"]);
+        assert_eq!(
+            fs::read_to_string(dest.join("conf/app.toml")).unwrap(),
+            "key = 1"
+        );
+        assert!(!dest.join("data").exists());
+    }
+
+    #[test]
+    fn test_non_utf8_entry_names() {
+        let dir = test_dir("raw-names");
+        let src = dir.join("src.zip");
+        write_test_zip(&src, &[("caf_.txt", b"x"), ("über.txt", b"y")]);
+        let bytes = fs::read(&src).unwrap();
+        let mut patched = Vec::new();
+        let mut rest = &bytes[..];
+        while let Some(at) = rest.windows(4).position(|w| w == b"caf_") {
+            patched.extend_from_slice(&rest[..at]);
+            patched.extend_from_slice(b"caf\xe9");
+            rest = &rest[at + 4..];
+        }
+        patched.extend_from_slice(rest);
+        fs::write(&src, patched).unwrap();
+
+        let entries = open_zip(&src, NameEncoding::Cp437).unwrap();
+        assert_eq!(entries[0].path, "cafΘ.txt");
+        assert!(entries[0].name_lossy);
+        assert_eq!(entries[1].path, "über.txt");
+        assert!(!entries[1].name_lossy);
+        let entries = open_zip(&src, NameEncoding::Latin1).unwrap();
+        assert_eq!(entries[0].path, "café.txt");
+
+        let tar = dir.join("src.tar");
+        let mut builder = tar::Builder::new(File::create(&tar).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.as_gnu_mut().unwrap().name[..12].copy_from_slice(b"dir/caf\xe9.txt");
+        header.set_size(1);
+        header.set_cksum();
+        builder.append(&header, &b"x"[..]).unwrap();
+        builder.finish().unwrap();
+        let entries = open_archive_cached(
+            &ArchiveCache::default(),
+            tar.to_string_lossy().into(),
+            None,
+            Some("latin1".into()),
+        )
+        .unwrap();
+        assert_eq!(entries[0].path, "dir/café.txt");
+        assert_eq!(entries[0].name, "café.txt");
+        assert!(entries[0].name_lossy);
+
+        // Lookups by the listed name (default CP437 fallback) find the tar entry.
+        let cache = ArchiveCache::default();
+        let listed = "dir/cafΘ.txt";
+        assert_eq!(read_archive_entry(&cache, &tar, listed).unwrap(), b"x");
+        let temp = extract_archive_entry_to_temp_cached(
+            &cache,
+            tar.to_string_lossy().into(),
+            listed.into(),
+            Some(dir.join("temp").to_string_lossy().into()),
+        )
+        .unwrap();
+        assert_eq!(fs::read(temp).unwrap(), b"x");
+        let summary = extract_tar_entries(
+            open_tar_reader(&tar, "tar").unwrap(),
+            &dir.join("selected"),
+            &[listed.to_string()],
+            None,
+        )
+        .unwrap();
+        assert!(summary.not_found.is_empty());
+        assert_eq!(fs::read(dir.join("selected").join(listed)).unwrap(), b"x");
+    }
+
+    #[test]
+    fn test_unflagged_utf8_entry_names() {
+        let dir = test_dir("unflagged-utf8");
+        let src = dir.join("src.zip");
+        // An ASCII placeholder keeps the UTF-8 flag clear; then swap in the UTF-8 bytes of
+        // "é", as some tools store names.
+        write_test_zip(&src, &[("docs/cafXX.txt", b"hello"), ("b.txt", b"b")]);
+        patch_test_zip(&src, b"cafXX", "café".as_bytes());
+
+        let cache = ArchiveCache::default();
+        let entries =
+            open_archive_cached(&cache, src.to_string_lossy().into(), None, None).unwrap();
+        assert_eq!(entries[0].path, "docs/café.txt");
+        assert!(!entries[0].name_lossy);
+
+        let preview = preview_archive_entry_cached(
+            &cache,
+            src.to_string_lossy().into(),
+            entries[0].path.clone(),
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(preview.text.as_deref(), Some("hello"));
+
+        let dest = dir.join("selected");
+        let summary = extract_zip_entries(&src, &dest, &[entries[0].path.clone()], None).unwrap();
+        assert!(summary.not_found.is_empty());
+        assert_eq!(
+            fs::read_to_string(dest.join("docs/café.txt")).unwrap(),
+            "hello"
+        );
+
+        let dest = dir.join("all");
+        extract_zip(&src, &dest, &ExtractOptions::default()).unwrap();
+        assert!(dest.join("docs/café.txt").is_file());
+    }
+
+    #[test]
+    fn test_archive_total_size() {
+        let dir = test_dir("total-size");
+        let zip = dir.join("src.zip");
+        write_test_zip(
+            &zip,
+            &[("docs/", b""), ("docs/a.txt", b"alpha"), ("b.txt", b"beta")],
+        );
+        let total = |path: &Path| {
+            tauri::async_runtime::block_on(archive_total_size(path.to_string_lossy().into()))
+                .unwrap()
+        };
+        assert_eq!(total(&zip), 9);
+
+        let tar = dir.join("src.tar.gz");
+        tauri::async_runtime::block_on(create_tar_archive(CreateTarArgs {
+            output_path: tar.to_string_lossy().into(),
+            input_paths: vec![dir.join("src.zip").to_string_lossy().into()],
+            compression: "gz".into(),
+        }))
+        .unwrap();
+        assert_eq!(total(&tar), fs::metadata(&zip).unwrap().len());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_refuses_symlink_escape() {
+        let dir = test_dir("symlink-escape");
+        let outside = dir.join("outside");
+        fs::create_dir_all(&outside).unwrap();
+
+        // Zip: a symlink entry followed by a write through it.
+        let src = dir.join("evil.zip");
+        let mut writer = ZipWriter::new(File::create(&src).unwrap());
+        writer
+            .add_symlink("foo", outside.to_string_lossy(), FileOptions::default())
+            .unwrap();
+        writer
+            .start_file("foo/passwd", FileOptions::default())
+            .unwrap();
+        writer.write_all(b"owned").unwrap();
+        writer.finish().unwrap();
+        let dest = dir.join("zip-dest");
+        let options = ExtractOptions {
+            concurrency: Some(1),
+            ..Default::default()
+        };
+        assert!(extract_zip(&src, &dest, &options).is_err());
+        assert!(!outside.join("passwd").exists());
+
+        // A link already planted in the destination is not followed either.
+        std::os::unix::fs::symlink(&outside, dest.join("planted")).unwrap();
+        assert!(validate_extract_path(&dest, Path::new("planted/passwd"))
+            .unwrap_err()
+            .message()
+            .contains("symlink"));
+
+        // Tar: "esc" -> "d/up/.." looks inside dest but resolves to its parent.
+        let src = dir.join("evil.tar");
+        let mut builder = tar::Builder::new(File::create(&src).unwrap());
+        let mut dir_header = tar::Header::new_gnu();
+        dir_header.set_entry_type(tar::EntryType::Directory);
+        dir_header.set_mode(0o755);
+        dir_header.set_size(0);
+        dir_header.set_cksum();
+        builder
+            .append_data(&mut dir_header, "d/", io::empty())
+            .unwrap();
+        for (link, target) in [("d/up", ".."), ("esc", "d/up/..")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            builder.append_link(&mut header, link, target).unwrap();
+        }
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "esc/pwned", &b"owned"[..])
+            .unwrap();
+        builder.finish().unwrap();
+
+        let dest = dir.join("tar-dest");
+        let archive = TarArchive::new(File::open(&src).unwrap());
+        let err = extract_tar_like(archive, &dest, &ExtractOptions::default()).unwrap_err();
+        assert!(err.message().contains("symlink"), "{err}");
+        assert!(!dir.join("pwned").exists());
+        assert!(dest.join("esc").symlink_metadata().is_err());
+
+        // Best effort records the refusal and keeps going, but still never creates the link.
+        let dest = dir.join("tar-dest-best-effort");
+        let options = ExtractOptions {
+            best_effort: Some(true),
+            ..Default::default()
+        };
+        let summary =
+            extract_tar_like(TarArchive::new(File::open(&src).unwrap()), &dest, &options).unwrap();
+        assert!(summary.failed.iter().any(|f| f.name == "esc"));
+        // "esc/pwned" still lands, as a plain file under a real "esc" directory.
+        assert!(!dest.join("esc").is_symlink());
+        assert!(dest.join("esc/pwned").is_file());
+        assert!(!dir.join("pwned").exists());
+    }
+
+    #[test]
+    fn test_extract_overwrite_policy() {
+        let dir = test_dir("overwrite");
+        let src = dir.join("src.zip");
+        write_test_zip(&src, &[("a.txt", b"archive"), ("b.txt", b"new")]);
+
+        let run = |policy: Option<&str>| {
+            let dest = dir.join(format!("dest-{}", policy.unwrap_or("default")));
+            fs::create_dir_all(&dest).unwrap();
+            fs::write(dest.join("a.txt"), "mine").unwrap();
+            let options = ExtractOptions {
+                overwrite: policy.map(String::from),
+                ..Default::default()
+            };
+            (extract_zip(&src, &dest, &options).unwrap(), dest)
+        };
+
+        let (summary, dest) = run(None);
+        assert_eq!(summary.extracted, 2);
+        assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "archive");
+
+        let (summary, dest) = run(Some("skip"));
+        assert_eq!(summary.extracted, 1);
+        assert_eq!(summary.skipped_existing, ["a.txt"]);
+        assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "mine");
+        assert_eq!(fs::read_to_string(dest.join("b.txt")).unwrap(), "new");
+
+        let (summary, dest) = run(Some("rename"));
+        assert_eq!(summary.extracted, 2);
+        assert_eq!(summary.renamed[0].to, "a (1).txt");
+        assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "mine");
+        assert_eq!(
+            fs::read_to_string(dest.join("a (1).txt")).unwrap(),
+            "archive"
+        );
+
+        let tar = dir.join("src.tar");
+        let mut builder = tar::Builder::new(File::create(&tar).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(7);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "a.txt", &b"archive"[..])
+            .unwrap();
+        builder.finish().unwrap();
+        let options = ExtractOptions {
+            overwrite: Some("skip".into()),
+            ..Default::default()
+        };
+        let summary =
+            extract_tar_like(TarArchive::new(File::open(&tar).unwrap()), &dest, &options).unwrap();
+        assert_eq!(summary.skipped_existing, ["a.txt"]);
+        assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "mine");
+        assert!(OverwriteGuard::new(Some("clobber")).is_err());
+    }
+
+    #[test]
+    fn test_plan_extraction_touches_nothing() {
+        let dir = test_dir("dry_run");
+        let src = dir.join("src.zip");
+        write_test_zip(&src, &[("docs/a.txt", b"a"), ("../evil.txt", b"x")]);
+        let dest = dir.join("dest");
+
+        let summary = plan_extraction(&src, &dest, &ExtractOptions::default()).unwrap();
+        assert_eq!(summary.planned, [dest.join("docs/a.txt").to_string_lossy()]);
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].name, "../evil.txt");
+        assert!(summary.failed[0].error.contains("Path traversal"));
+        assert_eq!(summary.extracted, 0);
+        assert!(!dest.exists());
+
+        // The plan matches what extraction then writes, renames and skips included.
+        let src = dir.join("options.zip");
+        write_test_zip(
+            &src,
+            &[
+                ("a.txt", b"old"),
+                ("x/img.png", b"1"),
+                ("y/IMG.png", b"2"),
+                ("a.txt", b"new"),
+                ("keep.txt", b"k"),
+            ],
+        );
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(dest.join("keep.txt"), b"mine").unwrap();
+        let options = ExtractOptions {
+            dedupe_by_name: Some("last".into()),
+            flatten: Some(true),
+            case_collision_policy: Some("rename".into()),
+            overwrite: Some("rename".into()),
+            ..Default::default()
+        };
+        let plan = plan_extraction(&src, &dest, &options).unwrap();
+        assert_eq!(plan.skipped_duplicates, ["a.txt"]);
+        assert_eq!(fs::read_dir(&dest).unwrap().count(), 1);
+        let summary = extract_zip(&src, &dest, &options).unwrap();
+        let written: Vec<String> = summary
+            .written
+            .iter()
+            .map(|file| file.path.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(plan.planned, written);
+        assert_eq!(plan.renamed.len(), summary.renamed.len());
+        assert!(plan
+            .planned
+            .contains(&dest.join("keep (1).txt").to_string_lossy().into()));
+
+        let limited = ExtractOptions {
+            max_entries: Some(2),
+            ..Default::default()
+        };
+        let err = plan_extraction(&src, &dest, &limited).unwrap_err();
+        assert!(err.message().starts_with(EXTRACTION_LIMIT_ERROR), "{err}");
+    }
+
+    #[test]
+    fn test_capsule_error_codes() {
+        let dir = test_dir("errors");
+        let missing = dir.join("missing.zip").to_string_lossy().into_owned();
+        let err = open_archive_cached(&ArchiveCache::default(), missing, None, None).unwrap_err();
+        assert_eq!(err.code(), "notFound");
+
+        let junk = dir.join("junk.zip");
+        fs::write(&junk, b"not a zip").unwrap();
+        let err = open_archive_cached(
+            &ArchiveCache::default(),
+            junk.to_string_lossy().into(),
+            None,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(err.code(), "invalidArchive");
+
+        let traversal = validate_extract_path(&dir, Path::new("../x")).unwrap_err();
+        assert_eq!(traversal.code(), "traversal");
+
+        // A missing parent while writing is an I/O failure, not a missing input, even
+        // though the OS message is the same.
+        let err = fs::create_dir(dir.join("no/such/dir"))
+            .map_err(|e| CapsuleError::io("Failed to create output dir", e))
+            .unwrap_err();
+        assert_eq!(err.code(), "io");
+        assert!(err.message().contains("(os error 2)"), "{err}");
+
+        // Plain messages fall back to `other`, whatever they say.
+        assert_eq!(CapsuleError::from("Archive not found").code(), "other");
+        let json =
+            serde_json::to_value(CapsuleError::Unsupported("Unsupported archive type".into()))
+                .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "code": "unsupported", "message": "Unsupported archive type" })
+        );
+    }
+
+    #[test]
+    fn test_search_archive() {
+        let dir = test_dir("search");
+        let src = dir.join("src.zip");
+        write_test_zip(
+            &src,
+            &[
+                ("docs/Readme.md", b"r"),
+                ("docs/notes.txt", b"n"),
+                ("src/main.rs", b"m"),
+            ],
+        );
+        let search = |query: &str, case_sensitive: bool| -> Vec<String> {
+            tauri::async_runtime::block_on(search_archive(
+                src.to_string_lossy().into(),
+                query.into(),
+                case_sensitive,
+            ))
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect()
+        };
+
+        assert_eq!(search("readme", false), ["docs/Readme.md"]);
+        assert!(search("readme", true).is_empty());
+        assert_eq!(search("*.txt", true), ["docs/notes.txt"]);
+        assert_eq!(search("docs/*", true), ["docs/Readme.md", "docs/notes.txt"]);
+        assert_eq!(search("MAIN.??", false), ["src/main.rs"]);
+    }
+
+    #[test]
+    fn test_archive_page() {
+        let dir = test_dir("page");
+        let src = dir.join("big.zip");
+        let names: Vec<String> = (0..10).map(|i| format!("f{i}.txt")).collect();
+        let files: Vec<(&str, &[u8])> = names.iter().map(|n| (n.as_str(), &b"x"[..])).collect();
+        write_test_zip(&src, &files);
+        let cache = ArchiveCache::default();
+
+        let page = archive_page(&cache, &src, 4, 3).unwrap();
+        assert_eq!(page.total, 10);
+        let paths: Vec<_> = page.entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["f4.txt", "f5.txt", "f6.txt"]);
+        assert_eq!(archive_page(&cache, &src, 8, 5).unwrap().entries.len(), 2);
+        assert!(archive_page(&cache, &src, 20, 5)
+            .unwrap()
+            .entries
+            .is_empty());
+
+        // A rewritten file is picked up instead of served from the cache.
+        write_test_zip(&src, &[("only.txt", b"a longer body than before")]);
+        let page = archive_page(&cache, &src, 0, 5).unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.entries[0].path, "only.txt");
+    }
+
+    #[test]
+    fn test_archive_cache_reuses_until_modified() {
+        let dir = test_dir("archive-cache");
+        let src = dir.join("src.zip");
+        write_test_zip(&src, &[("a.txt", b"alpha")]);
+        let cache = ArchiveCache::default();
+        let list = |path: &Path| {
+            open_archive_cached(&cache, path.to_string_lossy().into(), None, None).unwrap()
+        };
+
+        assert_eq!(list(&src).len(), 1);
+        // The same file by another spelling shares the cached handle.
+        assert_eq!(list(&dir.join(".").join("src.zip")).len(), 1);
+        assert_eq!(cache.0.lock().unwrap().len(), 1);
+        let preview = preview_archive_entry_cached(
+            &cache,
+            src.to_string_lossy().into(),
+            "a.txt".into(),
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(preview.text.as_deref(), Some("alpha"));
+
+        write_test_zip(&src, &[("a.txt", b"alpha"), ("b.txt", b"beta")]);
+        assert_eq!(list(&src).len(), 2);
+        assert_eq!(cache.0.lock().unwrap().len(), 1);
+
+        for i in 0..ARCHIVE_CACHE_SIZE + 2 {
+            let other = dir.join(format!("other{i}.zip"));
+            write_test_zip(&other, &[("x.txt", b"x")]);
+            list(&other);
+        }
+        assert_eq!(cache.0.lock().unwrap().len(), ARCHIVE_CACHE_SIZE);
+    }
+
+    /// A minimal RAR4 archive holding one file, `VERSION`, as written by WinRAR.
+    const VERSION_RAR: &[u8] = &[
+        0x52, 0x61, 0x72, 0x21, 0x1a, 0x07, 0x00, 0xcf, 0x90, 0x73, 0x00, 0x00, 0x0d, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x0f, 0x0c, 0x74, 0x20, 0x80, 0x27, 0x00, 0x15, 0x00, 0x00,
+        0x00, 0x0b, 0x00, 0x00, 0x00, 0x03, 0x45, 0xf3, 0x7d, 0xc6, 0xa4, 0x8a, 0x07, 0x47, 0x1d,
+        0x33, 0x07, 0x00, 0xa4, 0x81, 0x00, 0x00, 0x56, 0x45, 0x52, 0x53, 0x49, 0x4f, 0x4e, 0x0c,
+        0x00, 0x8f, 0xec, 0x8a, 0x45, 0xcc, 0x23, 0xc8, 0x48, 0x08, 0x83, 0x62, 0xfe, 0x5f, 0xdd,
+        0x5c, 0x53, 0x88, 0xf0, 0x72, 0xc4, 0x3d, 0x7b, 0x00, 0x40, 0x07, 0x00,
+    ];
+
+    #[test]
+    fn test_rar_list_and_extract() {
+        let dir = test_dir("rar");
+        let src = dir.join("version.rar");
+        fs::write(&src, VERSION_RAR).unwrap();
+        assert_eq!(detect_archive_type(&src), "rar");
+        fs::copy(&src, dir.join("renamed.bin")).unwrap();
+        assert_eq!(archive_type(&dir.join("renamed.bin")), "rar");
+
+        let entries = list_archive(&src).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            (entries[0].path.as_str(), entries[0].kind.as_str()),
+            ("VERSION", "file")
+        );
+
+        let dest = dir.join("out");
+        let summary = extract_rar(&src, &dest, &ExtractOptions::default()).unwrap();
+        assert_eq!(summary.extracted, 1);
+        let written = fs::read(dest.join("VERSION")).unwrap();
+        assert_eq!(written.len() as u64, entries[0].size);
+
+        fs::write(dir.join("junk.rar"), b"not a rar").unwrap();
+        assert!(list_archive(&dir.join("junk.rar")).is_err());
+    }
+
+    #[test]
+    fn test_extract_to_subfolder_target() {
+        let dir = test_dir("subfolder");
+        let loose = dir.join("report.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&loose).unwrap(),
+            Default::default(),
+        ));
+        for name in ["a.txt", "docs/b.txt"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(1);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, &b"x"[..]).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+        let rooted = dir.join("rooted.zip");
+        write_test_zip(
+            &rooted,
+            &[
+                ("proj/", b""),
+                ("proj/a.txt", b"a"),
+                ("proj/src/b.rs", b"b"),
+            ],
+        );
+        let single_file = dir.join("notes.zip");
+        write_test_zip(&single_file, &[("notes.txt", b"n")]);
+
+        let dest = dir.join("out");
+        assert_eq!(
+            subfolder_dest(&loose, &dest).unwrap(),
+            Some(dest.join("report"))
+        );
+        assert_eq!(subfolder_dest(&rooted, &dest).unwrap(), None);
+        assert_eq!(
+            subfolder_dest(&single_file, &dest).unwrap(),
+            Some(dest.join("notes"))
+        );
+        assert_eq!(archive_stem(Path::new("backup.TGZ")), "backup");
+        assert_eq!(archive_stem(Path::new("data.bin")), "data.bin");
+    }
+
+    #[test]
+    fn test_add_files_to_tar() {
+        let dir = test_dir("append-tar");
+        fs::write(dir.join("a.txt"), "aaa").unwrap();
+        fs::write(dir.join("b.txt"), "bb").unwrap();
+        let long = format!("{}.txt", "n".repeat(120));
+        fs::write(dir.join(&long), "long").unwrap();
+
+        for (compression, name) in [("none", "out.tar"), ("gz", "out.tar.gz")] {
+            let output = dir.join(name);
+            tauri::async_runtime::block_on(create_tar_archive(CreateTarArgs {
+                output_path: output.to_string_lossy().into(),
+                input_paths: vec![
+                    dir.join("a.txt").to_string_lossy().into(),
+                    dir.join(&long).to_string_lossy().into(),
+                ],
+                compression: compression.into(),
+            }))
+            .unwrap();
+
+            for _ in 0..2 {
+                tauri::async_runtime::block_on(add_files_to_tar(
+                    output.to_string_lossy().into(),
+                    vec![dir.join("b.txt").to_string_lossy().into()],
+                ))
+                .unwrap();
+            }
+
+            let listed: Vec<_> = list_archive(&output)
+                .unwrap()
+                .into_iter()
+                .map(|e| (e.path, e.size))
+                .collect();
+            assert_eq!(
+                listed,
+                [
+                    ("a.txt".to_string(), 3),
+                    (long.clone(), 4),
+                    ("b.txt".to_string(), 2),
+                    ("b.txt".to_string(), 2),
+                ],
+                "{compression}"
+            );
+        }
+        let leftovers = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".tmp"))
+            .count();
+        assert_eq!(leftovers, 0);
+
+        // A header whose size points past EOF must not be appended after.
+        let truncated = dir.join("truncated.tar");
+        let mut bytes = fs::read(dir.join("out.tar")).unwrap();
+        bytes.truncate(1024);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(u64::MAX / 2);
+        header.set_cksum();
+        bytes[..512].copy_from_slice(header.as_bytes());
+        fs::write(&truncated, &bytes).unwrap();
+        let err = tauri::async_runtime::block_on(add_files_to_tar(
+            truncated.to_string_lossy().into(),
+            vec![dir.join("b.txt").to_string_lossy().into()],
+        ))
+        .unwrap_err();
+        assert!(err.message().contains("past the end"), "{err}");
+        assert_eq!(fs::read(&truncated).unwrap(), bytes);
+
+        // A failure partway through the new files leaves the tar as it was.
+        #[cfg(unix)]
+        {
+            let inputs = dir.join("inputs");
+            fs::create_dir_all(&inputs).unwrap();
+            fs::write(inputs.join("c.txt"), "c".repeat(2000)).unwrap();
+            std::os::unix::fs::symlink("missing", inputs.join("dangling")).unwrap();
+            let output = dir.join("out.tar");
+            let before = fs::read(&output).unwrap();
+            assert!(tauri::async_runtime::block_on(add_files_to_tar(
+                output.to_string_lossy().into(),
+                vec![inputs.to_string_lossy().into()],
+            ))
+            .is_err());
+            assert_eq!(fs::read(&output).unwrap(), before);
+        }
+    }
+
+    #[test]
+    fn test_preview_line_range() {
+        let dir = test_dir("preview-lines");
+        let src = dir.join("src.zip");
+        let log: String = (0..50).map(|i| format!("line {i}\n")).collect();
+        write_test_zip(
+            &src,
+            &[
+                ("app.log", log.as_bytes()),
+                ("blob.bin", &[0xff, 0xfe, 0x00]),
+            ],
+        );
+        let preview = |entry: &str, lines| {
+            preview_archive_entry_cached(
+                &ArchiveCache::default(),
+                src.to_string_lossy().into(),
+                entry.into(),
+                None,
+                lines,
+            )
+            .unwrap()
+        };
+
+        let head = preview("app.log", Some((0, 2)));
+        assert_eq!(head.text.as_deref(), Some("line 0\nline 1\n"));
+        assert_eq!(head.has_more_lines, Some(true));
+        let tail = preview("app.log", Some((48, 10)));
+        assert_eq!(tail.text.as_deref(), Some("line 48\nline 49\n"));
+        assert_eq!(tail.has_more_lines, Some(false));
+        assert_eq!(preview("app.log", None).has_more_lines, None);
+
+        let binary = preview("blob.bin", Some((0, 2)));
+        assert_eq!(binary.kind, "binary");
+        assert_eq!(binary.has_more_lines, None);
+    }
+
+    #[test]
+    fn test_sniff_mime_type() {
+        let octet = || "application/octet-stream".to_string();
+        assert_eq!(
+            sniff_mime_type(octet(), b"all:\n\tcargo build\n"),
+            "text/plain"
+        );
+        assert_eq!(
+            sniff_mime_type(octet(), "caf\u{e9}".as_bytes()),
+            "text/plain"
+        );
+        // A multi-byte character cut off by the sample limit still counts as text.
+        assert_eq!(sniff_mime_type(octet(), b"ok \xc3"), "text/plain");
+        assert_eq!(sniff_mime_type(octet(), &"\u{e9}".as_bytes()[..1]), octet());
+        assert_eq!(sniff_mime_type(octet(), b"ELF\x00\x01\x02"), octet());
+        assert_eq!(sniff_mime_type(octet(), b"\x01\x02\x03\x04 abc"), octet());
+        assert_eq!(sniff_mime_type(octet(), b""), octet());
+        assert_eq!(sniff_mime_type("image/png".into(), b"text"), "image/png");
+    }
+
+    #[test]
+    fn test_extract_matching_glob() {
+        let dir = test_dir("extract-matching");
+        let src = dir.join("mixed.zip");
+        write_test_zip(
+            &src,
+            &[
+                ("logo.png", b"l"),
+                ("assets/", b""),
+                ("assets/icons/a.png", b"a"),
+                ("assets/readme.txt", b"r"),
+                ("notes.PNG.txt", b"n"),
+            ],
+        );
+
+        let mut all = matching_entry_paths(&src, "**/*.png").unwrap();
+        all.sort();
+        assert_eq!(all, vec!["assets/icons/a.png", "logo.png"]);
+        assert_eq!(
+            matching_entry_paths(&src, "*.png").unwrap(),
+            vec!["logo.png"]
+        );
+        assert!(matching_entry_paths(&src, "[").is_err());
+
+        let dest = dir.join("out");
+        let selected = matching_entry_paths(&src, "assets/**/*").unwrap();
+        let summary = extract_zip_entries(&src, &dest, &selected, None).unwrap();
+        assert_eq!(summary.extracted, 2);
+        assert!(dest.join("assets/icons/a.png").exists());
+        assert!(!dest.join("logo.png").exists());
+
+        // Links picked up by a pattern get the same checks as in a full extraction.
+        let tar_path = dir.join("links.tar");
+        let mut builder = tar::Builder::new(File::create(&tar_path).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(1);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "assets/a.png", &b"a"[..])
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Link);
+        header.set_size(0);
+        header.set_mode(0o644);
+        builder
+            .append_link(&mut header, "assets/passwd", "/etc/passwd")
+            .unwrap();
+        builder.finish().unwrap();
+        let tar_dest = dir.join("tar-out");
+        let err = tauri::async_runtime::block_on(extract_matching(
+            tar_path.to_string_lossy().into(),
+            tar_dest.to_string_lossy().into(),
+            "assets/*".into(),
+        ))
+        .unwrap_err();
+        assert_eq!(err.code(), "traversal");
+        assert!(tar_dest.join("assets/passwd").symlink_metadata().is_err());
+    }
+
+    #[test]
+    fn test_recent_files_capped_and_deduplicated() {
+        let dir = test_dir("recent-files");
+        let file = dir.join("config/recent.json");
+        assert!(read_recent_files(&file).is_empty());
+
+        for i in 0..20 {
+            record_recent_file(&file, &format!("/missing/{i}.zip")).unwrap();
+        }
+        let recent = record_recent_file(&file, "/missing/10.zip").unwrap();
+        assert_eq!(recent.len(), RECENT_FILES_LIMIT);
+        assert_eq!(recent[0], "/missing/10.zip");
+        assert_eq!(recent[1], "/missing/19.zip");
+        assert_eq!(recent.iter().filter(|p| *p == "/missing/10.zip").count(), 1);
+        assert_eq!(read_recent_files(&file), recent);
+
+        fs::write(&file, "not json").unwrap();
+        assert!(read_recent_files(&file).is_empty());
+    }
+
+    #[test]
+    fn test_zip_from_memory_entries() {
+        let dir = test_dir("zip-from-entries");
+        let entry = |name: &str, content: &str| MemoryZipEntry {
+            name: name.into(),
+            content_base64: BASE64.encode(content),
+        };
+
+        let out = dir.join("snippets.zip");
+        write_zip_from_entries(&out, &[entry("a.txt", "alpha"), entry("notes/b.md", "# b")])
+            .unwrap();
+        let mut archive = ZipArchive::new(File::open(&out).unwrap()).unwrap();
+        let mut text = String::new();
+        archive
+            .by_name("notes/b.md")
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "# b");
+
+        let bad = dir.join("bad.zip");
+        assert!(write_zip_from_entries(&bad, &[entry("../evil", "x")]).is_err());
+        assert!(write_zip_from_entries(&bad, &[entry("a", "x"), entry("a", "y")]).is_err());
+        let invalid = MemoryZipEntry {
+            name: "c".into(),
+            content_base64: "not base64!".into(),
+        };
+        assert!(write_zip_from_entries(&bad, &[invalid]).is_err());
+        assert!(!bad.exists());
+    }
+
+    #[test]
+    fn test_image_data_url() {
+        let dir = test_dir("image-data-url");
+        let png = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+        let zip = dir.join("pics.zip");
+        write_test_zip(&zip, &[("img/a.png", &png), ("notes.txt", b"hi")]);
+        let tar = dir.join("pics.tar");
+        let mut builder = tar::Builder::new(File::create(&tar).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(png.len() as u64);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "img/a.png", &png[..])
+            .unwrap();
+        builder.finish().unwrap();
+
+        let cache = ArchiveCache::default();
+        let expected = format!("data:image/png;base64,{}", BASE64.encode(png));
+        for archive in [&zip, &tar] {
+            let url = image_data_url(&cache, &archive.to_string_lossy(), "img/a.png").unwrap();
+            assert_eq!(url, expected);
+        }
+
+        let err = image_data_url(&cache, &zip.to_string_lossy(), "notes.txt").unwrap_err();
+        assert!(err.message().contains("Not an image"));
+        let err = image_data_url(&cache, &tar.to_string_lossy(), "img/b.png").unwrap_err();
+        assert_eq!(err.code(), "notFound");
+    }
+
+    #[test]
+    fn test_split_and_join_volumes() {
+        let dir = test_dir("split-volumes");
+        let source = dir.join("src");
+        fs::create_dir_all(&source).unwrap();
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i * 7919 % 251) as u8).collect();
+        fs::write(source.join("data.bin"), &data).unwrap();
+
+        let output = dir.join("out.zip");
+        let result = write_zip_archive(
+            CreateZipArgs {
+                compression_mode: "store".into(),
+                split_size_bytes: Some(8_000),
+                ..test_zip_args(&output, &[&source])
+            },
+            None,
+        )
+        .unwrap();
+        assert_eq!(result.volumes.len(), 3);
+        assert!(result.volumes[0].ends_with("out.zip.001"));
+        assert!(!output.exists());
+        let manifest = dir.join("out.zip.volumes.json");
+
+        let joined = join_volume_files(&manifest, None).unwrap();
+        assert_eq!(joined, output);
+        let entries = list_archive(&joined).unwrap();
+        assert!(entries.iter().any(|e| e.name == "data.bin"));
+
+        fs::write(&result.volumes[1], b"corrupt").unwrap();
+        let rejoined = dir.join("again.zip");
+        assert!(join_volume_files(&manifest, Some(&rejoined)).is_err());
+        assert!(!rejoined.exists());
+
+        assert!(split_into_volumes(&joined, 0).is_err());
+        assert!(split_into_volumes(&joined, u64::MAX).unwrap().is_empty());
+
+        // A volume that can't be created: the first one is cleaned up, the archive stays.
+        let blocked = dir.join("blocked.zip");
+        fs::copy(&joined, &blocked).unwrap();
+        fs::create_dir(dir.join("blocked.zip.002")).unwrap();
+        assert!(split_into_volumes(&blocked, 8_000).is_err());
+        assert!(!dir.join("blocked.zip.001").exists());
+        assert!(!dir.join("blocked.zip.volumes.json").exists());
+        assert!(blocked.is_file());
+    }
+
+    #[test]
+    fn test_diff_archives() {
+        let dir = test_dir("diff-archives");
+        let a = dir.join("a.zip");
+        let b = dir.join("b.tar");
+        write_test_zip(
+            &a,
+            &[
+                ("same.txt", b"same"),
+                ("edit.txt", b"v1"),
+                ("gone.txt", b"x"),
+            ],
+        );
+        let mut builder = tar::Builder::new(File::create(&b).unwrap());
+        for (name, data) in [("./same.txt", "same"), ("edit.txt", "v2"), ("new.txt", "n")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, data.as_bytes())
+                .unwrap();
+        }
+        builder.finish().unwrap();
+
+        let diff = tauri::async_runtime::block_on(diff_archives(
+            a.to_string_lossy().into(),
+            b.to_string_lossy().into(),
+        ))
+        .unwrap();
+        assert_eq!(diff.removed, vec!["gone.txt"]);
+        assert_eq!(diff.added, vec!["new.txt"]);
+        assert_eq!(diff.changed, vec!["edit.txt"]);
+    }
+
+    #[test]
+    fn test_count_archive_entries() {
+        let dir = test_dir("entry-count");
+        let zip = dir.join("a.zip");
+        write_test_zip(
+            &zip,
+            &[("docs/", b""), ("docs/a.txt", b"a"), ("b.txt", b"b")],
+        );
+        let tar = dir.join("a.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&tar).unwrap(),
+            Default::default(),
+        ));
+        for name in ["one", "two"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(1);
+            header.set_cksum();
+            builder.append_data(&mut header, name, &b"x"[..]).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let cache = ArchiveCache::default();
+        assert_eq!(count_archive_entries(&cache, &zip).unwrap(), 3);
+        assert_eq!(count_archive_entries(&cache, &tar).unwrap(), 2);
+        assert!(count_archive_entries(&cache, &dir.join("missing.zip")).is_err());
+    }
+
+    #[test]
+    fn test_archive_entry_paths() {
+        let dir = test_dir("entry-paths");
+        let zip = dir.join("a.zip");
+        write_test_zip(
+            &zip,
+            &[("docs/", b""), ("docs/a.txt", b"a"), ("b.txt", b"b")],
+        );
+        let gz = dir.join("notes.txt.gz");
+        let mut encoder =
+            flate2::write::GzEncoder::new(File::create(&gz).unwrap(), Default::default());
+        encoder.write_all(b"notes").unwrap();
+        encoder.finish().unwrap();
+
+        let cache = ArchiveCache::default();
+        assert_eq!(
+            archive_entry_paths(&cache, &zip).unwrap(),
+            ["docs/", "docs/a.txt", "b.txt"]
+        );
+        assert_eq!(archive_entry_paths(&cache, &gz).unwrap(), ["notes.txt"]);
+    }
+
+    #[test]
+    fn test_extract_entry_to_file() {
+        let dir = test_dir("single-entry");
+        let zip = dir.join("a.zip");
+        write_test_zip(&zip, &[("docs/report.txt", b"quarterly"), ("docs/", b"")]);
+        let rar = dir.join("version.rar");
+        fs::write(&rar, VERSION_RAR).unwrap();
+
+        let cache = ArchiveCache::default();
+        let dest = dir.join("saved/renamed.txt");
+        let written = extract_entry_to_file(&cache, &zip, "docs/report.txt", &dest).unwrap();
+        assert_eq!(written, 9);
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "quarterly");
+
+        let from_rar = dir.join("VERSION.copy");
+        extract_entry_to_file(&cache, &rar, "VERSION", &from_rar).unwrap();
+        assert_eq!(
+            fs::metadata(&from_rar).unwrap().len(),
+            list_archive(&rar).unwrap()[0].size
+        );
+
+        assert!(extract_entry_to_file(&cache, &zip, "missing.txt", &dir.join("x")).is_err());
+        assert!(extract_entry_to_file(&cache, &zip, "docs/report.txt", &dir).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_tar_preservation_options() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = test_dir("tar-preserve");
+        let src = dir.join("backup.tar");
+        let mut builder = tar::Builder::new(File::create(&src).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(2);
+        header.set_mode(0o2750);
+        header.set_mtime(1_000_000_000);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "tool", &b"hi"[..])
+            .unwrap();
+        builder.finish().unwrap();
+
+        let extract = |name: &str, options: ExtractOptions| {
+            let dest = dir.join(name);
+            extract_tar_like(TarArchive::new(File::open(&src).unwrap()), &dest, &options).unwrap();
+            fs::metadata(dest.join("tool")).unwrap()
+        };
+        let mtime = |meta: &fs::Metadata| {
+            meta.modified()
+                .unwrap()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+        };
+
+        let default = extract("default", ExtractOptions::default());
+        assert_eq!(default.permissions().mode() & 0o7000, 0);
+        assert_eq!(mtime(&default), 1_000_000_000);
+
+        let faithful = extract(
+            "faithful",
+            ExtractOptions {
+                preserve_permissions: Some(true),
+                preserve_mtime: Some(false),
+                ..Default::default()
+            },
+        );
+        assert_eq!(faithful.permissions().mode() & 0o7777, 0o2750);
+        assert_ne!(mtime(&faithful), 1_000_000_000);
+    }
+
+    #[test]
+    fn test_extract_flatten() {
+        let dir = test_dir("flatten");
+        let src = dir.join("nested.zip");
+        write_test_zip(
+            &src,
+            &[
+                ("a/", b""),
+                ("a/img.png", b"1"),
+                ("b/c/img.png", b"2"),
+                ("b/c/IMG.png", b"3"),
+                ("top.txt", b"t"),
+            ],
+        );
+        let options = ExtractOptions {
+            flatten: Some(true),
+            concurrency: Some(1),
+            ..Default::default()
+        };
+
+        let dest = dir.join("zip-out");
+        let summary = extract_zip(&src, &dest, &options).unwrap();
+        assert_eq!(summary.extracted, 4);
+        assert_eq!(fs::read_to_string(dest.join("img.png")).unwrap(), "1");
+        assert_eq!(fs::read_to_string(dest.join("img (1).png")).unwrap(), "2");
+        assert_eq!(fs::read_to_string(dest.join("IMG (2).png")).unwrap(), "3");
+        assert!(!dest.join("a").exists() && !dest.join("b").exists());
+        assert_eq!(summary.renamed.len(), 2);
+        // `onComplete: verify` checks the flattened files, not the nested stored paths.
+        let result = run_post_extract_action(PostExtractAction::Verify, &dest, &summary).unwrap();
+        let PostExtractResult::Verify {
+            checked,
+            mismatched,
+        } = result
+        else {
+            panic!("expected verify result");
+        };
+        assert_eq!(checked, 4);
+        assert!(mismatched.is_empty());
+        fs::write(dest.join("img (1).png"), b"changed").unwrap();
+        let (_, mismatched) = verify_extracted(&summary.written);
+        assert_eq!(mismatched, vec!["b/c/img.png".to_string()]);
+
+        let tar = dir.join("nested.tar");
+        let mut builder = tar::Builder::new(File::create(&tar).unwrap());
+        for name in ["x/one.txt", "y/one.txt"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(1);
+            header.set_cksum();
+            builder.append_data(&mut header, name, &b"z"[..]).unwrap();
+        }
+        builder.finish().unwrap();
+        let dest = dir.join("tar-out");
+        let summary =
+            extract_tar_like(TarArchive::new(File::open(&tar).unwrap()), &dest, &options).unwrap();
+        assert!(dest.join("one.txt").is_file() && dest.join("one (1).txt").is_file());
+        assert!(!dest.join("x").exists());
+        assert_eq!(verify_extracted(&summary.written), (2, Vec::new()));
+    }
+
+    #[test]
+    fn test_entry_text() {
+        let dir = test_dir("entry-text");
+        let src = dir.join("src.zip");
+        let big = "é".repeat(400_000);
+        write_test_zip(
+            &src,
+            &[
+                ("big.txt", big.as_bytes()),
+                ("blob.bin", &[0x66, 0xff, 0x00]),
+            ],
+        );
+        let cache = ArchiveCache::default();
+
+        let full = entry_text(&cache, &src, "big.txt", None).unwrap();
+        assert_eq!(full.text.len(), big.len());
+        assert!(!full.truncated);
+
+        // 5 bytes is two "é" and half of a third.
+        let head = entry_text(&cache, &src, "big.txt", Some(5)).unwrap();
+        assert_eq!(head.text, "éé");
+        assert!(head.truncated);
+
+        let err = entry_text(&cache, &src, "blob.bin", None).unwrap_err();
+        assert_eq!(err.code(), "notText");
+    }
+
+    #[test]
+    fn test_stream_text_chunks() {
+        let text = "ab€cd€€e".repeat(3);
+        let mut chunks = Vec::new();
+        let (count, bytes) = stream_text_chunks(&mut text.as_bytes(), 5, &mut |i, chunk| {
+            chunks.push((i, chunk))
+        })
+        .unwrap();
+        assert_eq!(count, chunks.len());
+        assert_eq!(bytes, text.len() as u64);
+        assert!(chunks.iter().enumerate().all(|(i, (index, _))| i == *index));
+        let joined: String = chunks.into_iter().map(|(_, chunk)| chunk).collect();
+        assert_eq!(joined, text);
+
+        let mut bad: &[u8] = b"fine\xe2\x82";
+        let err = stream_text_chunks(&mut bad, 4, &mut |_, _| {}).unwrap_err();
+        assert_eq!(err.code(), "notText");
+    }
+
+    #[test]
+    fn test_move_archive_file() {
+        let dir = test_dir("rename-archive");
+        let old = dir.join("old.zip");
+        write_test_zip(&old, &[("a.txt", b"a")]);
+        fs::write(index_path(&old), b"{}").unwrap();
+        let taken = dir.join("taken.zip");
+        fs::write(&taken, b"keep").unwrap();
+
+        let new = dir.join("sub/new.zip");
+        move_archive_file(&old, &new, false).unwrap();
+        assert!(!old.exists() && new.is_file());
+        assert!(index_path(&new).is_file() && !index_path(&old).exists());
+
+        let err = move_archive_file(&new, &taken, false).unwrap_err();
+        assert!(err.message().contains("already exists"));
+        assert_eq!(fs::read(&taken).unwrap(), b"keep");
+        move_archive_file(&new, &dir.join("sub/../sub/new.zip"), false).unwrap();
+        assert!(new.is_file());
+        move_archive_file(&new, &taken, true).unwrap();
+        assert_eq!(list_archive(&taken).unwrap().len(), 1);
+
+        assert!(move_archive_file(&old, &new, false).is_err());
+    }
+
+    #[test]
+    fn test_add_files_to_zip_name_collision() {
+        let dir = test_dir("add-collision");
+        let src = dir.join("src.zip");
+        write_test_zip(&src, &[("a.txt", b"old"), ("b.txt", b"b")]);
+        let added = dir.join("a.txt");
+        fs::write(&added, "new").unwrap();
+        let add = |replace_existing| {
+            tauri::async_runtime::block_on(add_files_to_zip(AddFilesArgs {
+                zip: src.to_string_lossy().into(),
+                files: vec![added.to_string_lossy().into()],
+                strict_validate: None,
+                replace_existing,
+            }))
+        };
+
+        let err = add(None).unwrap_err();
+        assert!(err.message().contains("a.txt"));
+        assert_eq!(
+            zip_entry_names(&mut ZipArchive::new(File::open(&src).unwrap()).unwrap())
+                .unwrap()
+                .len(),
+            2
+        );
+
+        add(Some(true)).unwrap();
+        let mut archive = ZipArchive::new(File::open(&src).unwrap()).unwrap();
+        let mut names = zip_entry_names(&mut archive).unwrap();
+        names.sort();
+        assert_eq!(names, vec!["a.txt", "b.txt"]);
+        let mut text = String::new();
+        archive
+            .by_name("a.txt")
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "new");
+    }
+
+    #[test]
+    fn test_zip_rewrites_keep_names_and_refuse_encryption() {
+        let dir = test_dir("rewrite-raw");
+        let src = dir.join("src.zip");
+        write_test_zip(&src, &[("docs/cafXX.txt", b"hello"), ("b.txt", b"b")]);
+        patch_test_zip(&src, b"cafXX", "café".as_bytes());
+        let added = dir.join("new.txt");
+        fs::write(&added, "new").unwrap();
+        let add = |zip: &Path| {
+            tauri::async_runtime::block_on(add_files_to_zip(AddFilesArgs {
+                zip: zip.to_string_lossy().into(),
+                files: vec![added.to_string_lossy().into()],
+                strict_validate: None,
+                replace_existing: None,
+            }))
+        };
+        let remove = |zip: &Path, name: &str| {
+            tauri::async_runtime::block_on(remove_files_from_zip(RemoveFilesArgs {
+                zip_path: zip.to_string_lossy().into(),
+                entry_names: vec![name.into()],
+            }))
+        };
+        let rename = |zip: &Path, old: &str, new: &str| {
+            tauri::async_runtime::block_on(rename_zip_entry(RenameEntryArgs {
+                zip_path: zip.to_string_lossy().into(),
+                old_name: old.into(),
+                new_name: new.into(),
+            }))
+        };
+        let move_entries = |zip: &Path, old: &str, new: &str| {
+            tauri::async_runtime::block_on(move_zip_entries(MoveEntriesArgs {
+                zip_path: zip.to_string_lossy().into(),
+                mapping: vec![(old.into(), new.into())],
+            }))
+        };
+        let update = |zip: &Path, name: &str| {
+            tauri::async_runtime::block_on(update_zip_entry(UpdateEntryArgs {
+                zip_path: zip.to_string_lossy().into(),
+                entry_name: name.into(),
+                content_base64: BASE64.encode("updated"),
+            }))
+        };
+        let set_comment = |zip: &Path| {
+            tauri::async_runtime::block_on(set_zip_comment(
+                zip.to_string_lossy().into(),
+                "notes".into(),
+            ))
+        };
+
+        let commented = dir.join("commented.zip");
+        fs::copy(&src, &commented).unwrap();
+        set_comment(&commented).unwrap();
+        let updated = dir.join("updated.zip");
+        fs::copy(&src, &updated).unwrap();
+        update(&updated, "b.txt").unwrap();
+        for copy in [&commented, &updated] {
+            let mut archive = ZipArchive::new(File::open(copy).unwrap()).unwrap();
+            assert_eq!(
+                zip_entry_names(&mut archive).unwrap(),
+                ["docs/café.txt", "b.txt"]
+            );
+        }
+
+        add(&src).unwrap();
+        remove(&src, "b.txt").unwrap();
+        rename(&src, "new.txt", "docs/new.txt").unwrap();
+        assert_eq!(
+            move_entries(&src, "docs/café.txt", "papers/café.txt")
+                .unwrap()
+                .moved,
+            1
+        );
+        let mut archive = ZipArchive::new(File::open(&src).unwrap()).unwrap();
+        assert_eq!(
+            zip_entry_names(&mut archive).unwrap(),
+            ["papers/café.txt", "docs/new.txt"]
+        );
+        let mut text = String::new();
+        archive
+            .by_name("papers/café.txt")
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "hello");
+
+        let secret = dir.join("secret.txt");
+        fs::write(&secret, "top secret").unwrap();
+        let locked = dir.join("locked.zip");
+        write_zip_archive(
+            CreateZipArgs {
+                password: Some("hunter2".into()),
+                encryption_method: Some("zipcrypto".into()),
+                ..test_zip_args(&locked, &[&secret])
+            },
+            None,
+        )
+        .unwrap();
+        let original = fs::read(&locked).unwrap();
+        assert_eq!(add(&locked).unwrap_err().code(), "unsupported");
+        assert_eq!(
+            remove(&locked, "secret.txt").unwrap_err().code(),
+            "unsupported"
+        );
+        assert_eq!(
+            rename(&locked, "secret.txt", "public.txt")
+                .unwrap_err()
+                .code(),
+            "unsupported"
+        );
+        assert_eq!(
+            move_entries(&locked, "secret.txt", "public.txt")
+                .unwrap_err()
+                .code(),
+            "unsupported"
+        );
+        assert_eq!(set_comment(&locked).unwrap_err().code(), "unsupported");
+        assert_eq!(
+            update(&locked, "secret.txt").unwrap_err().code(),
+            "unsupported"
+        );
+        let transcoded = dir.join("transcoded.zip");
+        let err = tauri::async_runtime::block_on(transcode_names(
+            locked.to_string_lossy().into(),
+            transcoded.to_string_lossy().into(),
+            "cp437".into(),
+        ))
+        .unwrap_err();
+        assert_eq!(err.code(), "unsupported");
+        assert!(!transcoded.exists());
+        assert_eq!(fs::read(&locked).unwrap(), original);
+    }
+
+    #[test]
+    fn test_failed_rewrite_leaves_original_intact() {
+        let dir = test_dir("rewrite-failure");
+        let src = dir.join("src.zip");
+        let mut writer = ZipWriter::new(File::create(&src).unwrap());
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file("data.txt", stored).unwrap();
+        writer.write_all(b"payload-payload").unwrap();
+        writer.finish().unwrap();
+        // Damage the stored bytes so the rewritten copy fails strict validation.
+        let mut bytes = fs::read(&src).unwrap();
+        let at = bytes.windows(7).position(|w| w == b"payload").unwrap();
+        bytes[at] = b'X';
+        fs::write(&src, &bytes).unwrap();
+        let added = dir.join("new.txt");
+        fs::write(&added, "fresh").unwrap();
+
+        let result = tauri::async_runtime::block_on(add_files_to_zip(AddFilesArgs {
+            zip: src.to_string_lossy().into(),
+            files: vec![added.to_string_lossy().into()],
+            strict_validate: Some(true),
+            replace_existing: None,
+        }));
+        assert!(result.is_err());
+        assert_eq!(fs::read(&src).unwrap(), bytes);
+        let leftovers = fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".tmp"))
+            .count();
+        assert_eq!(leftovers, 0);
+
+        let (a, b) = (TempBeside::new(&src), TempBeside::new(&src));
+        assert_ne!(a.path, b.path);
+        assert_eq!(a.path.parent(), src.parent());
+    }
+
+    #[test]
+    fn test_set_zip_comment() {
+        let dir = test_dir("set-comment");
+        let path = dir.join("release.zip");
+        write_test_zip(&path, &[("a.txt", b"alpha"), ("b.txt", b"beta")]);
+        let path_str: String = path.to_string_lossy().into();
+        assert_eq!(
+            tauri::async_runtime::block_on(get_zip_comment(path_str.clone())).unwrap(),
+            ""
+        );
+
+        tauri::async_runtime::block_on(set_zip_comment(
+            path_str.clone(),
+            "Built from commit abc123".into(),
+        ))
+        .unwrap();
+        assert_eq!(
+            tauri::async_runtime::block_on(get_zip_comment(path_str)).unwrap(),
+            "Built from commit abc123"
+        );
+        let mut archive = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let mut text = String::new();
+        archive
+            .by_name("b.txt")
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "beta");
+        assert_eq!(archive.len(), 2);
+    }
+
+    #[test]
+    fn test_extract_bomb_limits() {
+        let dir = test_dir("bomb-limits");
+        let zeros = dir.join("zeros.zip");
+        write_test_zip(&zeros, &[("zeros.bin", &vec![0u8; 2 * 1024 * 1024])]);
+        let strict = ExtractOptions {
+            max_compression_ratio: Some(100.0),
+            ..Default::default()
+        };
+        let err = extract_zip(&zeros, &dir.join("out-ratio"), &strict).unwrap_err();
+        assert_eq!(err.code(), "limitExceeded");
+        assert!(!dir.join("out-ratio/zeros.bin").exists());
+        let relaxed = ExtractOptions {
+            max_compression_ratio: Some(f64::INFINITY),
+            ..Default::default()
+        };
+        extract_zip(&zeros, &dir.join("out-relaxed"), &relaxed).unwrap();
+
+        let small = dir.join("small.zip");
+        write_test_zip(&small, &[("a.txt", b"12345678"), ("b.txt", b"12345678")]);
+        let capped = ExtractOptions {
+            max_total_bytes: Some(10),
+            ..Default::default()
+        };
+        let err = extract_zip(&small, &dir.join("out-total"), &capped).unwrap_err();
+        assert!(err.message().starts_with(EXTRACTION_LIMIT_ERROR), "{err}");
+
+        let tar = dir.join("many.tar");
+        let mut builder = tar::Builder::new(File::create(&tar).unwrap());
+        for name in ["one", "two", "three"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(1);
+            header.set_cksum();
+            builder.append_data(&mut header, name, &b"x"[..]).unwrap();
+        }
+        builder.finish().unwrap();
+        let few = ExtractOptions {
+            max_entries: Some(2),
+            ..Default::default()
+        };
+        let archive = open_tar_reader(&tar, "tar").unwrap();
+        let err = extract_tar_like(archive, &dir.join("out-tar"), &few).unwrap_err();
+        assert!(err.message().starts_with(EXTRACTION_LIMIT_ERROR), "{err}");
+
+        let sevenz_src = dir.join("sevenz-src");
+        fs::create_dir_all(&sevenz_src).unwrap();
+        for name in ["one", "two", "three"] {
+            fs::write(sevenz_src.join(name), "x").unwrap();
+        }
+        let sevenz = dir.join("many.7z");
+        sevenz_rust::compress_to_path(&sevenz_src, &sevenz).unwrap();
+        let err = extract_7z(&sevenz, &dir.join("out-7z"), &few).unwrap_err();
+        assert!(err.message().starts_with(EXTRACTION_LIMIT_ERROR), "{err}");
+        assert!(!dir.join("out-7z").exists());
+
+        // A single compressed file has no header size; its output is capped instead.
+        let gz = dir.join("zeros.bin.gz");
+        let mut encoder =
+            flate2::write::GzEncoder::new(File::create(&gz).unwrap(), Default::default());
+        encoder.write_all(&vec![0u8; 64 * 1024]).unwrap();
+        encoder.finish().unwrap();
+        let err = extract_compressed(&gz, "gz", &dir.join("out-gz"), &capped).unwrap_err();
+        assert!(err.message().starts_with(EXTRACTION_LIMIT_ERROR), "{err}");
+        assert!(!dir.join("out-gz/zeros.bin").exists());
+    }
+
+    #[test]
+    fn test_update_zip_entry() {
+        let dir = test_dir("update-entry");
+        let path = dir.join("config.zip");
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file("config.ini", stored).unwrap();
+        writer.write_all(b"debug=false").unwrap();
+        writer
+            .start_file("readme.txt", FileOptions::default())
+            .unwrap();
+        writer.write_all(b"hello").unwrap();
+        writer.finish().unwrap();
+        let update = |entry_name: &str| {
+            tauri::async_runtime::block_on(update_zip_entry(UpdateEntryArgs {
+                zip_path: path.to_string_lossy().into(),
+                entry_name: entry_name.into(),
+                content_base64: BASE64.encode("debug=true"),
+            }))
+        };
+
+        update("config.ini").unwrap();
+        let mut archive = ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let names: Vec<String> = zip_entry_names(&mut archive).unwrap();
+        assert_eq!(names, ["config.ini", "readme.txt"]);
+        let mut entry = archive.by_name("config.ini").unwrap();
+        assert_eq!(entry.compression(), CompressionMethod::Stored);
+        let mut text = String::new();
+        entry.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "debug=true");
+        drop(entry);
+        let mut text = String::new();
+        archive
+            .by_name("readme.txt")
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "hello");
+
+        assert_eq!(update("missing.ini").unwrap_err().code(), "notFound");
+    }
+
+    #[test]
+    fn test_zip_session_round_trip() {
+        let dir = test_dir("zip-session");
+        let output = dir.join("built.zip");
+        let sessions = ZipSessions::default();
+        sessions.begin("s1".into(), output.clone()).unwrap();
+        assert!(sessions.begin("s1".into(), output.clone()).is_err());
+        sessions
+            .add_bytes("s1", "docs/a.txt", b"hello ", false)
+            .unwrap();
+        sessions
+            .add_bytes("s1", "docs/a.txt", b"world", true)
+            .unwrap();
+        sessions.add_bytes("s1", "b.txt", b"b", true).unwrap();
+        assert!(sessions.add_bytes("s1", "b.txt", b"again", true).is_err());
+        assert_eq!(
+            sessions.finish("s1").unwrap(),
+            output.to_string_lossy().to_string()
+        );
+        assert!(sessions.finish("s1").is_err());
+
+        let mut archive = ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        let mut text = String::new();
+        archive
+            .by_name("docs/a.txt")
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "hello world");
+        assert_eq!(archive.len(), 2);
+    }
+
+    #[test]
+    fn test_zip_session_abort() {
+        let dir = test_dir("zip-session-abort");
+        let output = dir.join("partial.zip");
+        let sessions = ZipSessions::default();
+        sessions.begin("s1".into(), output.clone()).unwrap();
+        sessions
+            .add_bytes("s1", "a.txt", b"partial", false)
+            .unwrap();
+        assert!(output.exists());
+        sessions.abort("s1").unwrap();
+        assert!(!output.exists());
+        assert!(sessions.abort("s1").is_err());
+        assert!(sessions.add_bytes("s1", "a.txt", b"late", true).is_err());
+    }
 }
@@ -4,42 +4,144 @@ mod commands;
 mod menu;
 
 use std::env;
-use tauri::Emitter;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, DragDropEvent, Emitter, Listener, Manager, RunEvent, WindowEvent};
 
+/// Emit "open-with://file" for every non-flag arg (after the binary path) that names an
+/// existing file. Relative paths resolve against `cwd`.
+fn emit_open_with(app: &AppHandle, args: &[String], cwd: &Path) {
+    for arg in args.iter().skip(1).filter(|arg| !arg.starts_with('-')) {
+        let path = cwd.join(arg);
+        if path.is_file() {
+            let _ = app.emit("open-with://file", path.to_string_lossy().into_owned());
+        }
+    }
+}
+
+/// Emit "open-with://file" for each dropped path when they're all archives. Mixed drops
+/// are left to the frontend, which stages them as files to add.
+fn open_dropped_archives(app: &AppHandle, paths: &[PathBuf]) {
+    let all_archives = paths
+        .iter()
+        .all(|path| commands::detect_archive_type(path) != "unknown");
+    if !all_archives {
+        return;
+    }
+    for path in paths {
+        let _ = app.emit("open-with://file", path.to_string_lossy().into_owned());
+    }
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
+        // Must come first: a second launch hands its args to the running instance and exits
+        .plugin(tauri_plugin_single_instance::init(|app, args, cwd| {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.unminimize();
+                let _ = window.set_focus();
+            }
+            emit_open_with(app, &args, Path::new(&cwd));
+        }))
         // Keep only plugins that are actually in your Cargo.toml
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_opener::init())
+        // Open incremental zip sessions (begin_zip / zip_add_bytes / finish_zip)
+        .manage(commands::ZipSessions::default())
+        // Cancel flags for running extractions (extract_archive / cancel_extraction)
+        .manage(commands::ExtractionTokens::default())
+        // Parsed zips reused across open_archive / preview / paging calls
+        .manage(commands::ArchiveCache::default())
         // Setup: menu + menu events
         .setup(|app| {
-            menu::init_menu(&app.handle())?;
-            menu::wire_menu_events(&app.handle());
-			
-			  // Handle "Open with Capsule" – first non-zero arg is the path
-      let args: Vec<String> = env::args().collect();
-      if args.len() > 1 {
-        let path = args[1].clone();
-        let _ = app.emit("open-with://file", path);
-      }
-			
+            menu::init_menu(app.handle())?;
+            menu::wire_menu_events(app.handle());
+            let handle = app.handle().clone();
+            app.listen_any("menu://quit", move |_| handle.exit(0));
+
+            // Handle "Open with Capsule"
+            let args: Vec<String> = env::args().collect();
+            let cwd = env::current_dir().unwrap_or_default();
+            emit_open_with(app.handle(), &args, &cwd);
+
             Ok(())
         })
+        // Archives dropped onto the window open like "Open with Capsule"
+        .on_window_event(|window, event| {
+            if let WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) = event {
+                open_dropped_archives(window.app_handle(), paths);
+            }
+        })
         // Commands from src-tauri/src/commands.rs
         .invoke_handler(tauri::generate_handler![
             commands::open_archive,
+            commands::get_recent_files,
+            commands::push_recent_file,
+            commands::open_archive_page,
+            commands::search_archive,
             commands::extract_archive,
+            commands::cancel_extraction,
+            commands::extract_entries,
+            commands::extract_matching,
             commands::create_zip_archive,
+            commands::join_volumes,
+            commands::create_tar_archive,
+            commands::add_files_to_tar,
             commands::add_files_to_zip,
             commands::remove_files_from_zip,
+            commands::rename_zip_entry,
+            commands::update_zip_entry,
+            commands::move_zip_entries,
             commands::copy_file,
+            commands::rename_archive,
             commands::get_file_size,
+            commands::archive_total_size,
+            commands::archive_entry_count,
+            commands::list_entry_paths,
             commands::preview_archive_entry,
+            commands::preview_image_data_url,
+            commands::read_entry_text,
+            commands::stream_entry_text,
             commands::extract_archive_entry_to_temp,
+            commands::extract_single_entry,
+            commands::extract_entry_verified,
+            commands::verify_archive_checksum,
+            commands::normalize_zip,
+            commands::begin_zip,
+            commands::zip_add_bytes,
+            commands::finish_zip,
+            commands::abort_zip,
+            commands::create_zip_from_entries,
+            commands::suggest_nested_open,
+            commands::benchmark_read,
+            commands::test_archive,
+            commands::case_collisions,
+            commands::extract_all_to_memory,
+            commands::entries_modified_after_archive,
+            commands::dump_comments,
+            commands::get_zip_comment,
+            commands::set_zip_comment,
+            commands::create_diff_archive,
+            commands::entry_offsets,
+            commands::preview_transcoded_names,
+            commands::transcode_names,
+            commands::build_index,
+            commands::clear_index,
+            commands::export_manifest,
+            commands::diff_manifests,
+            commands::diff_archives,
+            commands::register_file_associations,
+            commands::check_file_associations,
+            commands::find_archives,
+            commands::reveal_in_file_manager,
         ])
         // Run app
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app_handle, event| {
+            // Don't leave half-written archives behind from abandoned zip sessions
+            if let RunEvent::Exit = event {
+                app_handle.state::<commands::ZipSessions>().abort_all();
+            }
+        });
 }
@@ -3,19 +3,60 @@ use tauri::{
     AppHandle,
     Runtime,
     Emitter,
-    menu::{Menu, MenuBuilder, SubmenuBuilder},
+    menu::{Menu, MenuBuilder, MenuItem, MenuItemBuilder, SubmenuBuilder},
 };
 
+/// Menu item ids for recent files are this prefix followed by the path.
+const OPEN_RECENT_PREFIX: &str = "open-recent:";
+
+/// A menu item with a keyboard shortcut; it fires the same menu event as a click.
+fn shortcut_item<R: Runtime>(
+    app: &AppHandle<R>,
+    id: &str,
+    text: &str,
+    accelerator: &str,
+) -> tauri::Result<MenuItem<R>> {
+    MenuItemBuilder::with_id(id, text)
+        .accelerator(accelerator)
+        .build(app)
+}
+
 /// Build the app menu and attach it to the app.
 pub fn init_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<()> {
+    refresh_recent_menu(app, &crate::commands::stored_recent_files(app))
+}
+
+/// Rebuild the app menu with `paths` in File → Open Recent and attach it to the app.
+pub fn refresh_recent_menu<R: Runtime>(app: &AppHandle<R>, paths: &[String]) -> tauri::Result<()> {
+    // --- Open Recent submenu ---
+    let mut recent_menu = SubmenuBuilder::new(app, "Open Recent");
+    for path in paths {
+        recent_menu = recent_menu.text(format!("{OPEN_RECENT_PREFIX}{path}"), path);
+    }
+    if paths.is_empty() {
+        let none = MenuItemBuilder::with_id("open-recent-none", "No Recent Files")
+            .enabled(false)
+            .build(app)?;
+        recent_menu = recent_menu.item(&none);
+    }
+    let recent_menu = recent_menu.build()?;
+
     // --- File menu ---
+    let open = shortcut_item(app, "file-open", "Open…", "CmdOrCtrl+O")?;
+    let save = shortcut_item(app, "file-save", "Save As…", "CmdOrCtrl+Shift+S")?;
+    let extract = shortcut_item(app, "file-extract", "Extract…", "CmdOrCtrl+E")?;
+    let close_tab = shortcut_item(app, "file-close-tab", "Close Tab", "CmdOrCtrl+W")?;
     let file_menu = SubmenuBuilder::new(app, "File")
-        .text("file-open", "Open…")
-        .text("file-save", "Save As…")
-        .text("file-extract", "Extract…")
+        .item(&open)
+        .item(&recent_menu)
+        .item(&save)
+        .item(&extract)
         .separator()
-        .text("file-close-tab", "Close Tab")
-        .build()?;
+        .item(&close_tab);
+    // macOS has Quit in the app menu instead
+    #[cfg(not(target_os = "macos"))]
+    let file_menu = file_menu.separator().text("file-quit", "Quit");
+    let file_menu = file_menu.build()?;
 
     // --- Edit menu ---
     let edit_menu = SubmenuBuilder::new(app, "Edit")
@@ -29,7 +70,26 @@ pub fn init_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<()> {
         .build()?;
 
     // Top-level menubar
-    let menu: Menu<_> = MenuBuilder::new(app)
+    let menu_builder = MenuBuilder::new(app);
+
+    // --- App menu (macOS only) ---
+    #[cfg(target_os = "macos")]
+    let menu_builder = {
+        let app_menu = SubmenuBuilder::new(app, "Capsule")
+            .about(None)
+            .separator()
+            .services()
+            .separator()
+            .hide()
+            .hide_others()
+            .show_all()
+            .separator()
+            .quit()
+            .build()?;
+        menu_builder.item(&app_menu)
+    };
+
+    let menu: Menu<_> = menu_builder
         .items(&[&file_menu, &edit_menu, &help_menu])
         .build()?;
 
@@ -41,11 +101,16 @@ pub fn init_menu<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<()> {
 pub fn wire_menu_events<R: Runtime>(app: &AppHandle<R>) {
     app.on_menu_event(|app_handle, event| {
         let id = event.id().0.as_str();
+        if let Some(path) = id.strip_prefix(OPEN_RECENT_PREFIX) {
+            let _ = app_handle.emit("menu://open-recent", path);
+            return;
+        }
         let name = match id {
             "file-open" => "menu://file-open",
             "file-save" => "menu://file-save",
             "file-extract" => "menu://file-extract",
             "file-close-tab" => "menu://file-close-tab",
+            "file-quit" => "menu://quit",
             "edit-add-files" => "menu://edit-add-files",
             "edit-remove-files" => "menu://edit-remove-files",
             "help-about" => "menu://help-about",
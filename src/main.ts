@@ -24,8 +24,38 @@ type CapsuleEntry = {
   type: string;
   path: string;
   modified?: string;
+  crc32?: number;
+  compressedSize?: number;
+  method?: string;
+  nameLossy?: boolean;
 };
 
+/** Error shape every backend command rejects with. */
+type CapsuleError = {
+  code:
+    | "notFound"
+    | "invalidArchive"
+    | "unsupported"
+    | "passwordRequired"
+    | "invalidPassword"
+    | "traversal"
+    | "cancelled"
+    | "lowSpace"
+    | "limitExceeded"
+    | "notText"
+    | "io"
+    | "other";
+  message: string;
+};
+
+function errorMessage(err: unknown): string {
+  if (err instanceof Error) return err.message;
+  if (err && typeof err === "object" && "message" in err) {
+    return String((err as CapsuleError).message);
+  }
+  return String(err);
+}
+
 type CapsuleTab = {
   id: number;
   title: string;
@@ -682,7 +712,7 @@ async function updatePreviewForSelection() {
   } catch (err) {
     console.error("Preview error:", err);
     if (previewMessage) {
-      previewMessage.textContent = `Preview failed: ${err instanceof Error ? err.message : String(err)}`;
+      previewMessage.textContent = `Preview failed: ${errorMessage(err)}`;
       previewMessage.hidden = false;
     }
   }
@@ -1079,15 +1109,16 @@ function initDropzoneAndDnd() {
     const paths = payload.paths ?? [];
     if (!paths.length) return;
 
-    const isArchive = (p: string) => /\.(zip|7z|tar|gz|tgz|bz2|rar)$/i.test(p);
+    // Same extensions as detect_archive_type in commands.rs, which the native drop
+    // handler uses; a mismatch would open a file natively and also stage it for adding.
+    const isArchive = (p: string) =>
+      /\.(zip|7z|tar|gz|tgz|bz2|tbz|xz|txz|zst|tzst|rar)$/i.test(p);
 
     const archives = paths.filter(isArchive);
     const nonArchives = paths.filter((p) => !isArchive(p));
 
-    if (archives.length === 1 && nonArchives.length === 0) {
-      await openArchiveAtPath(archives[0]);
-      return;
-    }
+    // Archive-only drops are opened by the native drop handler (lib.rs)
+    if (archives.length && nonArchives.length === 0) return;
 
     let tab = getActiveTab();
     if (!tab) {
@@ -1219,6 +1250,10 @@ function initMenuListeners() {
     handleBrowse();
   }).catch(() => {});
 
+  listen<string>("menu://open-recent", (event) => {
+    if (event.payload) openArchiveAtPath(event.payload);
+  }).catch(() => {});
+
   listen("menu://file-save", () => {
     handleSaveAsZip();
   }).catch(() => {});